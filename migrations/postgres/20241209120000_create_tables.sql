@@ -0,0 +1,59 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS author (
+  id uuid           primary key       not null,
+  name text                           not null
+);
+
+CREATE TABLE IF NOT EXISTS project (
+  id uuid           primary key       not null,
+  author uuid       references author not null,
+  created bigint                      not null,
+  version integer                     not null, -- update version of this entry
+  revision smallint, -- code revision when updated
+  name text                           not null
+);
+
+CREATE TABLE IF NOT EXISTS log (
+  id uuid           primary key         not null,
+  project uuid      references project  not null,
+  author uuid       references author   not null,
+  version integer, -- update version of this entry
+  revision smallint, -- code revision when updated
+  created bigint                        not null,
+  text text                             not null
+);
+
+CREATE TABLE IF NOT EXISTS pin (
+  project uuid      references project  not null,
+  log uuid          references log      not null,
+  author uuid       references author   not null, -- who pinned the entry
+  created bigint                        not null, -- pin date, newest pins first
+  primary key (project, log)
+);
+
+CREATE TABLE IF NOT EXISTS template (
+  id uuid           primary key       not null,
+  author uuid       references author not null,
+  project uuid      references project, -- NULL for personal templates
+  created bigint                      not null,
+  version integer                     not null, -- update version of this entry
+  revision smallint, -- code revision when updated
+  name text                           not null,
+  body text                           not null
+);
+
+CREATE TABLE IF NOT EXISTS log_field (
+  log uuid          references log      not null,
+  name text                             not null,
+  value text                            not null,
+  primary key (log, name)
+);
+CREATE INDEX IF NOT EXISTS log_field_by_value ON log_field (name, value);
+
+CREATE TABLE IF NOT EXISTS project_field (
+  project uuid      references project  not null,
+  name text                             not null,
+  kind text                             not null, -- string, number, date, user or enum:a,b,c
+  required boolean                      not null,
+  primary key (project, name)
+);
@@ -0,0 +1,3 @@
+-- Add migration script here
+CREATE UNIQUE INDEX IF NOT EXISTS author_name ON author (name);
+CREATE UNIQUE INDEX IF NOT EXISTS project_name ON project (name);
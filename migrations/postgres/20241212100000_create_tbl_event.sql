@@ -0,0 +1,7 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS event (
+  seq bigserial     primary key, -- order in which events happened
+  created bigint                        not null, -- when the event was recorded
+  kind text                             not null,
+  data text                             not null -- JSON encoded event
+);
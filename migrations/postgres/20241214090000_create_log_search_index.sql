@@ -0,0 +1,3 @@
+-- Add migration script here
+-- full-text index of the log texts, maintained by postgres with the table
+CREATE INDEX IF NOT EXISTS log_search ON log USING GIN (to_tsvector('english', text));
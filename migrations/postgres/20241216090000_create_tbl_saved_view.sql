@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS saved_view (
+  id uuid           primary key       not null,
+  author uuid       references author not null,
+  project uuid      references project, -- NULL for personal views
+  created bigint                      not null,
+  version integer                     not null, -- update version of this entry
+  revision smallint, -- code revision when updated
+  name text                           not null,
+  query text                          not null,
+  unique (author, name)
+);
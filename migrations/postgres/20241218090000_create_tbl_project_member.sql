@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS project_member (
+  project uuid      references project  not null,
+  member uuid       references author   not null,
+  author uuid       references author   not null, -- who added the member
+  created bigint                        not null,
+  primary key (project, member)
+);
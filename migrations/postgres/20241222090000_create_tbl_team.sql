@@ -0,0 +1,18 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS team (
+  id uuid           primary key       not null,
+  author uuid       references author not null, -- owner of the team
+  created bigint                      not null,
+  version integer                     not null, -- update version of this entry
+  revision smallint, -- code revision when updated
+  name text                           not null unique
+);
+CREATE TABLE IF NOT EXISTS team_member (
+  team uuid         references team     not null,
+  member uuid       references author   not null,
+  role text                             not null,
+  author uuid       references author   not null, -- who added the member
+  created bigint                        not null,
+  primary key (team, member)
+);
+ALTER TABLE project ADD COLUMN team uuid references team; -- NULL for personal projects
@@ -0,0 +1,13 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS audit (
+  seq bigserial     primary key, -- order in which changes happened
+  actor uuid                            not null, -- user who made the change
+  action text                           not null,
+  target text                           not null, -- id of the changed entry
+  before_summary text, -- NULL when the target did not exist
+  after_summary text, -- NULL when the target no longer exists
+  created bigint                        not null,
+  origin text                           not null -- cli, http or library
+);
+CREATE INDEX IF NOT EXISTS audit_actor ON audit (actor);
+CREATE INDEX IF NOT EXISTS audit_target ON audit (target);
@@ -0,0 +1,7 @@
+-- Add migration script here
+-- names are unique once normalized and folded as the naming policy says,
+-- the keys of existing rows being filled when the repository opens
+ALTER TABLE author ADD COLUMN name_key text;
+ALTER TABLE project ADD COLUMN name_key text;
+CREATE UNIQUE INDEX IF NOT EXISTS author_name_key ON author (name_key);
+CREATE UNIQUE INDEX IF NOT EXISTS project_name_key ON project (name_key);
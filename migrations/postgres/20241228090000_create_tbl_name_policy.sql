@@ -0,0 +1,7 @@
+-- Add migration script here
+-- fingerprints of the naming policies the name keys were computed under,
+-- for them to be computed again when the policy changes
+CREATE TABLE IF NOT EXISTS name_policy (
+  kind text primary key not null, -- table of the names
+  fingerprint text not null
+);
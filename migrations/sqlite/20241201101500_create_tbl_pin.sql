@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS pin (
+  project blob  references project  not null,
+  log blob      references log      not null,
+  author blob   references author   not null, -- who pinned the entry
+  created integer                   not null, -- pin date, newest pins first
+  primary key (project, log)
+)
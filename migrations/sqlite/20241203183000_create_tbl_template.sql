@@ -0,0 +1,18 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS template (
+  id blob         primary key       not null,
+  author blob     references author not null,
+  project blob    references project, -- NULL for personal templates
+  created integer                   not null,
+  version integer                   not null, -- update version of this entry
+  revision integer, -- code revision when updated
+  name text                         not null,
+  body text                         not null
+);
+CREATE TABLE IF NOT EXISTS log_field (
+  log blob      references log      not null,
+  name text                         not null,
+  value text                        not null,
+  primary key (log, name)
+);
+CREATE INDEX IF NOT EXISTS log_field_by_value ON log_field (name, value);
@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS project_field (
+  project blob  references project  not null,
+  name text                         not null,
+  kind text                         not null, -- string, number, date, user or enum:a,b,c
+  required integer                  not null,
+  primary key (project, name)
+)
@@ -0,0 +1,22 @@
+-- Add migration script here
+-- full-text index of the log texts, kept in sync with the log table
+CREATE VIRTUAL TABLE IF NOT EXISTS log_search USING fts5 (
+  log UNINDEXED, -- id of the log
+  text,
+  tokenize = 'porter unicode61'
+);
+
+INSERT INTO log_search (log, text) SELECT id, text FROM log;
+
+CREATE TRIGGER IF NOT EXISTS log_search_insert AFTER INSERT ON log BEGIN
+  INSERT INTO log_search (log, text) VALUES (new.id, new.text);
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_search_update AFTER UPDATE OF text ON log BEGIN
+  DELETE FROM log_search WHERE log = old.id;
+  INSERT INTO log_search (log, text) VALUES (new.id, new.text);
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_search_delete AFTER DELETE ON log BEGIN
+  DELETE FROM log_search WHERE log = old.id;
+END;
@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS saved_view (
+  id blob         primary key       not null,
+  author blob     references author not null,
+  project blob    references project, -- NULL for personal views
+  created integer                   not null,
+  version integer                   not null, -- update version of this entry
+  revision integer, -- code revision when updated
+  name text                         not null,
+  query text                        not null,
+  unique (author, name)
+);
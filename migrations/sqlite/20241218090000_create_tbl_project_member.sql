@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS project_member (
+  project blob  references project  not null,
+  member blob   references author   not null,
+  author blob   references author   not null, -- who added the member
+  created integer                   not null,
+  primary key (project, member)
+);
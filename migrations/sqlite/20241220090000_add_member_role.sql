@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE project_member ADD COLUMN role text not null default 'writer'; -- reader, writer or maintainer
@@ -0,0 +1,18 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS team (
+  id blob         primary key       not null,
+  author blob     references author not null, -- owner of the team
+  created integer                   not null,
+  version integer                   not null, -- update version of this entry
+  revision integer, -- code revision when updated
+  name text                         not null unique
+);
+CREATE TABLE IF NOT EXISTS team_member (
+  team blob     references team     not null,
+  member blob   references author   not null,
+  role text                         not null,
+  author blob   references author   not null, -- who added the member
+  created integer                   not null,
+  primary key (team, member)
+);
+ALTER TABLE project ADD COLUMN team blob references team; -- NULL for personal projects
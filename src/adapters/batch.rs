@@ -0,0 +1,35 @@
+use std::future::Future;
+
+use futures_util::{stream, StreamExt, TryStreamExt};
+
+use crate::core::repo::{EntryStream, RepoError};
+
+/// Number of rows fetched at once by the streams of the SQL adapters
+pub(super) const STREAM_BATCH: usize = 256;
+
+/// Streams rows fetched `size` at a time. Each batch is fetched after the
+/// `key` of the last row of the previous one, so rows must be sorted by key.
+pub(super) fn batched<T, K, F, Fut>(size: usize, key: fn(&T) -> K, fetch: F) -> EntryStream<T>
+where
+    T: Send + 'static,
+    K: Copy + Send + 'static,
+    F: Fn(Option<K>, usize) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<Vec<T>, RepoError>> + Send + 'static,
+{
+    stream::try_unfold(Some(None), move |after: Option<Option<K>>| {
+        let batch = after.map(|after| fetch(after, size));
+        async move {
+            let Some(batch) = batch else {
+                return Ok(None);
+            };
+            let rows = batch.await?;
+            let next = match rows.last() {
+                Some(last) if rows.len() == size => Some(Some(key(last))),
+                _ => None,
+            };
+            Ok(Some((stream::iter(rows.into_iter().map(Ok)), next)))
+        }
+    })
+    .try_flatten()
+    .boxed()
+}
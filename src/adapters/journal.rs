@@ -0,0 +1,300 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use anyhow::{anyhow, bail, Context};
+use tracing::warn;
+
+use super::memory::InMemory;
+use crate::{
+    core::{
+        audit::{AuditEntry, AuditQuery},
+        event::Event,
+        field::FieldDefinition,
+        model::{EntryId, Log, Member, Project, ProjectId, Role, User, UserId, Username},
+        query::{LogHit, LogQuery, LogSearch, ProjectActivity, ProjectSearch},
+        repo::{
+            AddMemberRequest, AddTeamMemberRequest, AuditRepository, AuthorRepository,
+            CreateAuthorRequest, CreateLogRequest, CreateProjectRequest, CreateTeamRequest,
+            CreateTemplateRequest, EntryStream, LogRepository, PinLogRequest, ProjectRepository,
+            RepoError, TeamRepository, TemplateRepository, UnitOfWork, ViewRepository,
+        },
+        team::{Team, TeamId, TeamMember, TeamName},
+        template::Template,
+        view::View,
+    },
+    Page, Paged, ProjectName,
+};
+
+/// Repository stored in a single file, as an append-only stream of CBOR
+/// encoded records, one per commit holding its events.
+///
+/// The whole journal is replayed in memory when opened, and every record is
+/// appended and synced to the file before its events are applied.
+#[derive(Debug, Clone)]
+pub struct Journal {
+    memory: InMemory,
+}
+
+impl Journal {
+    /// Opens the journal at `path`, creating it if needed. A record cut short
+    /// by a crash is dropped with all the events of its commit, truncating
+    /// the file back to the last complete record.
+    pub fn open(path: &Path) -> anyhow::Result<Journal> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)
+            .with_context(|| format!("failed to open journal at {}", path.display()))?;
+        let mut bytes = vec![];
+        file.read_to_end(&mut bytes)
+            .with_context(|| format!("failed to read journal at {}", path.display()))?;
+
+        let mut events = vec![];
+        let mut records = serde_cbor::Deserializer::from_slice(&bytes).into_iter::<Vec<Event>>();
+        let mut complete = 0;
+        loop {
+            match records.next() {
+                None => break,
+                Some(Ok(record)) => {
+                    events.extend(record);
+                    complete = records.byte_offset();
+                }
+                Some(Err(e)) if e.is_eof() => {
+                    warn!(
+                        "dropping the last {} bytes of {}, a record was not completely written",
+                        bytes.len() - complete,
+                        path.display()
+                    );
+                    file.set_len(complete as u64)
+                        .and_then(|()| file.sync_all())
+                        .with_context(|| format!("failed to repair journal {}", path.display()))?;
+                    break;
+                }
+                Some(Err(e)) => bail!(
+                    "corrupt journal {} after byte {complete}: {e}",
+                    path.display()
+                ),
+            }
+        }
+
+        let file = Mutex::new(file);
+        let memory = InMemory::persisted(events, Box::new(move |events| append(&file, events)))
+            .map_err(|e| anyhow!("inconsistent journal {}: {e}", path.display()))?;
+        Ok(Journal { memory })
+    }
+}
+
+/// Writes the events of a commit as a single record, a CBOR array prefixed
+/// by its length, and syncs it. On failure the file is cut back to its
+/// previous length, so that no partial record is followed by new ones.
+fn append(file: &Mutex<File>, events: &[Event]) -> Result<(), RepoError> {
+    let mut record = vec![];
+    serde_cbor::to_writer(&mut record, &events)
+        .map_err(|e| RepoError::Corrupt(format!("could not encode the commit: {e}")))?;
+    let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+    let length = file
+        .metadata()
+        .map_err(|e| RepoError::Unavailable(format!("journal unavailable: {e}")))?
+        .len();
+    file.write_all(&record)
+        .and_then(|()| file.sync_data())
+        .map_err(|e| {
+            let _ = file.set_len(length);
+            RepoError::Unavailable(format!("could not write to the journal: {e}"))
+        })
+}
+
+impl AuthorRepository for Journal {
+    async fn create_author(&self, request: CreateAuthorRequest) -> Result<User, RepoError> {
+        self.memory.create_author(request).await
+    }
+    async fn get_user_by_name(&self, username: &Username) -> Result<Option<User>, RepoError> {
+        self.memory.get_user_by_name(username).await
+    }
+    async fn get_user_by_id(&self, id: UserId) -> Result<Option<User>, RepoError> {
+        self.memory.get_user_by_id(id).await
+    }
+    #[cfg(feature = "admin")]
+    async fn list_users(&self, page: Page) -> Result<Paged<User>, RepoError> {
+        self.memory.list_users(page).await
+    }
+}
+
+impl ProjectRepository for Journal {
+    async fn create_project(&self, request: CreateProjectRequest) -> Result<Project, RepoError> {
+        self.memory.create_project(request).await
+    }
+    async fn get_project_by_name(&self, name: &ProjectName) -> Result<Option<Project>, RepoError> {
+        self.memory.get_project_by_name(name).await
+    }
+    async fn get_project_by_id(&self, id: ProjectId) -> Result<Option<Project>, RepoError> {
+        self.memory.get_project_by_id(id).await
+    }
+    async fn list_user_projects(
+        &self,
+        user: UserId,
+        page: Page,
+    ) -> Result<Paged<Project>, RepoError> {
+        self.memory.list_user_projects(user, page).await
+    }
+    async fn list_project_activity(
+        &self,
+        search: &ProjectSearch,
+    ) -> Result<Vec<ProjectActivity>, RepoError> {
+        self.memory.list_project_activity(search).await
+    }
+    fn stream_user_projects(&self, user: UserId) -> EntryStream<Project> {
+        self.memory.stream_user_projects(user)
+    }
+    async fn set_project_field(&self, definition: FieldDefinition) -> Result<(), RepoError> {
+        self.memory.set_project_field(definition).await
+    }
+    async fn list_project_fields(
+        &self,
+        project: ProjectId,
+    ) -> Result<Vec<FieldDefinition>, RepoError> {
+        self.memory.list_project_fields(project).await
+    }
+    async fn add_project_member(&self, request: AddMemberRequest) -> Result<Member, RepoError> {
+        self.memory.add_project_member(request).await
+    }
+    async fn set_member_role(
+        &self,
+        project: ProjectId,
+        user: UserId,
+        role: Role,
+    ) -> Result<bool, RepoError> {
+        self.memory.set_member_role(project, user, role).await
+    }
+    async fn remove_project_member(
+        &self,
+        project: ProjectId,
+        user: UserId,
+    ) -> Result<bool, RepoError> {
+        self.memory.remove_project_member(project, user).await
+    }
+    async fn transfer_project(&self, project: ProjectId, owner: UserId) -> Result<(), RepoError> {
+        self.memory.transfer_project(project, owner).await
+    }
+    async fn list_project_members(&self, project: ProjectId) -> Result<Vec<Member>, RepoError> {
+        self.memory.list_project_members(project).await
+    }
+    async fn list_user_memberships(&self, user: UserId) -> Result<Vec<Member>, RepoError> {
+        self.memory.list_user_memberships(user).await
+    }
+}
+
+impl TeamRepository for Journal {
+    async fn create_team(&self, request: CreateTeamRequest) -> Result<Team, RepoError> {
+        self.memory.create_team(request).await
+    }
+    async fn get_team_by_name(&self, name: &TeamName) -> Result<Option<Team>, RepoError> {
+        self.memory.get_team_by_name(name).await
+    }
+    async fn get_team_by_id(&self, id: TeamId) -> Result<Option<Team>, RepoError> {
+        self.memory.get_team_by_id(id).await
+    }
+    async fn list_user_teams(&self, user: UserId) -> Result<Vec<Team>, RepoError> {
+        self.memory.list_user_teams(user).await
+    }
+    async fn add_team_member(
+        &self,
+        request: AddTeamMemberRequest,
+    ) -> Result<TeamMember, RepoError> {
+        self.memory.add_team_member(request).await
+    }
+    async fn list_team_members(&self, team: TeamId) -> Result<Vec<TeamMember>, RepoError> {
+        self.memory.list_team_members(team).await
+    }
+    async fn list_team_projects(&self, team: TeamId) -> Result<Vec<Project>, RepoError> {
+        self.memory.list_team_projects(team).await
+    }
+}
+
+impl LogRepository for Journal {
+    async fn create_log(&self, request: CreateLogRequest) -> Result<Log, RepoError> {
+        self.memory.create_log(request).await
+    }
+    async fn query_logs(&self, query: &LogQuery) -> Result<Paged<Log>, RepoError> {
+        self.memory.query_logs(query).await
+    }
+    async fn search_logs(&self, search: &LogSearch) -> Result<Paged<LogHit>, RepoError> {
+        self.memory.search_logs(search).await
+    }
+    fn stream_project_logs(&self, project: ProjectId) -> EntryStream<Log> {
+        self.memory.stream_project_logs(project)
+    }
+    async fn get_project_log(
+        &self,
+        project: ProjectId,
+        id: EntryId,
+    ) -> Result<Option<Log>, RepoError> {
+        self.memory.get_project_log(project, id).await
+    }
+    async fn pin_log(&self, request: PinLogRequest) -> Result<(), RepoError> {
+        self.memory.pin_log(request).await
+    }
+    async fn unpin_log(&self, project: ProjectId, log: EntryId) -> Result<bool, RepoError> {
+        self.memory.unpin_log(project, log).await
+    }
+    async fn list_pinned_logs(&self, project: ProjectId) -> Result<Vec<Log>, RepoError> {
+        self.memory.list_pinned_logs(project).await
+    }
+}
+
+impl TemplateRepository for Journal {
+    async fn create_template(&self, request: CreateTemplateRequest) -> Result<Template, RepoError> {
+        self.memory.create_template(request).await
+    }
+    async fn list_templates(
+        &self,
+        user: UserId,
+        project: Option<ProjectId>,
+    ) -> Result<Vec<Template>, RepoError> {
+        self.memory.list_templates(user, project).await
+    }
+}
+
+impl ViewRepository for Journal {
+    async fn save_view(&self, view: View) -> Result<(), RepoError> {
+        self.memory.save_view(view).await
+    }
+    async fn list_views(
+        &self,
+        user: UserId,
+        projects: &[ProjectId],
+    ) -> Result<Vec<View>, RepoError> {
+        self.memory.list_views(user, projects).await
+    }
+}
+
+impl AuditRepository for Journal {
+    async fn record_audit(&self, entry: AuditEntry) -> Result<(), RepoError> {
+        self.memory.record_audit(entry).await
+    }
+    async fn list_audit(&self, query: &AuditQuery) -> Result<Paged<AuditEntry>, RepoError> {
+        self.memory.list_audit(query).await
+    }
+}
+
+impl UnitOfWork for Journal {
+    async fn begin(&self) -> Result<Self, RepoError> {
+        Ok(Journal {
+            memory: self.memory.begin().await?,
+        })
+    }
+    async fn commit(self) -> Result<(), RepoError> {
+        self.memory.commit().await
+    }
+    async fn rollback(self) -> Result<(), RepoError> {
+        self.memory.rollback().await
+    }
+    fn in_transaction(&self) -> bool {
+        self.memory.in_transaction()
+    }
+}
@@ -0,0 +1,405 @@
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+use crate::core::{model::Log, query::LogHit};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "1";
+const DIM: &str = "2";
+const ITALIC: &str = "3";
+const UNDERLINE: &str = "4";
+const STRIKE: &str = "9";
+const CODE: &str = "36";
+const CODE_BLOCK: &str = "33";
+const LINK: &str = "4;34";
+
+/// How log text (written in markdown) is presented
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextFormat {
+    /// text as written by the author
+    Raw,
+    /// ANSI styled text for terminals
+    #[default]
+    Ansi,
+    /// sanitized HTML fragment
+    Html,
+}
+
+fn options() -> Options {
+    Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS | Options::ENABLE_TABLES
+}
+
+/// Renders markdown `text` to the requested format
+pub fn render(text: &str, format: TextFormat) -> String {
+    match format {
+        TextFormat::Raw => text.to_string(),
+        TextFormat::Ansi => to_ansi(text),
+        TextFormat::Html => to_html(text),
+    }
+}
+
+/// Renders markdown to HTML, then strips anything not safe to embed in a page
+/// (scripts, event handlers, `javascript:` links...)
+pub fn to_html(text: &str) -> String {
+    let mut html = String::with_capacity(text.len() * 3 / 2);
+    pulldown_cmark::html::push_html(&mut html, Parser::new_ext(text, options()));
+    ammonia::clean(&html)
+}
+
+/// Renders markdown to text decorated with ANSI escape sequences
+pub fn to_ansi(text: &str) -> String {
+    let mut writer = AnsiWriter::default();
+    for event in Parser::new_ext(text, options()) {
+        writer.event(event);
+    }
+    writer.finish()
+}
+
+/// The text without the control characters, such as escape sequences, which
+/// would let it restyle or rewrite the terminal, line breaks and tabs aside
+fn printable(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() || matches!(c, '\n' | '\t'))
+        .collect()
+}
+
+#[derive(Default)]
+struct AnsiWriter {
+    out: String,
+    /// SGR codes currently applied
+    styles: Vec<&'static str>,
+    /// `None` for bullet lists, next item number for ordered lists
+    lists: Vec<Option<u64>>,
+    /// destinations of the links being written
+    links: Vec<(String, usize)>,
+    quotes: usize,
+    in_code_block: bool,
+    at_line_start: bool,
+    need_blank_line: bool,
+}
+
+impl AnsiWriter {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => {
+                if self.in_code_block {
+                    self.code_block_text(&text)
+                } else {
+                    self.write(&text)
+                }
+            }
+            Event::Code(code) => {
+                self.push_style(CODE);
+                self.write(&code);
+                self.pop_style();
+            }
+            // left out like the sanitized HTML leaves out unsafe tags
+            Event::Html(_) | Event::InlineHtml(_) => {}
+            Event::InlineMath(math) | Event::DisplayMath(math) => self.write(&math),
+            Event::FootnoteReference(name) => self.write(&format!("[^{name}]")),
+            Event::SoftBreak => self.write(" "),
+            Event::HardBreak => self.newline(),
+            Event::Rule => {
+                self.start_block();
+                self.push_style(DIM);
+                self.write("────────────────────");
+                self.pop_style();
+                self.end_block();
+            }
+            Event::TaskListMarker(done) => self.write(if done { "[x] " } else { "[ ] " }),
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph => self.start_block(),
+            Tag::Heading { level, .. } => {
+                self.start_block();
+                self.push_style(BOLD);
+                if level == HeadingLevel::H1 {
+                    self.push_style(UNDERLINE);
+                }
+            }
+            Tag::BlockQuote(_) => {
+                self.start_block();
+                self.quotes += 1;
+            }
+            Tag::CodeBlock(kind) => {
+                self.start_block();
+                if let CodeBlockKind::Fenced(lang) = kind {
+                    if !lang.is_empty() {
+                        self.push_style(DIM);
+                        self.write(&format!("[{lang}]"));
+                        self.pop_style();
+                        self.newline();
+                    }
+                }
+                self.in_code_block = true;
+            }
+            Tag::List(first) => {
+                if self.lists.is_empty() {
+                    self.start_block();
+                }
+                self.lists.push(first);
+            }
+            Tag::Item => {
+                self.ensure_line_start();
+                self.need_blank_line = false;
+                let depth = self.lists.len().saturating_sub(1);
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.write_prefix(depth);
+                self.out.push_str(&marker);
+                self.at_line_start = false;
+            }
+            Tag::Emphasis => self.push_style(ITALIC),
+            Tag::Strong => self.push_style(BOLD),
+            Tag::Strikethrough => self.push_style(STRIKE),
+            Tag::Link { dest_url, .. } => {
+                self.links.push((dest_url.to_string(), self.out.len()));
+                self.push_style(LINK);
+            }
+            Tag::Image { dest_url, .. } => {
+                self.links.push((dest_url.to_string(), self.out.len()));
+                self.write("[image: ");
+            }
+            Tag::Table(_) | Tag::TableHead | Tag::TableRow => self.ensure_line_start(),
+            Tag::TableCell => self.write("| "),
+            Tag::FootnoteDefinition(name) => {
+                self.start_block();
+                self.write(&format!("[^{name}]: "));
+            }
+            Tag::HtmlBlock | Tag::MetadataBlock(_) => self.start_block(),
+            Tag::DefinitionList | Tag::DefinitionListTitle | Tag::DefinitionListDefinition => {
+                self.start_block()
+            }
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::HtmlBlock | TagEnd::MetadataBlock(_) => self.end_block(),
+            TagEnd::Heading(level) => {
+                if level == HeadingLevel::H1 {
+                    self.pop_style();
+                }
+                self.pop_style();
+                self.end_block();
+            }
+            TagEnd::BlockQuote(_) => {
+                self.quotes = self.quotes.saturating_sub(1);
+                self.end_block();
+            }
+            TagEnd::CodeBlock => {
+                self.in_code_block = false;
+                self.end_block();
+            }
+            TagEnd::List(_) => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.end_block();
+                }
+            }
+            TagEnd::Item => self.ensure_line_start(),
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => self.pop_style(),
+            TagEnd::Link => {
+                self.pop_style();
+                if let Some((url, start)) = self.links.pop() {
+                    // avoid repeating autolinks such as <https://example.org>
+                    if self.out[start..].contains(&url) {
+                        return;
+                    }
+                    self.push_style(DIM);
+                    self.write(&format!(" <{url}>"));
+                    self.pop_style();
+                }
+            }
+            TagEnd::Image => {
+                if let Some((url, _)) = self.links.pop() {
+                    self.write(&format!("] <{url}>"));
+                }
+            }
+            TagEnd::Table | TagEnd::TableHead => self.end_block(),
+            TagEnd::TableRow => {
+                self.write("|");
+                self.newline();
+            }
+            TagEnd::TableCell => self.write(" "),
+            TagEnd::FootnoteDefinition => self.end_block(),
+            TagEnd::DefinitionList
+            | TagEnd::DefinitionListTitle
+            | TagEnd::DefinitionListDefinition => self.end_block(),
+        }
+    }
+
+    fn code_block_text(&mut self, text: &str) {
+        for line in printable(text).lines() {
+            self.ensure_line_start();
+            self.write_prefix(self.lists.len());
+            self.out.push_str("    ");
+            self.at_line_start = false;
+            self.push_style(CODE_BLOCK);
+            self.out.push_str(line);
+            self.pop_style();
+            self.newline();
+        }
+    }
+
+    /// Writes the quote bars and list indentation of the current line
+    fn write_prefix(&mut self, list_depth: usize) {
+        for _ in 0..self.quotes {
+            self.out.push_str("│ ");
+        }
+        for _ in 0..list_depth {
+            self.out.push_str("  ");
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        for (i, line) in printable(text).split('\n').enumerate() {
+            if i > 0 {
+                self.newline();
+            }
+            if line.is_empty() {
+                continue;
+            }
+            if self.at_line_start {
+                self.write_prefix(self.lists.len());
+                self.at_line_start = false;
+            }
+            self.out.push_str(line);
+        }
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        self.at_line_start = true;
+    }
+
+    fn ensure_line_start(&mut self) {
+        if !self.out.is_empty() && !self.at_line_start {
+            self.newline();
+        }
+    }
+
+    fn start_block(&mut self) {
+        self.ensure_line_start();
+        if self.need_blank_line {
+            self.write_prefix(0);
+            self.newline();
+            self.need_blank_line = false;
+        }
+    }
+
+    fn end_block(&mut self) {
+        self.ensure_line_start();
+        self.need_blank_line = true;
+    }
+
+    fn push_style(&mut self, code: &'static str) {
+        self.styles.push(code);
+        self.out.push_str(&format!("\x1b[{code}m"));
+    }
+
+    fn pop_style(&mut self) {
+        self.styles.pop();
+        self.out.push_str(RESET);
+        for code in &self.styles {
+            self.out.push_str(&format!("\x1b[{code}m"));
+        }
+    }
+
+    fn finish(mut self) -> String {
+        if !self.styles.is_empty() {
+            self.out.push_str(RESET);
+        }
+        let len = self.out.trim_end().len();
+        self.out.truncate(len);
+        self.out
+    }
+}
+
+/// A log whose text is displayed in a given [`TextFormat`]
+#[derive(Debug, Clone)]
+pub struct Rendered {
+    log: Log,
+    format: TextFormat,
+}
+
+impl Log {
+    pub fn rendered(self, format: TextFormat) -> Rendered {
+        Rendered { log: self, format }
+    }
+}
+
+impl Rendered {
+    pub fn log(&self) -> &Log {
+        &self.log
+    }
+    pub fn text(&self) -> String {
+        render(self.log.text(), self.format)
+    }
+}
+
+impl std::fmt::Display for Rendered {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.format {
+            TextFormat::Raw => write!(f, "{}", self.log),
+            _ => {
+                writeln!(f, "Log #{} by user {}:", self.log.id(), self.log.author())?;
+                for (name, value) in self.log.fields() {
+                    writeln!(f, "\x1b[{DIM}m{name}: {value}{RESET}")?;
+                }
+                write!(f, "{}", self.text())
+            }
+        }
+    }
+}
+
+/// A search hit whose snippet is displayed in a given [`TextFormat`]
+#[derive(Debug, Clone)]
+pub struct RenderedHit {
+    hit: LogHit,
+    format: TextFormat,
+}
+
+impl LogHit {
+    pub fn rendered(self, format: TextFormat) -> RenderedHit {
+        RenderedHit { hit: self, format }
+    }
+}
+
+impl RenderedHit {
+    pub fn hit(&self) -> &LogHit {
+        &self.hit
+    }
+    /// The snippet, matches being rendered in bold
+    pub fn snippet(&self) -> String {
+        render(&self.hit.snippet, self.format)
+    }
+}
+
+impl std::fmt::Display for RenderedHit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.format {
+            TextFormat::Raw => write!(f, "{}", self.hit),
+            _ => {
+                let log = &self.hit.log;
+                writeln!(
+                    f,
+                    "Log #{} of {} by user {}:",
+                    log.id(),
+                    self.hit.project,
+                    log.author()
+                )?;
+                write!(f, "{}", self.snippet())
+            }
+        }
+    }
+}
@@ -0,0 +1,968 @@
+use std::sync::{Arc, Mutex, RwLock};
+
+use futures_util::{stream, StreamExt};
+
+use crate::{
+    core::{
+        audit::{AuditEntry, AuditQuery},
+        event::Event,
+        field::{FieldDefinition, FieldFilter, FieldOp},
+        model::{
+            Date, EntryId, Log, Member, Project, ProjectId, Role, User, UserId, Username,
+            MAX_PINNED_LOGS,
+        },
+        pagination::{page_of, Keyed, SortOrder},
+        query::{self, LogHit, LogQuery, LogSearch, ProjectActivity, ProjectSearch},
+        repo::{
+            AddMemberRequest, AddTeamMemberRequest, AuditRepository, AuthorRepository,
+            CreateAuthorRequest, CreateLogRequest, CreateProjectRequest, CreateTeamRequest,
+            CreateTemplateRequest, EntryStream, LogRepository, PinLogRequest, ProjectRepository,
+            RepoError, TeamRepository, TemplateRepository, UnitOfWork, ViewRepository,
+        },
+        team::{Team, TeamId, TeamMember, TeamName},
+        template::Template,
+        view::View,
+    },
+    Page, Paged, ProjectName,
+};
+
+#[derive(Clone)]
+struct Pin {
+    project: ProjectId,
+    log: EntryId,
+    created: Date,
+}
+
+/// Tables of the in-memory database. Rows are kept in insertion order, which
+/// is the order SQLite returns them in without an `ORDER BY`.
+#[derive(Clone, Default)]
+struct Tables {
+    authors: Vec<User>,
+    projects: Vec<Project>,
+    members: Vec<Member>,
+    teams: Vec<Team>,
+    team_members: Vec<TeamMember>,
+    logs: Vec<(ProjectId, Log)>,
+    pins: Vec<Pin>,
+    fields: Vec<FieldDefinition>,
+    templates: Vec<Template>,
+    views: Vec<View>,
+    audit: Vec<AuditEntry>,
+}
+
+impl Tables {
+    fn has_author(&self, id: UserId) -> bool {
+        self.authors.iter().any(|a| a.id() == id)
+    }
+    fn has_project(&self, id: ProjectId) -> bool {
+        self.projects.iter().any(|p| p.id() == id)
+    }
+    fn has_team(&self, id: TeamId) -> bool {
+        self.teams.iter().any(|t| t.id() == id)
+    }
+    fn is_member(&self, project: ProjectId, user: UserId) -> bool {
+        self.members
+            .iter()
+            .any(|m| m.project == project && m.user == user)
+    }
+
+    /// Enforces the uniqueness and reference constraints
+    fn check(&self, event: &Event) -> Result<(), RepoError> {
+        match event {
+            Event::UserCreated(author) => {
+                let key = author.name.key();
+                if self.authors.iter().any(|a| a.name.key() == key) {
+                    return Err(RepoError::Conflict(format!(
+                        "author {} already exists",
+                        author.name
+                    )));
+                }
+            }
+            Event::ProjectCreated(project) => {
+                if !self.has_author(project.meta.author) {
+                    return Err(RepoError::ForeignKey(format!(
+                        "unknown author {}",
+                        project.meta.author
+                    )));
+                }
+                if let Some(team) = project.team {
+                    if !self.has_team(team) {
+                        return Err(RepoError::ForeignKey(format!("unknown team {team}")));
+                    }
+                }
+                let key = project.name.key();
+                if self.projects.iter().any(|p| p.name.key() == key) {
+                    return Err(RepoError::Conflict(format!(
+                        "project {} already exists",
+                        project.name
+                    )));
+                }
+            }
+            Event::TeamCreated(team) => {
+                if !self.has_author(team.meta.author) {
+                    return Err(RepoError::ForeignKey(format!(
+                        "unknown author {}",
+                        team.meta.author
+                    )));
+                }
+                if self.teams.iter().any(|t| t.name == team.name) {
+                    return Err(RepoError::Conflict(format!(
+                        "team {} already exists",
+                        team.name
+                    )));
+                }
+            }
+            Event::TeamMemberAdded(member) => {
+                for user in [member.user, member.by] {
+                    if !self.has_author(user) {
+                        return Err(RepoError::ForeignKey(format!("unknown author {user}")));
+                    }
+                }
+                if !self.has_team(member.team) {
+                    return Err(RepoError::ForeignKey(format!(
+                        "unknown team {}",
+                        member.team
+                    )));
+                }
+                if self
+                    .team_members
+                    .iter()
+                    .any(|m| m.team == member.team && m.user == member.user)
+                {
+                    return Err(RepoError::Conflict(format!(
+                        "{} is already a member of {}",
+                        member.user, member.team
+                    )));
+                }
+            }
+            Event::MemberAdded(member) => {
+                for user in [member.user, member.by] {
+                    if !self.has_author(user) {
+                        return Err(RepoError::ForeignKey(format!("unknown author {user}")));
+                    }
+                }
+                if !self.has_project(member.project) {
+                    return Err(RepoError::ForeignKey(format!(
+                        "unknown project {}",
+                        member.project
+                    )));
+                }
+                if self.is_member(member.project, member.user) {
+                    return Err(RepoError::Conflict(format!(
+                        "{} is already a member of {}",
+                        member.user, member.project
+                    )));
+                }
+            }
+            Event::MemberRoleChanged { .. } | Event::MemberRemoved { .. } => {}
+            Event::ProjectTransferred { project, owner } => {
+                if !self.has_author(*owner) {
+                    return Err(RepoError::ForeignKey(format!("unknown author {owner}")));
+                }
+                if !self.has_project(*project) {
+                    return Err(RepoError::ForeignKey(format!("unknown project {project}")));
+                }
+            }
+            Event::LogAdded { project, log } => {
+                if !self.has_author(log.author()) {
+                    return Err(RepoError::ForeignKey(format!(
+                        "unknown author {}",
+                        log.author()
+                    )));
+                }
+                if !self.has_project(*project) {
+                    return Err(RepoError::ForeignKey(format!("unknown project {project}")));
+                }
+            }
+            Event::LogPinned {
+                project, log, by, ..
+            } => {
+                if !self.has_author(*by) {
+                    return Err(RepoError::ForeignKey(format!("unknown author {by}")));
+                }
+                if !self.logs.iter().any(|(_, l)| l.id() == *log) {
+                    return Err(RepoError::ForeignKey(format!("unknown log {log}")));
+                }
+                let pins = self.pins.iter();
+                let others = pins.filter(|pin| pin.project == *project && pin.log != *log);
+                if others.count() >= MAX_PINNED_LOGS {
+                    return Err(RepoError::Conflict(format!(
+                        "project {project} has {MAX_PINNED_LOGS} pinned logs already"
+                    )));
+                }
+            }
+            Event::LogUnpinned { .. } | Event::Audited(_) => {}
+            Event::FieldDeclared(definition) => {
+                if !self.has_project(definition.project) {
+                    return Err(RepoError::ForeignKey(format!(
+                        "unknown project {}",
+                        definition.project
+                    )));
+                }
+            }
+            Event::TemplateCreated(template) => {
+                if let Some(project) = template.project {
+                    if !self.has_project(project) {
+                        return Err(RepoError::ForeignKey(format!("unknown project {project}")));
+                    }
+                }
+            }
+            Event::ViewSaved(view) => {
+                if !self.has_author(view.meta.author) {
+                    return Err(RepoError::ForeignKey(format!(
+                        "unknown author {}",
+                        view.meta.author
+                    )));
+                }
+                if let Some(project) = view.project {
+                    if !self.has_project(project) {
+                        return Err(RepoError::ForeignKey(format!("unknown project {project}")));
+                    }
+                }
+                if self.views.iter().any(|v| {
+                    v.id() != view.id() && v.meta.author == view.meta.author && v.name == view.name
+                }) {
+                    return Err(RepoError::Conflict(format!(
+                        "view {} already exists",
+                        view.name
+                    )));
+                }
+            }
+        }
+        if let Event::UserCreated(_)
+        | Event::ProjectCreated(_)
+        | Event::TeamCreated(_)
+        | Event::LogAdded { .. }
+        | Event::TemplateCreated(_) = event
+        {
+            if self.contains(event) {
+                return Err(RepoError::Conflict("duplicate id".into()));
+            }
+        }
+        Ok(())
+    }
+
+    fn contains(&self, event: &Event) -> bool {
+        match event {
+            Event::UserCreated(author) => self.has_author(author.id()),
+            Event::ProjectCreated(project) => self.has_project(project.id()),
+            Event::TeamCreated(team) => self.has_team(team.id()),
+            Event::LogAdded { log, .. } => self.logs.iter().any(|(_, l)| l.id() == log.id()),
+            Event::TemplateCreated(template) => {
+                self.templates.iter().any(|t| t.id() == template.id())
+            }
+            _ => false,
+        }
+    }
+
+    fn apply(&mut self, event: Event) {
+        match event {
+            Event::UserCreated(author) => self.authors.push(author),
+            Event::ProjectCreated(project) => self.projects.push(project),
+            Event::TeamCreated(team) => self.teams.push(team),
+            Event::TeamMemberAdded(member) => self.team_members.push(member),
+            Event::MemberAdded(member) => self.members.push(member),
+            Event::MemberRoleChanged {
+                project,
+                user,
+                role,
+            } => {
+                let members = self.members.iter_mut();
+                for member in members.filter(|m| m.project == project && m.user == user) {
+                    member.role = role;
+                }
+            }
+            Event::MemberRemoved { project, user } => self
+                .members
+                .retain(|m| m.project != project || m.user != user),
+            Event::ProjectTransferred { project, owner } => {
+                if let Some(project) = self.projects.iter_mut().find(|p| p.id() == project) {
+                    project.meta.author = owner;
+                    project.meta.version = project.meta.version.next();
+                }
+            }
+            Event::LogAdded { project, log } => self.logs.push((project, log)),
+            Event::LogPinned {
+                project, log, at, ..
+            } => {
+                if !self
+                    .pins
+                    .iter()
+                    .any(|pin| pin.project == project && pin.log == log)
+                {
+                    self.pins.push(Pin {
+                        project,
+                        log,
+                        created: at,
+                    });
+                }
+            }
+            Event::LogUnpinned { project, log } => self
+                .pins
+                .retain(|pin| pin.project != project || pin.log != log),
+            Event::FieldDeclared(definition) => match self
+                .fields
+                .iter_mut()
+                .find(|d| d.project == definition.project && d.name == definition.name)
+            {
+                Some(existing) => *existing = definition,
+                None => self.fields.push(definition),
+            },
+            Event::TemplateCreated(template) => self.templates.push(template),
+            Event::ViewSaved(view) => match self.views.iter_mut().find(|v| v.id() == view.id()) {
+                Some(existing) => *existing = view,
+                None => self.views.push(view),
+            },
+            Event::Audited(entry) => self.audit.push(entry),
+        }
+    }
+}
+
+/// Repository keeping everything in memory, lost when the process exits.
+///
+/// Clones share the same data. Meant for tests and ephemeral sessions; it
+/// enforces the same uniqueness and reference constraints as the SQL
+/// schemas.
+#[derive(Clone, Default)]
+pub struct InMemory {
+    tables: Arc<RwLock<Tables>>,
+    persist: Option<Arc<Persist>>,
+    /// open transaction, whose events are applied to a copy of the tables
+    work: Option<Arc<Work>>,
+}
+
+/// Called with the events before they are applied, all of them or none
+/// being persisted, see `InMemory::persisted`
+pub(super) type Persist = dyn Fn(&[Event]) -> Result<(), RepoError> + Send + Sync;
+
+/// Transaction of a repository returned by `UnitOfWork::begin`
+struct Work {
+    /// repository the events are committed to
+    base: InMemory,
+    /// events of the transaction, `None` once it is committed or rolled back
+    events: Mutex<Option<Vec<Event>>>,
+}
+
+impl std::fmt::Debug for InMemory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InMemory")
+            .field("persisted", &self.persist.is_some())
+            .field("transaction", &self.work.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl InMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Tables> {
+        self.tables.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Tables> {
+        self.tables.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Tables rebuilt from past events, passing the new ones to `persist`
+    /// before applying them
+    #[cfg(feature = "journal")]
+    pub(super) fn persisted(
+        events: impl IntoIterator<Item = Event>,
+        persist: Box<Persist>,
+    ) -> Result<Self, RepoError> {
+        let mut tables = Tables::default();
+        for event in events {
+            tables.check(&event)?;
+            tables.apply(event);
+        }
+        Ok(Self {
+            tables: Arc::new(RwLock::new(tables)),
+            persist: Some(Arc::from(persist)),
+            work: None,
+        })
+    }
+
+    /// Checks the event, persists it if needed then applies it. Writes are
+    /// serialized, so events are persisted in the order they apply.
+    ///
+    /// In a transaction, the event is only kept to be committed later.
+    fn commit(&self, event: Event) -> Result<(), RepoError> {
+        let mut tables = self.write();
+        tables.check(&event)?;
+        if let Some(work) = &self.work {
+            let mut events = work.events.lock().unwrap_or_else(|e| e.into_inner());
+            events
+                .as_mut()
+                .ok_or_else(|| RepoError::Unavailable("transaction already finished".into()))?
+                .push(event.clone());
+        } else if let Some(persist) = &self.persist {
+            persist(std::slice::from_ref(&event))?;
+        }
+        tables.apply(event);
+        Ok(())
+    }
+
+    /// Commits the events at once: they are checked again since other
+    /// writes may have happened since the transaction began
+    fn commit_all(&self, events: Vec<Event>) -> Result<(), RepoError> {
+        let mut tables = self.write();
+        let mut staged = tables.clone();
+        for event in &events {
+            staged.check(event)?;
+            staged.apply(event.clone());
+        }
+        if let Some(persist) = &self.persist {
+            persist(&events)?;
+        }
+        *tables = staged;
+        Ok(())
+    }
+
+    /// Ends the transaction, returning its events
+    fn finish(self) -> Result<(InMemory, Vec<Event>), RepoError> {
+        let work = self
+            .work
+            .ok_or_else(|| RepoError::NotFound("no transaction to finish".into()))?;
+        let events = work
+            .events
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .ok_or_else(|| RepoError::Unavailable("transaction already finished".into()))?;
+        Ok((work.base.clone(), events))
+    }
+
+    fn is_pinned(&self, project: ProjectId, log: EntryId) -> bool {
+        self.read()
+            .pins
+            .iter()
+            .any(|pin| pin.project == project && pin.log == log)
+    }
+}
+
+fn matches(filter: &FieldFilter, value: &str) -> bool {
+    let ordering = if filter.numeric {
+        match (
+            value.trim().parse::<f64>(),
+            filter.value.trim().parse::<f64>(),
+        ) {
+            (Ok(value), Ok(expected)) => value.partial_cmp(&expected),
+            _ => None,
+        }
+    } else {
+        Some(value.cmp(filter.value.as_str()))
+    };
+    let Some(ordering) = ordering else {
+        return false;
+    };
+    match filter.op {
+        FieldOp::Eq => ordering.is_eq(),
+        FieldOp::Ne => ordering.is_ne(),
+        FieldOp::Lt => ordering.is_lt(),
+        FieldOp::Le => ordering.is_le(),
+        FieldOp::Gt => ordering.is_gt(),
+        FieldOp::Ge => ordering.is_ge(),
+    }
+}
+
+impl AuthorRepository for InMemory {
+    async fn create_author(&self, request: CreateAuthorRequest) -> Result<User, RepoError> {
+        let author = User::create(request.username);
+        self.commit(Event::UserCreated(author.clone()))?;
+        Ok(author)
+    }
+
+    async fn get_user_by_name(&self, username: &Username) -> Result<Option<User>, RepoError> {
+        let key = username.key();
+        Ok(self
+            .read()
+            .authors
+            .iter()
+            .find(|a| a.name.key() == key)
+            .cloned())
+    }
+
+    async fn get_user_by_id(&self, id: UserId) -> Result<Option<User>, RepoError> {
+        Ok(self.read().authors.iter().find(|a| a.id() == id).cloned())
+    }
+
+    #[cfg(feature = "admin")]
+    async fn list_users(&self, page: Page) -> Result<Paged<User>, RepoError> {
+        Ok(page_of(self.read().authors.iter(), page, SortOrder::Asc))
+    }
+}
+
+impl ProjectRepository for InMemory {
+    async fn create_project(&self, request: CreateProjectRequest) -> Result<Project, RepoError> {
+        let project = Project::new(request.project_name, request.owner).with_team(request.team);
+        self.commit(Event::ProjectCreated(project.clone()))?;
+        Ok(project)
+    }
+
+    async fn get_project_by_name(&self, name: &ProjectName) -> Result<Option<Project>, RepoError> {
+        let key = name.key();
+        Ok(self
+            .read()
+            .projects
+            .iter()
+            .find(|p| p.name.key() == key)
+            .cloned())
+    }
+
+    async fn get_project_by_id(&self, id: ProjectId) -> Result<Option<Project>, RepoError> {
+        Ok(self.read().projects.iter().find(|p| p.id() == id).cloned())
+    }
+
+    async fn list_user_projects(
+        &self,
+        user: UserId,
+        page: Page,
+    ) -> Result<Paged<Project>, RepoError> {
+        let tables = self.read();
+        let projects = tables.projects.iter().filter(|p| p.meta.author == user);
+        Ok(page_of(projects, page, SortOrder::Asc))
+    }
+
+    async fn list_project_activity(
+        &self,
+        search: &ProjectSearch,
+    ) -> Result<Vec<ProjectActivity>, RepoError> {
+        let tables = self.read();
+        let activities = tables
+            .projects
+            .iter()
+            .filter(|project| {
+                search
+                    .owner
+                    .is_none_or(|owner| project.meta.author == owner)
+            })
+            .map(|project| {
+                let last_log = tables
+                    .logs
+                    .iter()
+                    .filter(|(p, _)| *p == project.id())
+                    .map(|(_, log)| log.meta.created)
+                    .max_by_key(Date::as_i64);
+                ProjectActivity {
+                    project: project.clone(),
+                    last_activity: last_log.unwrap_or(project.meta.created),
+                }
+            })
+            .filter(|activity| {
+                let last = activity.last_activity.as_i64();
+                search
+                    .active_since
+                    .is_none_or(|since| since.as_i64() <= last)
+            })
+            .collect();
+        Ok(activities)
+    }
+    fn stream_user_projects(&self, user: UserId) -> EntryStream<Project> {
+        let projects: Vec<Project> = self
+            .read()
+            .projects
+            .iter()
+            .filter(|p| p.meta.author == user)
+            .cloned()
+            .collect();
+        stream::iter(projects.into_iter().map(Ok)).boxed()
+    }
+
+    async fn set_project_field(&self, definition: FieldDefinition) -> Result<(), RepoError> {
+        self.commit(Event::FieldDeclared(definition))
+    }
+
+    async fn list_project_fields(
+        &self,
+        project: ProjectId,
+    ) -> Result<Vec<FieldDefinition>, RepoError> {
+        let mut fields: Vec<FieldDefinition> = self
+            .read()
+            .fields
+            .iter()
+            .filter(|d| d.project == project)
+            .cloned()
+            .collect();
+        fields.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(fields)
+    }
+
+    async fn add_project_member(&self, request: AddMemberRequest) -> Result<Member, RepoError> {
+        let AddMemberRequest {
+            by,
+            project,
+            user,
+            role,
+        } = request;
+        let member = Member {
+            project,
+            user,
+            role,
+            by,
+            created: Date::now(),
+        };
+        self.commit(Event::MemberAdded(member.clone()))?;
+        Ok(member)
+    }
+
+    async fn set_member_role(
+        &self,
+        project: ProjectId,
+        user: UserId,
+        role: Role,
+    ) -> Result<bool, RepoError> {
+        if !self.read().is_member(project, user) {
+            return Ok(false);
+        }
+        self.commit(Event::MemberRoleChanged {
+            project,
+            user,
+            role,
+        })?;
+        Ok(true)
+    }
+
+    async fn remove_project_member(
+        &self,
+        project: ProjectId,
+        user: UserId,
+    ) -> Result<bool, RepoError> {
+        if !self.read().is_member(project, user) {
+            return Ok(false);
+        }
+        self.commit(Event::MemberRemoved { project, user })?;
+        Ok(true)
+    }
+
+    async fn transfer_project(&self, project: ProjectId, owner: UserId) -> Result<(), RepoError> {
+        self.commit(Event::ProjectTransferred { project, owner })
+    }
+
+    async fn list_project_members(&self, project: ProjectId) -> Result<Vec<Member>, RepoError> {
+        let tables = self.read();
+        let members = tables.members.iter().filter(|m| m.project == project);
+        Ok(members.cloned().collect())
+    }
+
+    async fn list_user_memberships(&self, user: UserId) -> Result<Vec<Member>, RepoError> {
+        let tables = self.read();
+        let memberships = tables.members.iter().filter(|m| m.user == user);
+        Ok(memberships.cloned().collect())
+    }
+}
+
+impl TeamRepository for InMemory {
+    async fn create_team(&self, request: CreateTeamRequest) -> Result<Team, RepoError> {
+        let team = Team::new(request.name, request.owner);
+        self.commit(Event::TeamCreated(team.clone()))?;
+        Ok(team)
+    }
+
+    async fn get_team_by_name(&self, name: &TeamName) -> Result<Option<Team>, RepoError> {
+        Ok(self.read().teams.iter().find(|t| &t.name == name).cloned())
+    }
+
+    async fn get_team_by_id(&self, id: TeamId) -> Result<Option<Team>, RepoError> {
+        Ok(self.read().teams.iter().find(|t| t.id() == id).cloned())
+    }
+
+    async fn list_user_teams(&self, user: UserId) -> Result<Vec<Team>, RepoError> {
+        let tables = self.read();
+        let teams = tables.teams.iter().filter(|t| {
+            t.meta.author == user
+                || tables
+                    .team_members
+                    .iter()
+                    .any(|m| m.team == t.id() && m.user == user)
+        });
+        Ok(teams.cloned().collect())
+    }
+
+    async fn add_team_member(
+        &self,
+        request: AddTeamMemberRequest,
+    ) -> Result<TeamMember, RepoError> {
+        let AddTeamMemberRequest {
+            by,
+            team,
+            user,
+            role,
+        } = request;
+        let member = TeamMember {
+            team,
+            user,
+            role,
+            by,
+            created: Date::now(),
+        };
+        self.commit(Event::TeamMemberAdded(member.clone()))?;
+        Ok(member)
+    }
+
+    async fn list_team_members(&self, team: TeamId) -> Result<Vec<TeamMember>, RepoError> {
+        let tables = self.read();
+        let members = tables.team_members.iter().filter(|m| m.team == team);
+        Ok(members.cloned().collect())
+    }
+
+    async fn list_team_projects(&self, team: TeamId) -> Result<Vec<Project>, RepoError> {
+        let tables = self.read();
+        let projects = tables.projects.iter().filter(|p| p.team == Some(team));
+        Ok(projects.cloned().collect())
+    }
+}
+
+impl LogRepository for InMemory {
+    async fn create_log(&self, request: CreateLogRequest) -> Result<Log, RepoError> {
+        let CreateLogRequest {
+            author,
+            project,
+            text,
+            fields,
+        } = request;
+        let log = Log::new(text, author).with_fields(fields);
+        self.commit(Event::LogAdded {
+            project,
+            log: log.clone(),
+        })?;
+        Ok(log)
+    }
+
+    async fn query_logs(&self, query: &LogQuery) -> Result<Paged<Log>, RepoError> {
+        let tables = self.read();
+        let logs = tables
+            .logs
+            .iter()
+            .filter(|(p, log)| {
+                *p == query.project
+                    && query.selects(log)
+                    && query.fields.iter().all(|filter| {
+                        log.fields
+                            .get(&filter.name)
+                            .is_some_and(|value| matches(filter, value))
+                    })
+            })
+            .map(|(_, log)| log);
+        Ok(page_of(logs, query.page.clone(), query.sort))
+    }
+
+    async fn search_logs(&self, search: &LogSearch) -> Result<Paged<LogHit>, RepoError> {
+        let tables = self.read();
+        let (wanted, excluded) = (search.phrases(false), search.phrases(true));
+        let fields = search.fields();
+        let terms = search.terms();
+        let mut hits: Vec<LogHit> = tables
+            .logs
+            .iter()
+            .filter(|(project, log)| {
+                search.selects(*project, log)
+                    && match search.any {
+                        true => wanted.iter().any(|p| query::contains_phrase(&log.text, p)),
+                        false => wanted.iter().all(|p| query::contains_phrase(&log.text, p)),
+                    }
+                    && !excluded
+                        .iter()
+                        .any(|p| query::contains_phrase(&log.text, p))
+                    && fields.iter().all(|(filter, negated)| {
+                        let value = log.fields.get(&filter.name);
+                        value.is_some_and(|value| matches(filter, value)) != *negated
+                    })
+            })
+            .filter_map(|(project, log)| {
+                let project = tables.projects.iter().find(|p| p.id() == *project)?;
+                Some(LogHit {
+                    log: log.clone(),
+                    project: project.name.clone(),
+                    snippet: query::snippet(&log.text, &terms),
+                    score: query::score(&log.text, &terms),
+                })
+            })
+            .collect();
+        // best first, then newest first
+        hits.sort_by(|a, b| {
+            let newer = b.log.cursor().cmp(&a.log.cursor());
+            b.score.total_cmp(&a.score).then(newer)
+        });
+        let total = hits.len();
+        let page = &search.page;
+        let hits = hits
+            .into_iter()
+            .skip(page.offset())
+            .take(page.page_size())
+            .collect();
+        Ok(Paged::new(hits, page, total))
+    }
+
+    fn stream_project_logs(&self, project: ProjectId) -> EntryStream<Log> {
+        let logs: Vec<Log> = self
+            .read()
+            .logs
+            .iter()
+            .filter(|(p, _)| *p == project)
+            .map(|(_, log)| log.clone())
+            .collect();
+        stream::iter(logs.into_iter().map(Ok)).boxed()
+    }
+
+    async fn get_project_log(
+        &self,
+        project: ProjectId,
+        id: EntryId,
+    ) -> Result<Option<Log>, RepoError> {
+        Ok(self
+            .read()
+            .logs
+            .iter()
+            .find(|(p, log)| *p == project && log.id() == id)
+            .map(|(_, log)| log.clone()))
+    }
+
+    async fn pin_log(&self, request: PinLogRequest) -> Result<(), RepoError> {
+        let PinLogRequest { by, project, log } = request;
+        if self.is_pinned(project, log) {
+            return Ok(());
+        }
+        self.commit(Event::LogPinned {
+            project,
+            log,
+            by,
+            at: Date::now(),
+        })
+    }
+
+    async fn unpin_log(&self, project: ProjectId, log: EntryId) -> Result<bool, RepoError> {
+        if !self.is_pinned(project, log) {
+            return Ok(false);
+        }
+        self.commit(Event::LogUnpinned { project, log })?;
+        Ok(true)
+    }
+
+    async fn list_pinned_logs(&self, project: ProjectId) -> Result<Vec<Log>, RepoError> {
+        let tables = self.read();
+        let mut pins: Vec<&Pin> = tables
+            .pins
+            .iter()
+            .filter(|pin| pin.project == project)
+            .collect();
+        pins.sort_by_key(|pin| std::cmp::Reverse(pin.created.as_i64()));
+        Ok(pins
+            .into_iter()
+            .filter_map(|pin| tables.logs.iter().find(|(_, log)| log.id() == pin.log))
+            .map(|(_, log)| log.clone())
+            .collect())
+    }
+}
+
+impl TemplateRepository for InMemory {
+    async fn create_template(&self, request: CreateTemplateRequest) -> Result<Template, RepoError> {
+        let CreateTemplateRequest {
+            author,
+            project,
+            name,
+            body,
+        } = request;
+        let template = Template::new(name, author, project, body);
+        self.commit(Event::TemplateCreated(template.clone()))?;
+        Ok(template)
+    }
+
+    async fn list_templates(
+        &self,
+        user: UserId,
+        project: Option<ProjectId>,
+    ) -> Result<Vec<Template>, RepoError> {
+        let mut templates: Vec<Template> = self
+            .read()
+            .templates
+            .iter()
+            .filter(|t| match t.project {
+                None => t.meta.author == user,
+                Some(p) => Some(p) == project,
+            })
+            .cloned()
+            .collect();
+        templates.sort_by(|a, b| {
+            (a.project.is_some(), a.name.to_string())
+                .cmp(&(b.project.is_some(), b.name.to_string()))
+        });
+        Ok(templates)
+    }
+}
+
+impl ViewRepository for InMemory {
+    async fn save_view(&self, view: View) -> Result<(), RepoError> {
+        self.commit(Event::ViewSaved(view))
+    }
+
+    async fn list_views(
+        &self,
+        user: UserId,
+        projects: &[ProjectId],
+    ) -> Result<Vec<View>, RepoError> {
+        let mut views: Vec<View> = self
+            .read()
+            .views
+            .iter()
+            .filter(|v| v.meta.author == user || v.project.is_some_and(|p| projects.contains(&p)))
+            .cloned()
+            .collect();
+        views.sort_by_key(|v| (v.meta.author != user, v.name.to_string()));
+        Ok(views)
+    }
+}
+
+impl AuditRepository for InMemory {
+    async fn record_audit(&self, entry: AuditEntry) -> Result<(), RepoError> {
+        self.commit(Event::Audited(entry))
+    }
+
+    async fn list_audit(&self, query: &AuditQuery) -> Result<Paged<AuditEntry>, RepoError> {
+        let tables = self.read();
+        let entries: Vec<&AuditEntry> = tables
+            .audit
+            .iter()
+            .rev()
+            .filter(|entry| query.selects(entry))
+            .collect();
+        let page = &query.page;
+        let data = entries
+            .iter()
+            .skip(page.offset())
+            .take(page.page_size())
+            .map(|entry| (*entry).clone())
+            .collect();
+        Ok(Paged::new(data, page, entries.len()))
+    }
+}
+
+impl UnitOfWork for InMemory {
+    async fn begin(&self) -> Result<Self, RepoError> {
+        if self.work.is_some() {
+            return Err(RepoError::Conflict("transaction already started".into()));
+        }
+        Ok(InMemory {
+            tables: Arc::new(RwLock::new(self.read().clone())),
+            persist: None,
+            work: Some(Arc::new(Work {
+                base: self.clone(),
+                events: Mutex::new(Some(vec![])),
+            })),
+        })
+    }
+
+    async fn commit(self) -> Result<(), RepoError> {
+        let (base, events) = self.finish()?;
+        base.commit_all(events)
+    }
+
+    async fn rollback(self) -> Result<(), RepoError> {
+        self.finish().map(|_| ())
+    }
+
+    fn in_transaction(&self) -> bool {
+        self.work.is_some()
+    }
+}
@@ -1,6 +1,23 @@
 pub mod axum;
+mod batch;
 pub mod conf;
+#[cfg(feature = "journal")]
+mod journal;
+pub mod markdown;
+mod memory;
+#[cfg(feature = "postgres")]
+mod postgres;
+mod schema;
+mod sql;
 mod sqlite;
+mod transaction;
 
 pub use conf::{Config, Database};
+#[cfg(feature = "journal")]
+pub use journal::Journal as JournalRepo;
+pub use markdown::{Rendered, RenderedHit, TextFormat};
+pub use memory::InMemory as InMemoryRepo;
+#[cfg(feature = "postgres")]
+pub use postgres::Postgres as PostgresRepo;
+pub use schema::SchemaStatus;
 pub use sqlite::Sqlite as SqliteRepo;
@@ -0,0 +1,1343 @@
+use std::{collections::HashMap, str::FromStr};
+
+use anyhow::Context;
+use itertools::Itertools;
+use sqlx::{migrate::Migrator, Connection, PgConnection};
+use tracing::instrument;
+
+use super::{
+    batch::{batched, STREAM_BATCH},
+    schema::{self, SchemaStatus},
+    sql,
+    transaction::{self, Conn},
+};
+use crate::{
+    core::{
+        audit::{AuditEntry, AuditQuery},
+        event::Event,
+        field::{FieldDefinition, FieldFilter, FieldKind, FieldOp},
+        model::{
+            Date, EntryId, Log, Member, Project, ProjectId, Role, User, UserId, Username,
+            MAX_PINNED_LOGS,
+        },
+        naming::{NamePolicy, Naming},
+        pagination::SortOrder,
+        query::{LogHit, LogQuery, LogSearch, ProjectActivity, ProjectSearch},
+        repo::{
+            AddMemberRequest, AddTeamMemberRequest, AuditRepository, AuthorRepository,
+            CreateAuthorRequest, CreateLogRequest, CreateProjectRequest, CreateTeamRequest,
+            CreateTemplateRequest, EntryStream, LogRepository, PinLogRequest, ProjectRepository,
+            RepoError, TeamRepository, TemplateRepository, UnitOfWork, ViewRepository,
+        },
+        team::{Team, TeamId, TeamMember, TeamName},
+        template::{LogFields, Template},
+        view::View,
+    },
+    Page, Paged, ProjectName,
+};
+
+const NUMBER: &str = r"^\s*[-+]?([0-9]+\.?[0-9]*|\.[0-9]+)([eE][-+]?[0-9]+)?\s*$";
+
+/// Repository backed by a PostgreSQL server, e.g. `postgres://user@localhost/reverie`
+#[derive(Clone)]
+pub struct Postgres {
+    pool: sqlx::PgPool,
+    /// transaction every query runs in, see `UnitOfWork`
+    transaction: Option<transaction::Shared<sqlx::Postgres>>,
+}
+
+impl std::fmt::Debug for Postgres {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Postgres")
+            .field("pool", &self.pool)
+            .field("transaction", &self.transaction.is_some())
+            .finish()
+    }
+}
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+
+impl Postgres {
+    /// Connects to the database and applies the pending migrations
+    pub async fn new(url: &str) -> anyhow::Result<Postgres> {
+        let db = Self::open(url).await?;
+        db.migrate().await?;
+        db.record_existing_rows().await?;
+        db.update_name_keys().await?;
+        Ok(db)
+    }
+
+    /// Connects to the database without touching its schema
+    pub async fn open(url: &str) -> anyhow::Result<Postgres> {
+        let pool = sqlx::PgPool::connect_with(
+            sqlx::postgres::PgConnectOptions::from_str(url)
+                .with_context(|| format!("invalid database url {}", url))?,
+        )
+        .await
+        .with_context(|| format!("failed to connect to database at {}", url))?;
+        Ok(Postgres {
+            pool,
+            transaction: None,
+        })
+    }
+
+    /// Applies the pending migrations, returning how many were applied
+    pub async fn migrate(&self) -> anyhow::Result<usize> {
+        let mut conn = self.pool.acquire().await?;
+        schema::migrate(&MIGRATOR, &mut *conn).await
+    }
+
+    pub async fn schema_status(&self) -> anyhow::Result<SchemaStatus> {
+        let mut conn = self.pool.acquire().await?;
+        schema::status(&MIGRATOR, &mut *conn).await
+    }
+
+    /// Applies the event to the tables and records it, in one transaction.
+    /// Returns the number of changed rows: an event changing nothing, like
+    /// pinning a pinned log, is not recorded.
+    async fn commit(&self, event: Event) -> Result<u64, sqlx::Error> {
+        let mut conn = self.conn().await?;
+        // a savepoint when already in a transaction
+        let mut tx = conn.begin().await?;
+        let changed = apply(&mut tx, &event).await?;
+        if changed == 0 {
+            return Ok(0);
+        }
+        record(&mut tx, &event).await?;
+        tx.commit().await?;
+        Ok(changed)
+    }
+
+    /// Replaces the content of the tables with the projection of the
+    /// recorded events, returning how many events were replayed
+    pub async fn rebuild(&self) -> anyhow::Result<usize> {
+        self.record_existing_rows().await?;
+        let mut tx = self.pool.begin().await?;
+        for table in PROJECTIONS {
+            sqlx::query(&format!("DELETE FROM {table}"))
+                .execute(&mut *tx)
+                .await?;
+        }
+        let events: Vec<(i64, String)> = sqlx::query_as("SELECT seq,data FROM event ORDER BY seq")
+            .fetch_all(&mut *tx)
+            .await?;
+        for (seq, data) in &events {
+            let event: Event =
+                serde_json::from_str(data).with_context(|| format!("corrupt event #{seq}"))?;
+            apply(&mut tx, &event)
+                .await
+                .with_context(|| format!("could not apply event #{seq}"))?;
+        }
+        tx.commit().await?;
+        Ok(events.len())
+    }
+
+    /// Users and projects get the keys of their names under the installed
+    /// naming policy, when they were computed under another one or not at
+    /// all. Fails on names which are the same under the installed policy.
+    async fn update_name_keys(&self) -> anyhow::Result<()> {
+        let naming = Naming::current();
+        let mut tx = self.pool.begin().await?;
+        if stale_name_keys(&mut tx, "author", &naming.users).await? {
+            let authors: Vec<User> = sqlx::query_as("SELECT id,name FROM author")
+                .fetch_all(&mut *tx)
+                .await?;
+            let names = authors.into_iter().map(|a| (a.id(), a.name));
+            let keys = sql::name_keys("users", names, Username::key)?;
+            sqlx::query("UPDATE author SET name_key = NULL")
+                .execute(&mut *tx)
+                .await?;
+            for (id, key) in keys {
+                sqlx::query("UPDATE author SET name_key = $1 WHERE id = $2")
+                    .bind(key)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            record_name_policy(&mut tx, "author", &naming.users).await?;
+        }
+        if stale_name_keys(&mut tx, "project", &naming.projects).await? {
+            let projects: Vec<Project> =
+                sqlx::query_as("SELECT id,author,created,version,revision,name,team FROM project")
+                    .fetch_all(&mut *tx)
+                    .await?;
+            let names = projects.into_iter().map(|p| (p.id(), p.name));
+            let keys = sql::name_keys("projects", names, ProjectName::key)?;
+            sqlx::query("UPDATE project SET name_key = NULL")
+                .execute(&mut *tx)
+                .await?;
+            for (id, key) in keys {
+                sqlx::query("UPDATE project SET name_key = $1 WHERE id = $2")
+                    .bind(key)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            record_name_policy(&mut tx, "project", &naming.projects).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Databases written to before events were recorded get the events
+    /// producing their content, so that rebuilding them loses nothing
+    async fn record_existing_rows(&self) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let (events,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM event")
+            .fetch_one(&mut *tx)
+            .await?;
+        let (authors,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM author")
+            .fetch_one(&mut *tx)
+            .await?;
+        if events > 0 || authors == 0 {
+            return Ok(());
+        }
+        let mut existing = vec![];
+        let authors: Vec<User> = sqlx::query_as("SELECT id,name FROM author")
+            .fetch_all(&mut *tx)
+            .await?;
+        existing.extend(authors.into_iter().map(Event::UserCreated));
+        let projects: Vec<Project> = sqlx::query_as(
+            "SELECT id,author,created,version,revision,name,team FROM project ORDER BY created",
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        existing.extend(projects.into_iter().map(Event::ProjectCreated));
+        let members: Vec<(ProjectId, UserId, String, UserId, Date)> = sqlx::query_as(
+            "SELECT project,member,role,author,created FROM project_member ORDER BY created",
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        for (project, user, role, by, created) in members {
+            existing.push(Event::MemberAdded(Member {
+                project,
+                user,
+                role: Role::from_str(&role).map_err(anyhow::Error::msg)?,
+                by,
+                created,
+            }));
+        }
+        let fields: Vec<(ProjectId, String, String, bool)> =
+            sqlx::query_as("SELECT project,name,kind,required FROM project_field")
+                .fetch_all(&mut *tx)
+                .await?;
+        for (project, name, kind, required) in fields {
+            existing.push(Event::FieldDeclared(FieldDefinition {
+                project,
+                kind: FieldKind::from_str(&kind).map_err(anyhow::Error::msg)?,
+                name,
+                required,
+            }));
+        }
+        let templates: Vec<Template> = sqlx::query_as(
+            "SELECT id,author,project,created,version,revision,name,body FROM template ORDER BY created",
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        existing.extend(templates.into_iter().map(Event::TemplateCreated));
+        let logs: Vec<ProjectLog> = sqlx::query_as(
+            "SELECT project,id,author,created,version,revision,text FROM log ORDER BY created",
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let mut fields: HashMap<EntryId, LogFields> = HashMap::new();
+        let rows: Vec<(EntryId, String, String)> =
+            sqlx::query_as("SELECT log,name,value FROM log_field")
+                .fetch_all(&mut *tx)
+                .await?;
+        for (log, name, value) in rows {
+            fields.entry(log).or_default().insert(name, value);
+        }
+        existing.extend(logs.into_iter().map(|ProjectLog { project, log }| {
+            let fields = fields.remove(&log.id()).unwrap_or_default();
+            Event::LogAdded {
+                project,
+                log: log.with_fields(fields),
+            }
+        }));
+        let pins: Vec<(ProjectId, EntryId, UserId, Date)> =
+            sqlx::query_as("SELECT project,log,author,created FROM pin ORDER BY created")
+                .fetch_all(&mut *tx)
+                .await?;
+        existing.extend(
+            pins.into_iter()
+                .map(|(project, log, by, at)| Event::LogPinned {
+                    project,
+                    log,
+                    by,
+                    at,
+                }),
+        );
+        for event in &existing {
+            record(&mut tx, event).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Members whose `column` is the id, oldest first
+    async fn list_members<T>(&self, column: &str, id: T) -> Result<Vec<Member>, RepoError>
+    where
+        T: for<'q> sqlx::Encode<'q, sqlx::Postgres> + sqlx::Type<sqlx::Postgres> + Send,
+    {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        let rows: Vec<(ProjectId, UserId, String, UserId, Date)> = sqlx::query_as(&format!(
+            "SELECT project,member,role,author,created FROM project_member \
+            WHERE {column} = $1 ORDER BY created"
+        ))
+        .bind(id)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(repo_error)?;
+        rows.into_iter()
+            .map(|(project, user, role, by, created)| {
+                Ok(Member {
+                    project,
+                    user,
+                    role: Role::from_str(&role).map_err(RepoError::Corrupt)?,
+                    by,
+                    created,
+                })
+            })
+            .collect()
+    }
+
+    /// Connection for the next query, see `transaction::connection`
+    async fn conn(&self) -> Result<Conn<sqlx::Postgres>, sqlx::Error> {
+        transaction::connection(&self.pool, self.transaction.as_ref()).await
+    }
+}
+
+/// Tables holding the projection of the events, children first
+const PROJECTIONS: [&str; 12] = [
+    "audit",
+    "pin",
+    "log_field",
+    "project_field",
+    "template",
+    "saved_view",
+    "project_member",
+    "log",
+    "project",
+    "team_member",
+    "team",
+    "author",
+];
+
+#[derive(sqlx::FromRow)]
+struct ProjectLog {
+    project: ProjectId,
+    #[sqlx(flatten)]
+    log: Log,
+}
+
+async fn record(conn: &mut sqlx::PgConnection, event: &Event) -> Result<(), sqlx::Error> {
+    let data = serde_json::to_string(event).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+    sqlx::query("INSERT INTO event (created,kind,data) VALUES ($1,$2,$3)")
+        .bind(Date::now().as_i64())
+        .bind(event.kind())
+        .bind(data)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Whether names of the table miss their key, or have the key of another
+/// naming policy than `policy`
+async fn stale_name_keys(
+    conn: &mut PgConnection,
+    table: &str,
+    policy: &NamePolicy,
+) -> Result<bool, sqlx::Error> {
+    let recorded: Option<(String,)> =
+        sqlx::query_as("SELECT fingerprint FROM name_policy WHERE kind = $1")
+            .bind(table)
+            .fetch_optional(&mut *conn)
+            .await?;
+    let (missing,): (bool,) = sqlx::query_as(&format!(
+        "SELECT EXISTS (SELECT 1 FROM {table} WHERE name_key IS NULL)"
+    ))
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(missing || recorded.map(|(fingerprint,)| fingerprint) != Some(policy.fingerprint()))
+}
+
+/// Records the names of the table as keyed under `policy`
+async fn record_name_policy(
+    conn: &mut PgConnection,
+    table: &str,
+    policy: &NamePolicy,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO name_policy (kind,fingerprint) VALUES ($1,$2) \
+        ON CONFLICT (kind) DO UPDATE SET fingerprint = excluded.fingerprint",
+    )
+    .bind(table)
+    .bind(policy.fingerprint())
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Updates the tables with the event, returning the number of changed rows
+async fn apply(conn: &mut sqlx::PgConnection, event: &Event) -> Result<u64, sqlx::Error> {
+    let result = match event {
+        Event::UserCreated(author) => {
+            sqlx::query("INSERT INTO author (id,name,name_key) VALUES ($1,$2,$3)")
+                .bind(author.id())
+                .bind(&author.name)
+                .bind(author.name.key())
+                .execute(&mut *conn)
+                .await?
+        }
+        Event::ProjectCreated(project) => {
+            sqlx::query(
+                "INSERT INTO project (id,author,created,version,revision,name,team,name_key) \
+                VALUES ($1,$2,$3,$4,$5,$6,$7,$8)",
+            )
+            .bind(project.id())
+            .bind(project.meta.author)
+            .bind(project.meta.created.as_i64())
+            .bind(project.meta.version)
+            .bind(project.meta.revision)
+            .bind(&project.name)
+            .bind(project.team)
+            .bind(project.name.key())
+            .execute(&mut *conn)
+            .await?
+        }
+        Event::TeamCreated(team) => {
+            sqlx::query(
+                "INSERT INTO team (id,author,created,version,revision,name) \
+                VALUES ($1,$2,$3,$4,$5,$6)",
+            )
+            .bind(team.id())
+            .bind(team.meta.author)
+            .bind(team.meta.created.as_i64())
+            .bind(team.meta.version)
+            .bind(team.meta.revision)
+            .bind(&team.name)
+            .execute(&mut *conn)
+            .await?
+        }
+        Event::TeamMemberAdded(member) => {
+            sqlx::query(
+                "INSERT INTO team_member (team,member,role,author,created) \
+                VALUES ($1,$2,$3,$4,$5)",
+            )
+            .bind(member.team)
+            .bind(member.user)
+            .bind(member.role.to_string())
+            .bind(member.by)
+            .bind(member.created.as_i64())
+            .execute(&mut *conn)
+            .await?
+        }
+        Event::MemberAdded(member) => {
+            sqlx::query(
+                "INSERT INTO project_member (project,member,role,author,created) \
+                VALUES ($1,$2,$3,$4,$5)",
+            )
+            .bind(member.project)
+            .bind(member.user)
+            .bind(member.role.to_string())
+            .bind(member.by)
+            .bind(member.created.as_i64())
+            .execute(&mut *conn)
+            .await?
+        }
+        Event::MemberRoleChanged {
+            project,
+            user,
+            role,
+        } => {
+            sqlx::query("UPDATE project_member SET role = $1 WHERE project = $2 AND member = $3")
+                .bind(role.to_string())
+                .bind(project)
+                .bind(user)
+                .execute(&mut *conn)
+                .await?
+        }
+        Event::MemberRemoved { project, user } => {
+            sqlx::query("DELETE FROM project_member WHERE project = $1 AND member = $2")
+                .bind(project)
+                .bind(user)
+                .execute(&mut *conn)
+                .await?
+        }
+        Event::ProjectTransferred { project, owner } => {
+            sqlx::query("UPDATE project SET author = $1, version = version + 1 WHERE id = $2")
+                .bind(owner)
+                .bind(project)
+                .execute(&mut *conn)
+                .await?
+        }
+        Event::LogAdded { project, log } => {
+            let result = sqlx::query(
+                "INSERT INTO log (id,project,author,created,version,revision,text) VALUES ($1,$2,$3,$4,$5,$6,$7)",
+            )
+            .bind(log.id())
+            .bind(project)
+            .bind(log.meta.author)
+            .bind(log.meta.created.as_i64())
+            .bind(log.meta.version)
+            .bind(log.meta.revision)
+            .bind(&log.text)
+            .execute(&mut *conn)
+            .await?;
+            for (name, value) in &log.fields {
+                sqlx::query("INSERT INTO log_field (log,name,value) VALUES ($1,$2,$3)")
+                    .bind(log.id())
+                    .bind(name)
+                    .bind(value)
+                    .execute(&mut *conn)
+                    .await?;
+            }
+            result
+        }
+        Event::LogPinned {
+            project,
+            log,
+            by,
+            at,
+        } => {
+            sqlx::query(
+                "INSERT INTO pin (project,log,author,created) VALUES ($1,$2,$3,$4) ON CONFLICT DO NOTHING",
+            )
+            .bind(project)
+            .bind(log)
+            .bind(by)
+            .bind(at.as_i64())
+            .execute(&mut *conn)
+            .await?
+        }
+        Event::LogUnpinned { project, log } => {
+            sqlx::query("DELETE FROM pin WHERE project = $1 AND log = $2")
+                .bind(project)
+                .bind(log)
+                .execute(&mut *conn)
+                .await?
+        }
+        Event::FieldDeclared(definition) => {
+            sqlx::query(
+                "INSERT INTO project_field (project,name,kind,required) VALUES ($1,$2,$3,$4) ON CONFLICT (project,name) DO UPDATE SET kind = excluded.kind, required = excluded.required",
+            )
+            .bind(definition.project)
+            .bind(&definition.name)
+            .bind(definition.kind.to_string())
+            .bind(definition.required)
+            .execute(&mut *conn)
+            .await?
+        }
+        Event::TemplateCreated(template) => {
+            sqlx::query(
+                "INSERT INTO template (id,author,project,created,version,revision,name,body) VALUES ($1,$2,$3,$4,$5,$6,$7,$8)",
+            )
+            .bind(template.id())
+            .bind(template.meta.author)
+            .bind(template.project)
+            .bind(template.meta.created.as_i64())
+            .bind(template.meta.version)
+            .bind(template.meta.revision)
+            .bind(&template.name)
+            .bind(&template.body)
+            .execute(&mut *conn)
+            .await?
+        }
+        Event::ViewSaved(view) => {
+            sqlx::query(
+                "INSERT INTO saved_view (id,author,project,created,version,revision,name,query) \
+                VALUES ($1,$2,$3,$4,$5,$6,$7,$8) ON CONFLICT (id) DO UPDATE SET \
+                project = excluded.project, version = excluded.version, \
+                revision = excluded.revision, query = excluded.query",
+            )
+            .bind(view.id())
+            .bind(view.meta.author)
+            .bind(view.project)
+            .bind(view.meta.created.as_i64())
+            .bind(view.meta.version)
+            .bind(view.meta.revision)
+            .bind(&view.name)
+            .bind(&view.query)
+            .execute(&mut *conn)
+            .await?
+        }
+        Event::Audited(entry) => {
+            sqlx::query(
+                "INSERT INTO audit \
+                (actor,action,target,before_summary,after_summary,created,origin) \
+                VALUES ($1,$2,$3,$4,$5,$6,$7)",
+            )
+            .bind(entry.actor)
+            .bind(&entry.action)
+            .bind(&entry.target)
+            .bind(&entry.before)
+            .bind(&entry.after)
+            .bind(entry.at.as_i64())
+            .bind(entry.origin.to_string())
+            .execute(&mut *conn)
+            .await?
+        }
+    };
+    Ok(result.rows_affected())
+}
+
+/// Classifies the errors of PostgreSQL, whose class XX (internal error)
+/// reports a damaged database
+fn repo_error(error: sqlx::Error) -> RepoError {
+    sql::repo_error(error, |code| code.starts_with("XX"))
+}
+
+/// Pushes the `FROM` and `WHERE` clauses selecting the logs of the query
+fn push_log_query(query: &mut sqlx::QueryBuilder<'_, sqlx::Postgres>, log_query: &LogQuery) {
+    query.push(" FROM log WHERE log.project = ");
+    query.push_bind(log_query.project);
+    sql::push_log_criteria(query, log_query);
+    for filter in &log_query.fields {
+        push_field_filter(query, filter, false);
+    }
+}
+
+/// Pushes the condition on a field of the logs, which they must not meet
+/// when `negated`
+fn push_field_filter(
+    query: &mut sqlx::QueryBuilder<'_, sqlx::Postgres>,
+    filter: &FieldFilter,
+    negated: bool,
+) {
+    let op = match filter.op {
+        FieldOp::Eq => "=",
+        FieldOp::Ne => "!=",
+        FieldOp::Lt => "<",
+        FieldOp::Le => "<=",
+        FieldOp::Gt => ">",
+        FieldOp::Ge => ">=",
+    };
+    query.push(match negated {
+        true => " AND NOT EXISTS (",
+        false => " AND EXISTS (",
+    });
+    query.push("SELECT 1 FROM log_field WHERE log_field.log = log.id AND log_field.name = ");
+    query.push_bind(filter.name.clone());
+    if filter.numeric {
+        // values stored before the field was declared may not be numbers
+        query.push(format!(
+            " AND (CASE WHEN log_field.value ~ '{NUMBER}' THEN log_field.value::double precision END) {op} "
+        ));
+        query.push_bind(filter.value.trim().parse::<f64>().unwrap_or(f64::NAN));
+    } else {
+        query.push(format!(" AND log_field.value {op} "));
+        query.push_bind(filter.value.clone());
+    }
+    query.push(")");
+}
+
+/// Pushes the `FROM` and `WHERE` clauses selecting the logs of the search,
+/// whose terms all have to match
+fn push_search(query: &mut sqlx::QueryBuilder<'_, sqlx::Postgres>, search: &LogSearch) {
+    let phrases = |negated| {
+        let phrases = search.phrases(negated).into_iter();
+        phrases
+            .map(|words| format!("\"{}\"", words.join(" ")))
+            .collect_vec()
+    };
+    let (wanted, unwanted) = (phrases(false), phrases(true));
+    query.push(" FROM log JOIN project ON project.id = log.project");
+    if wanted.is_empty() {
+        query.push(" WHERE TRUE");
+    } else {
+        query.push(", websearch_to_tsquery('english', ");
+        query.push_bind(wanted.join(if search.any { " or " } else { " " }));
+        query.push(") AS search (query) WHERE to_tsvector('english', log.text) @@ search.query");
+    }
+    if !unwanted.is_empty() {
+        query.push(" AND NOT to_tsvector('english', log.text) @@ websearch_to_tsquery('english', ");
+        query.push_bind(unwanted.join(" or "));
+        query.push(")");
+    }
+    for (filter, negated) in search.fields() {
+        push_field_filter(query, filter, negated);
+    }
+    sql::push_search_criteria(query, search);
+}
+
+/// Columns of the hits of the search, which are only ranked when it has
+/// words to match
+fn search_columns(search: &LogSearch) -> &'static str {
+    match search.phrases(false).is_empty() {
+        true => "left(log.text, 120) AS snippet,0::float8 AS score",
+        false => {
+            "ts_headline('english', log.text, search.query, \
+            'StartSel=\u{E000}, StopSel=\u{E001}, MaxWords=16, MinWords=6') AS snippet,\
+            ts_rank(to_tsvector('english', log.text), search.query)::float8 AS score"
+        }
+    }
+}
+
+/// Loads the fields of the logs with a single query
+async fn load_fields(conn: &mut PgConnection, logs: &mut [Log]) -> Result<(), RepoError> {
+    if logs.is_empty() {
+        return Ok(());
+    }
+    let mut query = sqlx::QueryBuilder::new("SELECT log,name,value FROM log_field WHERE log IN (");
+    let mut ids = query.separated(",");
+    for log in logs.iter() {
+        ids.push_bind(log.id());
+    }
+    ids.push_unseparated(")");
+    let fields: Vec<(EntryId, String, String)> = query
+        .build_query_as()
+        .fetch_all(conn)
+        .await
+        .map_err(repo_error)?;
+    for (id, name, value) in fields {
+        if let Some(log) = logs.iter_mut().find(|log| log.id() == id) {
+            log.fields.insert(name, value);
+        }
+    }
+    Ok(())
+}
+
+impl AuthorRepository for Postgres {
+    async fn create_author(&self, request: CreateAuthorRequest) -> Result<User, RepoError> {
+        let new_author = User::create(request.username);
+        self.commit(Event::UserCreated(new_author.clone()))
+            .await
+            .map_err(repo_error)?;
+        Ok(new_author)
+    }
+
+    async fn get_user_by_name(&self, username: &Username) -> Result<Option<User>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        sqlx::query_as("SELECT id,name FROM author WHERE name_key = $1")
+            .bind(username.key())
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(repo_error)
+    }
+
+    async fn get_user_by_id(&self, id: UserId) -> Result<Option<User>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        sqlx::query_as("SELECT id,name FROM author WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(repo_error)
+    }
+
+    #[instrument]
+    async fn list_users(&self, page: Page) -> Result<Paged<User>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        let (total,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM author")
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(repo_error)?;
+        let mut query = sqlx::QueryBuilder::new("SELECT id,name FROM author WHERE TRUE");
+        sql::push_page(&mut query, "id", &page, SortOrder::Asc);
+        let users: Vec<User> = query
+            .build_query_as()
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(repo_error)?;
+        Ok(Paged::from_rows(users, &page, total as usize))
+    }
+}
+
+impl LogRepository for Postgres {
+    async fn create_log(&self, request: CreateLogRequest) -> Result<Log, RepoError> {
+        let CreateLogRequest {
+            author,
+            project,
+            text,
+            fields,
+        } = request;
+        let log = Log::new(text, author).with_fields(fields);
+        self.commit(Event::LogAdded {
+            project,
+            log: log.clone(),
+        })
+        .await
+        .map_err(repo_error)?;
+        Ok(log)
+    }
+
+    fn stream_project_logs(&self, project: ProjectId) -> EntryStream<Log> {
+        let db = self.clone();
+        batched(STREAM_BATCH, Log::id, move |after, size| {
+            let db = db.clone();
+            async move {
+                let mut conn = db.conn().await.map_err(repo_error)?;
+                let mut logs: Vec<Log> = sqlx::query_as(
+                    "SELECT id,author,created,version,revision,text FROM log WHERE project = $1 AND ($2::uuid IS NULL OR id > $2) ORDER BY id LIMIT $3",
+                )
+                .bind(project)
+                .bind(after)
+                .bind(size as i64)
+                .fetch_all(&mut *conn)
+                .await
+                .map_err(repo_error)?;
+                load_fields(&mut conn, &mut logs).await?;
+                Ok(logs)
+            }
+        })
+    }
+
+    /// Fetches the whole page at once, see `stream_project_logs`
+    async fn query_logs(&self, log_query: &LogQuery) -> Result<Paged<Log>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        let mut count = sqlx::QueryBuilder::new("SELECT COUNT(*)");
+        push_log_query(&mut count, log_query);
+        let (total,): (i64,) = count
+            .build_query_as()
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(repo_error)?;
+        let mut query = sqlx::QueryBuilder::new(
+            "SELECT log.id,log.author,log.created,log.version,log.revision,log.text",
+        );
+        push_log_query(&mut query, log_query);
+        let page = &log_query.page;
+        sql::push_page(&mut query, "log.id", page, log_query.sort);
+        let mut logs: Vec<Log> = query
+            .build_query_as()
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(repo_error)?;
+        load_fields(&mut conn, &mut logs).await?;
+        Ok(Paged::from_rows(logs, page, total as usize))
+    }
+
+    async fn search_logs(&self, search: &LogSearch) -> Result<Paged<LogHit>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        let mut count = sqlx::QueryBuilder::new("SELECT COUNT(*)");
+        push_search(&mut count, search);
+        let (total,): (i64,) = count
+            .build_query_as()
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(repo_error)?;
+        let mut query = sqlx::QueryBuilder::new(
+            "SELECT log.id,log.author,log.created,log.version,log.revision,log.text,\
+            project.name AS project,",
+        );
+        query.push(search_columns(search));
+        push_search(&mut query, search);
+        sql::push_ranked_page(&mut query, &search.page);
+        let hits: Vec<LogHit> = query
+            .build_query_as()
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(repo_error)?;
+        let (mut logs, rejoin) = sql::hit_logs(hits);
+        load_fields(&mut conn, &mut logs).await?;
+        Ok(Paged::new(rejoin(logs), &search.page, total as usize))
+    }
+
+    async fn get_project_log(
+        &self,
+        project: ProjectId,
+        id: EntryId,
+    ) -> Result<Option<Log>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        let log: Option<Log> = sqlx::query_as(
+            "SELECT id,author,created,version,revision,text FROM log WHERE project = $1 AND id = $2",
+        )
+        .bind(project)
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(repo_error)?;
+        let mut logs = Vec::from_iter(log);
+        load_fields(&mut conn, &mut logs).await?;
+        Ok(logs.pop())
+    }
+
+    async fn pin_log(&self, request: PinLogRequest) -> Result<(), RepoError> {
+        let PinLogRequest { by, project, log } = request;
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        let mut tx = conn.begin().await.map_err(repo_error)?;
+        // concurrent pins of the project wait for this one to count theirs
+        sqlx::query("SELECT id FROM project WHERE id = $1 FOR UPDATE")
+            .bind(project)
+            .execute(&mut *tx)
+            .await
+            .map_err(repo_error)?;
+        let (others,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM pin WHERE project = $1 AND log <> $2")
+                .bind(project)
+                .bind(log)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(repo_error)?;
+        if others >= MAX_PINNED_LOGS as i64 {
+            return Err(RepoError::Conflict(format!(
+                "project {project} has {MAX_PINNED_LOGS} pinned logs already"
+            )));
+        }
+        let event = Event::LogPinned {
+            project,
+            log,
+            by,
+            at: Date::now(),
+        };
+        if apply(&mut tx, &event).await.map_err(repo_error)? > 0 {
+            record(&mut tx, &event).await.map_err(repo_error)?;
+            tx.commit().await.map_err(repo_error)?;
+        }
+        Ok(())
+    }
+
+    async fn unpin_log(&self, project: ProjectId, log: EntryId) -> Result<bool, RepoError> {
+        let unpinned = self
+            .commit(Event::LogUnpinned { project, log })
+            .await
+            .map_err(repo_error)?;
+        Ok(unpinned > 0)
+    }
+
+    async fn list_pinned_logs(&self, project: ProjectId) -> Result<Vec<Log>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        let mut logs = sqlx::query_as(
+            "SELECT log.id,log.author,log.created,log.version,log.revision,log.text FROM pin JOIN log ON log.id = pin.log WHERE pin.project = $1 ORDER BY pin.created DESC",
+        )
+        .bind(project)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(repo_error)?;
+        load_fields(&mut conn, &mut logs).await?;
+        Ok(logs)
+    }
+}
+
+impl ProjectRepository for Postgres {
+    async fn create_project(&self, request: CreateProjectRequest) -> Result<Project, RepoError> {
+        let CreateProjectRequest {
+            owner: author,
+            project_name,
+            team,
+        } = request;
+        let project = Project::new(project_name, author).with_team(team);
+        self.commit(Event::ProjectCreated(project.clone()))
+            .await
+            .map_err(repo_error)?;
+        Ok(project)
+    }
+    async fn get_project_by_name(&self, name: &ProjectName) -> Result<Option<Project>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        sqlx::query_as(
+            "SELECT id,author,created,version,revision,name,team FROM project WHERE name_key = $1",
+        )
+        .bind(name.key())
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(repo_error)
+    }
+
+    async fn get_project_by_id(&self, id: ProjectId) -> Result<Option<Project>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        sqlx::query_as(
+            "SELECT id,author,created,version,revision,name,team FROM project WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(repo_error)
+    }
+    async fn list_project_activity(
+        &self,
+        search: &ProjectSearch,
+    ) -> Result<Vec<ProjectActivity>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        let mut query = sqlx::QueryBuilder::new(
+            "SELECT project.id,project.author,project.created,project.version,project.revision,\
+            project.name,project.team,COALESCE(MAX(log.created), project.created) AS last_activity \
+            FROM project LEFT JOIN log ON log.project = project.id WHERE TRUE",
+        );
+        sql::push_activity_criteria(&mut query, search);
+        query
+            .build_query_as()
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(repo_error)
+    }
+    fn stream_user_projects(&self, user: UserId) -> EntryStream<Project> {
+        let db = self.clone();
+        batched(STREAM_BATCH, Project::id, move |after, size| {
+            let db = db.clone();
+            async move {
+                let mut conn = db.conn().await.map_err(repo_error)?;
+                sqlx::query_as(
+                    "SELECT id,author,created,version,revision,name,team FROM project WHERE author = $1 AND ($2::uuid IS NULL OR id > $2) ORDER BY id LIMIT $3",
+                )
+                .bind(user)
+                .bind(after)
+                .bind(size as i64)
+                .fetch_all(&mut *conn)
+                .await
+                .map_err(repo_error)
+            }
+        })
+    }
+    async fn list_user_projects(
+        &self,
+        user: UserId,
+        page: Page,
+    ) -> Result<Paged<Project>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        let (total,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM project WHERE author = $1")
+            .bind(user)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(repo_error)?;
+        let mut query = sqlx::QueryBuilder::new(
+            "SELECT id,author,created,version,revision,name,team FROM project WHERE author = ",
+        );
+        query.push_bind(user);
+        sql::push_page(&mut query, "id", &page, SortOrder::Asc);
+        let projects: Vec<Project> = query
+            .build_query_as()
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(repo_error)?;
+        Ok(Paged::from_rows(projects, &page, total as usize))
+    }
+
+    async fn set_project_field(&self, definition: FieldDefinition) -> Result<(), RepoError> {
+        self.commit(Event::FieldDeclared(definition))
+            .await
+            .map_err(repo_error)?;
+        Ok(())
+    }
+
+    async fn list_project_fields(
+        &self,
+        project: ProjectId,
+    ) -> Result<Vec<FieldDefinition>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        let rows: Vec<(String, String, bool)> = sqlx::query_as(
+            "SELECT name,kind,required FROM project_field WHERE project = $1 ORDER BY name",
+        )
+        .bind(project)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(repo_error)?;
+        rows.into_iter()
+            .map(|(name, kind, required)| {
+                Ok(FieldDefinition {
+                    project,
+                    kind: FieldKind::from_str(&kind).map_err(RepoError::Corrupt)?,
+                    name,
+                    required,
+                })
+            })
+            .collect()
+    }
+
+    async fn add_project_member(&self, request: AddMemberRequest) -> Result<Member, RepoError> {
+        let AddMemberRequest {
+            by,
+            project,
+            user,
+            role,
+        } = request;
+        let member = Member {
+            project,
+            user,
+            role,
+            by,
+            created: Date::now(),
+        };
+        self.commit(Event::MemberAdded(member.clone()))
+            .await
+            .map_err(repo_error)?;
+        Ok(member)
+    }
+
+    async fn set_member_role(
+        &self,
+        project: ProjectId,
+        user: UserId,
+        role: Role,
+    ) -> Result<bool, RepoError> {
+        let changed = self
+            .commit(Event::MemberRoleChanged {
+                project,
+                user,
+                role,
+            })
+            .await
+            .map_err(repo_error)?;
+        Ok(changed > 0)
+    }
+
+    async fn remove_project_member(
+        &self,
+        project: ProjectId,
+        user: UserId,
+    ) -> Result<bool, RepoError> {
+        let removed = self
+            .commit(Event::MemberRemoved { project, user })
+            .await
+            .map_err(repo_error)?;
+        Ok(removed > 0)
+    }
+
+    async fn transfer_project(&self, project: ProjectId, owner: UserId) -> Result<(), RepoError> {
+        let transferred = self
+            .commit(Event::ProjectTransferred { project, owner })
+            .await
+            .map_err(repo_error)?;
+        match transferred {
+            0 => Err(RepoError::NotFound(format!("project {project}"))),
+            _ => Ok(()),
+        }
+    }
+
+    async fn list_project_members(&self, project: ProjectId) -> Result<Vec<Member>, RepoError> {
+        self.list_members("project", project).await
+    }
+
+    async fn list_user_memberships(&self, user: UserId) -> Result<Vec<Member>, RepoError> {
+        self.list_members("member", user).await
+    }
+}
+
+impl TeamRepository for Postgres {
+    async fn create_team(&self, request: CreateTeamRequest) -> Result<Team, RepoError> {
+        let team = Team::new(request.name, request.owner);
+        self.commit(Event::TeamCreated(team.clone()))
+            .await
+            .map_err(repo_error)?;
+        Ok(team)
+    }
+
+    async fn get_team_by_name(&self, name: &TeamName) -> Result<Option<Team>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        sqlx::query_as("SELECT id,author,created,version,revision,name FROM team WHERE name = $1")
+            .bind(name)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(repo_error)
+    }
+
+    async fn get_team_by_id(&self, id: TeamId) -> Result<Option<Team>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        sqlx::query_as("SELECT id,author,created,version,revision,name FROM team WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(repo_error)
+    }
+
+    async fn list_user_teams(&self, user: UserId) -> Result<Vec<Team>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        sqlx::query_as(
+            "SELECT id,author,created,version,revision,name FROM team WHERE author = $1 \
+            OR id IN (SELECT team FROM team_member WHERE member = $1) ORDER BY created",
+        )
+        .bind(user)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(repo_error)
+    }
+
+    async fn add_team_member(
+        &self,
+        request: AddTeamMemberRequest,
+    ) -> Result<TeamMember, RepoError> {
+        let AddTeamMemberRequest {
+            by,
+            team,
+            user,
+            role,
+        } = request;
+        let member = TeamMember {
+            team,
+            user,
+            role,
+            by,
+            created: Date::now(),
+        };
+        self.commit(Event::TeamMemberAdded(member.clone()))
+            .await
+            .map_err(repo_error)?;
+        Ok(member)
+    }
+
+    async fn list_team_members(&self, team: TeamId) -> Result<Vec<TeamMember>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        let rows: Vec<(UserId, String, UserId, Date)> = sqlx::query_as(
+            "SELECT member,role,author,created FROM team_member WHERE team = $1 ORDER BY created",
+        )
+        .bind(team)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(repo_error)?;
+        rows.into_iter()
+            .map(|(user, role, by, created)| {
+                Ok(TeamMember {
+                    team,
+                    user,
+                    role: Role::from_str(&role).map_err(RepoError::Corrupt)?,
+                    by,
+                    created,
+                })
+            })
+            .collect()
+    }
+
+    async fn list_team_projects(&self, team: TeamId) -> Result<Vec<Project>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        sqlx::query_as(
+            "SELECT id,author,created,version,revision,name,team FROM project WHERE team = $1 \
+            ORDER BY created",
+        )
+        .bind(team)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(repo_error)
+    }
+}
+
+impl AuditRepository for Postgres {
+    async fn record_audit(&self, entry: AuditEntry) -> Result<(), RepoError> {
+        self.commit(Event::Audited(entry))
+            .await
+            .map_err(repo_error)?;
+        Ok(())
+    }
+
+    async fn list_audit(&self, query: &AuditQuery) -> Result<Paged<AuditEntry>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        let mut count = sqlx::QueryBuilder::new("SELECT COUNT(*) FROM audit WHERE TRUE");
+        sql::push_audit_criteria(&mut count, query);
+        let (total,): (i64,) = count
+            .build_query_as()
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(repo_error)?;
+        let mut select = sqlx::QueryBuilder::new(format!(
+            "SELECT {} FROM audit WHERE TRUE",
+            sql::AuditRow::COLUMNS
+        ));
+        sql::push_audit_criteria(&mut select, query);
+        select.push(" ORDER BY seq DESC LIMIT ");
+        select.push_bind(query.page.page_size() as i64);
+        select.push(" OFFSET ");
+        select.push_bind(query.page.offset() as i64);
+        let rows: Vec<sql::AuditRow> = select
+            .build_query_as()
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(repo_error)?;
+        let entries = rows
+            .into_iter()
+            .map(sql::AuditRow::entry)
+            .collect::<Result<_, _>>()?;
+        Ok(Paged::new(entries, &query.page, total as usize))
+    }
+}
+
+impl UnitOfWork for Postgres {
+    async fn begin(&self) -> Result<Self, RepoError> {
+        if self.transaction.is_some() {
+            return Err(RepoError::Conflict("transaction already started".into()));
+        }
+        let transaction = transaction::begin(&self.pool).await.map_err(repo_error)?;
+        Ok(Postgres {
+            pool: self.pool.clone(),
+            transaction: Some(transaction),
+        })
+    }
+
+    async fn commit(self) -> Result<(), RepoError> {
+        let transaction = self
+            .transaction
+            .ok_or_else(|| RepoError::NotFound("no transaction to commit".into()))?;
+        transaction::finish(&transaction, true)
+            .await
+            .map_err(repo_error)
+    }
+
+    async fn rollback(self) -> Result<(), RepoError> {
+        let transaction = self
+            .transaction
+            .ok_or_else(|| RepoError::NotFound("no transaction to roll back".into()))?;
+        transaction::finish(&transaction, false)
+            .await
+            .map_err(repo_error)
+    }
+
+    fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+}
+
+impl TemplateRepository for Postgres {
+    async fn create_template(&self, request: CreateTemplateRequest) -> Result<Template, RepoError> {
+        let CreateTemplateRequest {
+            author,
+            project,
+            name,
+            body,
+        } = request;
+        let template = Template::new(name, author, project, body);
+        self.commit(Event::TemplateCreated(template.clone()))
+            .await
+            .map_err(repo_error)?;
+        Ok(template)
+    }
+
+    async fn list_templates(
+        &self,
+        user: UserId,
+        project: Option<ProjectId>,
+    ) -> Result<Vec<Template>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        sqlx::query_as(
+            "SELECT id,author,project,created,version,revision,name,body FROM template WHERE (author = $1 AND project IS NULL) OR project = $2 ORDER BY project IS NOT NULL, name",
+        )
+        .bind(user)
+        .bind(project)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(repo_error)
+    }
+}
+
+impl ViewRepository for Postgres {
+    async fn save_view(&self, view: View) -> Result<(), RepoError> {
+        self.commit(Event::ViewSaved(view))
+            .await
+            .map_err(repo_error)?;
+        Ok(())
+    }
+
+    async fn list_views(
+        &self,
+        user: UserId,
+        projects: &[ProjectId],
+    ) -> Result<Vec<View>, RepoError> {
+        let mut conn = self.conn().await.map_err(repo_error)?;
+        let mut query = sqlx::QueryBuilder::new(
+            "SELECT id,author,project,created,version,revision,name,query FROM saved_view \
+            WHERE author = ",
+        );
+        query.push_bind(user);
+        query.push(" OR project IN (");
+        let mut ids = query.separated(",");
+        for project in projects {
+            ids.push_bind(*project);
+        }
+        // matching nothing, for the list to be valid without projects
+        ids.push("NULL");
+        ids.push_unseparated(") ORDER BY author != ");
+        query.push_bind(user);
+        query.push(", name");
+        query
+            .build_query_as()
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(repo_error)
+    }
+}
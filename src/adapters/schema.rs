@@ -0,0 +1,115 @@
+use anyhow::bail;
+use itertools::Itertools;
+use sqlx::migrate::{AppliedMigration, Migrate, Migrator};
+
+/// Schema of a database compared to the migrations embedded in the binary
+#[derive(Debug, Clone)]
+pub struct SchemaStatus {
+    /// version of the last applied migration, `None` on an empty database
+    pub current: Option<i64>,
+    /// version of the last migration known by the binary
+    pub latest: i64,
+    /// migrations not applied yet, as (version, description)
+    pub pending: Vec<(i64, String)>,
+}
+impl std::fmt::Display for SchemaStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.current {
+            Some(current) => write!(f, "schema version {current}")?,
+            None => write!(f, "empty schema")?,
+        }
+        if self.pending.is_empty() {
+            write!(f, " (up to date)")
+        } else {
+            write!(
+                f,
+                ", {} pending migration(s) up to {}:\n{}",
+                self.pending.len(),
+                self.latest,
+                self.pending
+                    .iter()
+                    .map(|(version, description)| format!(" - {version} {description}"))
+                    .join("\n")
+            )
+        }
+    }
+}
+
+/// Reads the applied migrations. Fails if the database was migrated by a
+/// more recent binary, whose schema this one can not know.
+pub(crate) async fn status<C>(migrator: &Migrator, conn: &mut C) -> anyhow::Result<SchemaStatus>
+where
+    C: Migrate + Send,
+{
+    conn.ensure_migrations_table().await?;
+    let applied = conn.list_applied_migrations().await?;
+    compare(migrator, &applied)
+}
+
+fn compare(migrator: &Migrator, applied: &[AppliedMigration]) -> anyhow::Result<SchemaStatus> {
+    let latest = migrator.iter().map(|m| m.version).max().unwrap_or_default();
+    let current = applied.iter().map(|m| m.version).max();
+    if let Some(current) = current.filter(|current| *current > latest) {
+        bail!(
+            "database schema version {current} is newer than the latest known by this binary ({latest})"
+        );
+    }
+    let pending = migrator
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .filter(|m| !applied.iter().any(|a| a.version == m.version))
+        .map(|m| (m.version, m.description.to_string()))
+        .collect();
+    Ok(SchemaStatus {
+        current,
+        latest,
+        pending,
+    })
+}
+
+/// Applies the pending migrations, returning how many were applied. They are
+/// read under the lock, so that processes starting together apply them once.
+pub(crate) async fn migrate<C>(migrator: &Migrator, conn: &mut C) -> anyhow::Result<usize>
+where
+    C: Migrate + Send,
+{
+    conn.lock().await?;
+    let applied = apply_pending(migrator, conn).await;
+    conn.unlock().await?;
+    applied
+}
+
+/// Applies the pending migrations once the applied ones are checked: none may
+/// have failed halfway nor changed since it was applied
+async fn apply_pending<C>(migrator: &Migrator, conn: &mut C) -> anyhow::Result<usize>
+where
+    C: Migrate + Send,
+{
+    conn.ensure_migrations_table().await?;
+    if let Some(version) = conn.dirty_version().await? {
+        bail!("migration {version} failed halfway, the database needs to be repaired");
+    }
+    let applied = conn.list_applied_migrations().await?;
+    let status = compare(migrator, &applied)?;
+    let up = || {
+        migrator
+            .iter()
+            .filter(|m| !m.migration_type.is_down_migration())
+    };
+    for migration in up() {
+        let applied = applied.iter().find(|a| a.version == migration.version);
+        if applied.is_some_and(|applied| applied.checksum != migration.checksum) {
+            bail!(
+                "migration {} {} was changed after being applied",
+                migration.version,
+                migration.description
+            );
+        }
+    }
+    for migration in up() {
+        if !applied.iter().any(|a| a.version == migration.version) {
+            conn.apply(migration).await?;
+        }
+    }
+    Ok(status.pending.len())
+}
@@ -0,0 +1,268 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use sqlx::{error::ErrorKind, Database, Encode, QueryBuilder, Type};
+
+use crate::core::{
+    audit::{AuditEntry, AuditQuery, Origin},
+    model::{Date, Log, ProjectId, UserId},
+    pagination::{Cursor, Page, PageCursor, SortOrder},
+    query::{self, LogHit, LogQuery, LogSearch, ProjectSearch},
+    repo::RepoError,
+};
+
+/// Classifies an error of a SQL adapter. `corruption` tells the database
+/// codes reporting a damaged storage apart from the ones of a storage
+/// unavailable for now.
+pub(super) fn repo_error(error: sqlx::Error, corruption: fn(&str) -> bool) -> RepoError {
+    let message = error.to_string();
+    match &error {
+        sqlx::Error::RowNotFound => RepoError::NotFound(message),
+        sqlx::Error::Database(e) => match e.kind() {
+            ErrorKind::UniqueViolation => RepoError::Conflict(message),
+            ErrorKind::ForeignKeyViolation => RepoError::ForeignKey(message),
+            ErrorKind::NotNullViolation | ErrorKind::CheckViolation => RepoError::Corrupt(message),
+            _ if e.code().is_some_and(|code| corruption(&code)) => RepoError::Corrupt(message),
+            _ => RepoError::Unavailable(message),
+        },
+        sqlx::Error::Decode(_)
+        | sqlx::Error::ColumnDecode { .. }
+        | sqlx::Error::ColumnNotFound(_)
+        | sqlx::Error::ColumnIndexOutOfBounds { .. }
+        | sqlx::Error::TypeNotFound { .. } => RepoError::Corrupt(message),
+        _ => RepoError::Unavailable(message),
+    }
+}
+
+/// Pushes the end of a listing query: the entries of the page, in the
+/// `order` of `column`. Pages selected by a cursor seek it instead of
+/// counting the entries to skip. Fetches `Page::fetch_size` rows, in reverse
+/// order for `Before` pages, as expected by `Paged::from_rows`.
+pub(super) fn push_page<'a, DB>(
+    query: &mut QueryBuilder<'a, DB>,
+    column: &str,
+    page: &Page,
+    order: SortOrder,
+) where
+    DB: Database,
+    Cursor: Encode<'a, DB> + Type<DB>,
+    i64: Encode<'a, DB> + Type<DB>,
+{
+    let (forward, backward) = match order {
+        SortOrder::Asc => (("> ", "ASC"), ("< ", "DESC")),
+        SortOrder::Desc => (("< ", "DESC"), ("> ", "ASC")),
+    };
+    let (seek, direction) = match page.cursor() {
+        Some(PageCursor::After(cursor)) => (Some((forward.0, cursor)), forward.1),
+        Some(PageCursor::Before(cursor)) => (Some((backward.0, cursor)), backward.1),
+        None => (None, forward.1),
+    };
+    if let Some((operator, cursor)) = seek {
+        query.push(format!(" AND {column} {operator}"));
+        query.push_bind(cursor);
+    }
+    query.push(format!(" ORDER BY {column} {direction} LIMIT "));
+    query.push_bind(page.fetch_size() as i64);
+    query.push(" OFFSET ");
+    query.push_bind(page.offset() as i64);
+}
+
+/// Pushes the conditions on the author and creation date of the logs of the
+/// query, after a `WHERE` clause on the `log` table
+pub(super) fn push_log_criteria<'a, DB>(query: &mut QueryBuilder<'a, DB>, log_query: &LogQuery)
+where
+    DB: Database,
+    UserId: Encode<'a, DB> + Type<DB>,
+    Date: Encode<'a, DB> + Type<DB>,
+{
+    if let Some(author) = log_query.author {
+        query.push(" AND log.author = ");
+        query.push_bind(author);
+    }
+    push_dates(query, log_query.since, log_query.until);
+}
+
+/// Pushes the conditions on the project, author and creation date of the
+/// logs of the search, after a `WHERE` clause on the `log` table
+pub(super) fn push_search_criteria<'a, DB>(query: &mut QueryBuilder<'a, DB>, search: &LogSearch)
+where
+    DB: Database,
+    ProjectId: Encode<'a, DB> + Type<DB>,
+    UserId: Encode<'a, DB> + Type<DB>,
+    Date: Encode<'a, DB> + Type<DB>,
+{
+    query.push(" AND log.project IN (");
+    let mut projects = query.separated(",");
+    for project in &search.projects {
+        projects.push_bind(*project);
+    }
+    projects.push_unseparated(")");
+    for (authors, clause) in [
+        (&search.authors, " AND log.author IN ("),
+        (&search.excluded_authors, " AND log.author NOT IN ("),
+    ] {
+        if authors.is_empty() {
+            continue;
+        }
+        query.push(clause);
+        let mut ids = query.separated(",");
+        for author in authors {
+            ids.push_bind(*author);
+        }
+        ids.push_unseparated(")");
+    }
+    push_dates(query, search.since, search.until);
+}
+
+/// Pushes the conditions on the owner and last activity of the projects of
+/// the search, after a `WHERE` clause on the `project` table left joined
+/// with their logs
+pub(super) fn push_activity_criteria<'a, DB>(
+    query: &mut QueryBuilder<'a, DB>,
+    search: &ProjectSearch,
+) where
+    DB: Database,
+    UserId: Encode<'a, DB> + Type<DB>,
+    Date: Encode<'a, DB> + Type<DB>,
+{
+    if let Some(owner) = search.owner {
+        query.push(" AND project.author = ");
+        query.push_bind(owner);
+    }
+    query.push(" GROUP BY project.id");
+    if let Some(since) = search.active_since {
+        query.push(" HAVING COALESCE(MAX(log.created), project.created) >= ");
+        query.push_bind(since);
+    }
+    query.push(" ORDER BY project.id");
+}
+
+/// Pushes the conditions of the audit query, after a `WHERE` clause on the
+/// `audit` table
+pub(super) fn push_audit_criteria<'a, DB>(query: &mut QueryBuilder<'a, DB>, audit: &AuditQuery)
+where
+    DB: Database,
+    UserId: Encode<'a, DB> + Type<DB>,
+    String: Encode<'a, DB> + Type<DB>,
+{
+    if let Some(actor) = audit.actor {
+        query.push(" AND actor = ");
+        query.push_bind(actor);
+    }
+    if let Some(action) = &audit.action {
+        query.push(" AND action = ");
+        query.push_bind(action.clone());
+    }
+    if let Some(target) = &audit.target {
+        query.push(" AND target = ");
+        query.push_bind(target.clone());
+    }
+}
+
+/// Row of the `audit` table
+#[derive(sqlx::FromRow)]
+pub(super) struct AuditRow {
+    actor: UserId,
+    action: String,
+    target: String,
+    before_summary: Option<String>,
+    after_summary: Option<String>,
+    created: Date,
+    origin: String,
+}
+impl AuditRow {
+    pub(super) const COLUMNS: &'static str =
+        "actor,action,target,before_summary,after_summary,created,origin";
+
+    pub(super) fn entry(self) -> Result<AuditEntry, RepoError> {
+        Ok(AuditEntry {
+            actor: self.actor,
+            action: self.action,
+            target: self.target,
+            before: self.before_summary,
+            after: self.after_summary,
+            at: self.created,
+            origin: Origin::from_str(&self.origin).map_err(RepoError::Corrupt)?,
+        })
+    }
+}
+
+fn push_dates<'a, DB>(query: &mut QueryBuilder<'a, DB>, since: Option<Date>, until: Option<Date>)
+where
+    DB: Database,
+    Date: Encode<'a, DB> + Type<DB>,
+{
+    if let Some(since) = since {
+        query.push(" AND log.created >= ");
+        query.push_bind(since);
+    }
+    if let Some(until) = until {
+        query.push(" AND log.created < ");
+        query.push_bind(until);
+    }
+}
+
+/// Pushes the end of a search query: the hits of the numbered page, best
+/// `score` first, then the newest
+pub(super) fn push_ranked_page<'a, DB>(query: &mut QueryBuilder<'a, DB>, page: &Page)
+where
+    DB: Database,
+    i64: Encode<'a, DB> + Type<DB>,
+{
+    query.push(" ORDER BY score DESC, log.id DESC LIMIT ");
+    query.push_bind(page.page_size() as i64);
+    query.push(" OFFSET ");
+    query.push_bind(page.offset() as i64);
+}
+
+/// Splits hits into their logs, to load their fields, and a function putting
+/// the logs back into the hits, whose snippets become markdown
+pub(super) fn hit_logs(hits: Vec<LogHit>) -> (Vec<Log>, impl FnOnce(Vec<Log>) -> Vec<LogHit>) {
+    let (logs, rest): (Vec<_>, Vec<_>) = hits
+        .into_iter()
+        .map(|hit| (hit.log, (hit.project, hit.snippet, hit.score)))
+        .unzip();
+    let rejoin = |logs: Vec<Log>| {
+        logs.into_iter()
+            .zip(rest)
+            .map(|(log, (project, snippet, score))| LogHit {
+                log,
+                project,
+                snippet: query::highlighted(&snippet),
+                score,
+            })
+            .collect()
+    };
+    (logs, rejoin)
+}
+
+/// Keys of the names, as computed by `key`. Fails on names having the same
+/// key, which the naming policy made the same name.
+pub(super) fn name_keys<I, N: Display>(
+    kind: &str,
+    names: impl IntoIterator<Item = (I, N)>,
+    key: impl Fn(&N) -> String,
+) -> anyhow::Result<Vec<(I, String)>> {
+    let mut keys = vec![];
+    let mut named: HashMap<String, Vec<String>> = HashMap::new();
+    for (id, name) in names {
+        let key = key(&name);
+        named.entry(key.clone()).or_default().push(name.to_string());
+        keys.push((id, key));
+    }
+    let mut same: Vec<_> = named
+        .into_values()
+        .filter(|names| names.len() > 1)
+        .map(|mut names| {
+            names.sort();
+            names.join(" and ")
+        })
+        .collect();
+    if !same.is_empty() {
+        same.sort();
+        anyhow::bail!(
+            "{kind} {} have the same name under the naming policy",
+            same.join(", ")
+        );
+    }
+    Ok(keys)
+}
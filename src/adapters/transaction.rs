@@ -0,0 +1,74 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use sqlx::{pool::PoolConnection, Database, Pool, Transaction};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+/// Transaction shared by the clones of a repository returned by
+/// `UnitOfWork::begin`, `None` once committed or rolled back
+pub(super) type Shared<DB> = Arc<Mutex<Option<Transaction<'static, DB>>>>;
+
+/// Connection a query runs on: one of the pool, or the open transaction
+pub(super) enum Conn<DB: Database> {
+    Pool(PoolConnection<DB>),
+    Transaction(OwnedMutexGuard<Option<Transaction<'static, DB>>>),
+}
+
+impl<DB: Database> Deref for Conn<DB> {
+    type Target = DB::Connection;
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Conn::Pool(conn) => conn,
+            Conn::Transaction(tx) => tx.as_ref().expect("checked by `connection`"),
+        }
+    }
+}
+
+impl<DB: Database> DerefMut for Conn<DB> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Conn::Pool(conn) => conn,
+            Conn::Transaction(tx) => tx.as_mut().expect("checked by `connection`"),
+        }
+    }
+}
+
+/// Connection for the next query: the transaction if there is one, which is
+/// locked until the connection is dropped
+pub(super) async fn connection<DB: Database>(
+    pool: &Pool<DB>,
+    transaction: Option<&Shared<DB>>,
+) -> Result<Conn<DB>, sqlx::Error> {
+    let Some(transaction) = transaction else {
+        return Ok(Conn::Pool(pool.acquire().await?));
+    };
+    let tx = transaction.clone().lock_owned().await;
+    if tx.is_none() {
+        // the transaction is over, its repository must not be used anymore
+        return Err(sqlx::Error::PoolClosed);
+    }
+    Ok(Conn::Transaction(tx))
+}
+
+pub(super) async fn begin<DB: Database>(pool: &Pool<DB>) -> Result<Shared<DB>, sqlx::Error> {
+    Ok(Arc::new(Mutex::new(Some(pool.begin().await?))))
+}
+
+/// Commits or rolls back the transaction, failing if it is already over
+pub(super) async fn finish<DB: Database>(
+    transaction: &Shared<DB>,
+    commit: bool,
+) -> Result<(), sqlx::Error> {
+    let tx = transaction
+        .lock()
+        .await
+        .take()
+        .ok_or(sqlx::Error::PoolClosed)?;
+    if commit {
+        tx.commit().await
+    } else {
+        tx.rollback().await
+    }
+}
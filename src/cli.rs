@@ -1,16 +1,33 @@
-use std::str::FromStr;
+use std::{num::NonZeroUsize, str::FromStr};
 
 use clap::{ArgGroup, Args, Parser};
 use derive_more::derive::Display;
+use futures_util::{Stream, StreamExt};
 use itertools::Itertools;
+#[cfg(feature = "journal")]
+use reverie::JournalRepo;
+#[cfg(feature = "postgres")]
+use reverie::PostgresRepo;
 use reverie::{
-    LocalLogStoreService, LogService, Page, ProjectId, ProjectName, SqliteRepo, UserId, Username,
+    AuditQuery, Config, Cursor, Database, Date, EntryId, FieldFilter, FieldKind, InMemoryRepo,
+    LocalLogStoreService, LogFields, LogQuery, LogSearch, LogService, LogServiceError, Origin,
+    Page, ProjectId, ProjectName, ProjectSearch, Role, SortOrder, SqliteRepo, TeamId, TeamName,
+    Template, TemplateName, TextFormat, UserId, Username, ViewName,
 };
 use tracing_subscriber::{filter, fmt, layer::SubscriberExt};
+
+/// Number of related logs shown after a new log
+const RELATED_LOGS: usize = 3;
 #[derive(Debug, Parser)]
 pub struct CliArgs {
     #[clap(subcommand)]
     cmd: CmdArgs,
+    /// print log texts as written instead of rendering their markdown
+    #[clap(long, global = true)]
+    raw: bool,
+    /// work on an empty in-memory database, dropped on exit
+    #[clap(long, global = true)]
+    ephemeral: bool,
 }
 #[derive(Debug, clap::Subcommand)]
 enum CmdArgs {
@@ -22,12 +39,69 @@ enum CmdArgs {
     Id(IdArgs),
     #[clap(subcommand)]
     Search(SearchArgs),
+    /// run a saved search, see `new view`
+    View(ViewArgs),
+    /// pin a log on top of its project
+    Pin(PinArgs),
+    Unpin(PinArgs),
+    /// change the role of a member of a project
+    Role(RoleArgs),
+    /// hand a project over to another owner, staying its maintainer
+    Transfer(TransferArgs),
+    #[clap(subcommand)]
+    Remove(RemoveArgs),
+    /// print every entry as JSON, one per line
+    #[clap(subcommand)]
+    Export(ExportArgs),
+    /// list the recorded changes, newest first
+    Audit(AuditArgs),
+    /// manage the schema of the database
+    #[clap(subcommand)]
+    Db(DbArgs),
+}
+#[derive(Debug, clap::Subcommand)]
+pub enum ExportArgs {
+    Logs(ListFieldsArgs),
+    Projects(ExportProjectsArgs),
+}
+#[derive(Debug, Args, Clone)]
+pub struct ExportProjectsArgs {
+    owner: UserIdOrNameArg,
+    /// user reading the projects, who only exports the ones they may read
+    #[clap(short, long)]
+    user: UserIdOrNameArg,
+}
+#[derive(Debug, clap::Subcommand)]
+pub enum DbArgs {
+    /// apply the pending migrations
+    Migrate,
+    /// show the schema version and the pending migrations
+    Status,
+    /// rebuild the tables from the recorded events
+    Rebuild,
 }
 #[derive(Debug, clap::Subcommand)]
 pub enum NewArgs {
     Log(NewLogArgs),
     User(UsernameArg),
-    Project(UserProjectArgs),
+    Project(NewProjectArgs),
+    Template(NewTemplateArgs),
+    /// save a search under a name, replacing the view with the same name
+    View(NewViewArgs),
+    /// declare a custom field on the logs of a project
+    Field(NewFieldArgs),
+    /// add a user to the members of a project, with their role
+    Member(NewMemberArgs),
+    /// create a team, whose members share its projects
+    Team(NewTeamArgs),
+    /// add a user to the members of a team, with the role they get on its
+    /// projects
+    TeamMember(NewTeamMemberArgs),
+}
+#[derive(Debug, clap::Subcommand)]
+pub enum RemoveArgs {
+    /// remove a member of a project, or leave it when removing oneself
+    Member(RemoveMemberArgs),
 }
 #[derive(Debug, clap::Subcommand)]
 pub enum IdArgs {
@@ -41,15 +115,40 @@ pub enum SearchArgs {
 }
 #[derive(Debug, Args, Clone)]
 pub struct SearchLogsArgs {
+    /// searching user, whose projects are searched
     #[clap(short, long)]
-    user: Username,
-    #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
-    desc: String,
-    // before: Date,
-    // after: Date,
+    user: UserIdOrNameArg,
+    /// only logs written on or after the day, as YYYY-MM-DD
+    #[clap(long)]
+    since: Option<Date>,
+    /// only logs written before the day, as YYYY-MM-DD
+    #[clap(long)]
+    until: Option<Date>,
+    #[clap(long, default_value = "1")]
+    page: NonZeroUsize,
+    #[clap(long, default_value = "10")]
+    size: NonZeroUsize,
+    /// search such as `disk "full again" author:me -project:web kind:task`,
+    /// an argument holding spaces being a phrase
+    #[clap(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+    desc: Vec<String>,
 }
 #[derive(Debug, Args, Clone)]
-pub struct SearchProjectsArgs {}
+pub struct SearchProjectsArgs {
+    /// start of the name, part of it, or the name with a few typos
+    name: String,
+    /// only the projects of the user
+    #[clap(short, long)]
+    owner: Option<UserIdOrNameArg>,
+    /// user searching among the projects they may read
+    #[clap(short, long)]
+    user: UserIdOrNameArg,
+    /// only the projects created or logged to on or after the day, as YYYY-MM-DD
+    #[clap(long)]
+    active_since: Option<Date>,
+    #[clap(long, default_value = "10")]
+    limit: usize,
+}
 #[derive(Debug, Args, Clone)]
 pub struct LogDescriptionArgs {
     #[clap(trailing_var_arg = true, allow_hyphen_values = false)]
@@ -63,7 +162,155 @@ pub struct NewLogArgs {
     author: UserIdOrNameArg,
     #[clap(short, long)]
     project: ProjectIdOrNameArg,
-    text: String,
+    /// write the log from a template, in $EDITOR unless every field is given
+    #[clap(short, long, conflicts_with = "text")]
+    template: Option<TemplateName>,
+    /// field value as `key=value` (repeatable)
+    #[clap(short, long = "field", value_parser = parse_field)]
+    fields: Vec<(String, String)>,
+    #[clap(required_unless_present = "template")]
+    text: Option<String>,
+}
+#[derive(Debug, Args, Clone)]
+pub struct NewProjectArgs {
+    name: ProjectName,
+    owner: UserIdOrNameArg,
+    /// first log of the project, written by the owner
+    #[clap(short, long, conflicts_with = "team")]
+    log: Option<String>,
+    /// team owning the project, which the owner must maintain
+    #[clap(short, long)]
+    team: Option<TeamName>,
+}
+#[derive(Debug, Args, Clone)]
+pub struct NewFieldArgs {
+    name: String,
+    /// string, number, date (YYYY-MM-DD), user or enum:a,b,c
+    kind: FieldKind,
+    #[clap(short, long)]
+    project: ProjectIdOrNameArg,
+    /// user declaring the field, who must maintain the project
+    #[clap(short, long)]
+    user: UserIdOrNameArg,
+    #[clap(short, long)]
+    required: bool,
+}
+#[derive(Debug, Args, Clone)]
+pub struct NewMemberArgs {
+    member: UserIdOrNameArg,
+    #[clap(short, long)]
+    project: ProjectIdOrNameArg,
+    /// reader, writer or maintainer
+    #[clap(short, long, default_value = "writer")]
+    role: Role,
+    /// user adding the member, who must maintain the project
+    #[clap(short, long)]
+    user: UserIdOrNameArg,
+}
+#[derive(Debug, Args, Clone)]
+pub struct NewTeamArgs {
+    name: TeamName,
+    owner: UserIdOrNameArg,
+}
+#[derive(Debug, Args, Clone)]
+pub struct NewTeamMemberArgs {
+    member: UserIdOrNameArg,
+    #[clap(short, long)]
+    team: TeamName,
+    /// reader, writer or maintainer
+    #[clap(short, long, default_value = "writer")]
+    role: Role,
+    /// user adding the member, who must maintain the team
+    #[clap(short, long)]
+    user: UserIdOrNameArg,
+}
+#[derive(Debug, Args, Clone)]
+pub struct RemoveMemberArgs {
+    member: UserIdOrNameArg,
+    #[clap(short, long)]
+    project: ProjectIdOrNameArg,
+    /// user removing the member, who must maintain the project unless they
+    /// are the member
+    #[clap(short, long)]
+    user: UserIdOrNameArg,
+}
+#[derive(Debug, Args, Clone)]
+pub struct RoleArgs {
+    member: UserIdOrNameArg,
+    /// reader, writer or maintainer
+    role: Role,
+    #[clap(short, long)]
+    project: ProjectIdOrNameArg,
+    /// user changing the role, who must maintain the project
+    #[clap(short, long)]
+    user: UserIdOrNameArg,
+}
+#[derive(Debug, Args, Clone)]
+pub struct TransferArgs {
+    project: ProjectIdOrNameArg,
+    /// new owner of the project
+    owner: UserIdOrNameArg,
+    /// current owner of the project
+    #[clap(short, long)]
+    user: UserIdOrNameArg,
+}
+#[derive(Debug, Args, Clone)]
+pub struct NewTemplateArgs {
+    name: TemplateName,
+    #[clap(short, long)]
+    author: UserIdOrNameArg,
+    /// share the template with the project instead of keeping it personal
+    #[clap(short, long)]
+    project: Option<ProjectIdOrNameArg>,
+    /// text of the template, with `{{field}}` placeholders
+    body: String,
+}
+#[derive(Debug, Args, Clone)]
+pub struct NewViewArgs {
+    name: ViewName,
+    #[clap(short, long)]
+    author: UserIdOrNameArg,
+    /// share the view with the members of the project
+    #[clap(short, long)]
+    project: Option<ProjectIdOrNameArg>,
+    /// search such as `author:me kind:blocker -status:done`, see `search logs`
+    #[clap(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+    query: Vec<String>,
+}
+#[derive(Debug, Args, Clone)]
+pub struct ViewArgs {
+    name: ViewName,
+    /// user running the view, `me` in its search
+    #[clap(short, long)]
+    user: UserIdOrNameArg,
+    #[clap(long, default_value = "1")]
+    page: NonZeroUsize,
+    #[clap(long, default_value = "10")]
+    size: NonZeroUsize,
+}
+#[derive(Debug, Args, Clone)]
+pub struct AuditArgs {
+    /// only the changes made by the user
+    #[clap(short, long)]
+    actor: Option<UserIdOrNameArg>,
+    /// only the changes of a kind, such as `log_added` or `member_removed`
+    #[clap(long)]
+    action: Option<String>,
+    /// only the changes of the entry with the id
+    #[clap(short, long)]
+    target: Option<String>,
+    #[clap(long, default_value = "1")]
+    page: NonZeroUsize,
+    #[clap(long, default_value = "10")]
+    size: NonZeroUsize,
+}
+#[derive(Debug, Args, Clone)]
+pub struct PinArgs {
+    #[clap(short, long)]
+    user: UserIdOrNameArg,
+    #[clap(short, long)]
+    project: ProjectIdOrNameArg,
+    log: EntryId,
 }
 #[derive(Debug, Args, Clone)]
 pub struct UsernameArg {
@@ -78,6 +325,18 @@ pub struct UserProjectArgs {
 enum ListArgs {
     Logs(ListLogsArgs),
     Projects(ListProjectsArgs),
+    Templates(ListTemplatesArgs),
+    /// views of the user and views shared with their projects
+    Views(ListViewsArgs),
+    Fields(ListFieldsArgs),
+    /// owner and members of a project, with their roles
+    Members(ListFieldsArgs),
+    /// teams the user owns or belongs to
+    Teams(ListViewsArgs),
+    /// owner and members of a team, with their roles
+    TeamMembers(ListTeamArgs),
+    /// projects of a team
+    TeamProjects(ListTeamArgs),
     #[cfg(feature = "admin")]
     Users(PageArgs),
 }
@@ -90,9 +349,59 @@ pub struct ListProjectsArgs {
 #[derive(Debug, Args, Clone)]
 pub struct ListLogsArgs {
     project: ProjectIdOrNameArg,
+    /// user reading the logs, who must be able to read the project
+    #[clap(short, long)]
+    user: UserIdOrNameArg,
+    /// only logs whose field matches, e.g. `env=prod` or `severity>=2` (repeatable)
+    #[clap(short, long = "field")]
+    filters: Vec<FieldFilter>,
+    /// only logs written by the user
+    #[clap(long)]
+    by: Option<Username>,
+    /// only logs written on or after the day, as YYYY-MM-DD
+    #[clap(long)]
+    since: Option<Date>,
+    /// only logs written before the day, as YYYY-MM-DD
+    #[clap(long)]
+    until: Option<Date>,
+    /// `asc` for oldest first, `desc` for newest first
+    #[clap(long, default_value = "asc")]
+    sort: SortOrder,
     #[clap(flatten)]
     pagination: PageArgs,
 }
+#[derive(Debug, Args, Clone)]
+pub struct ListFieldsArgs {
+    project: ProjectIdOrNameArg,
+    /// user reading the project
+    #[clap(short, long)]
+    user: UserIdOrNameArg,
+}
+#[derive(Debug, Args, Clone)]
+pub struct ListTeamArgs {
+    team: TeamName,
+    /// user reading the team, who must belong to it
+    #[clap(short, long)]
+    user: UserIdOrNameArg,
+}
+#[derive(Debug, Args, Clone)]
+pub struct ListTemplatesArgs {
+    user: UserIdOrNameArg,
+    #[clap(short, long)]
+    project: Option<ProjectIdOrNameArg>,
+}
+#[derive(Debug, Args, Clone)]
+pub struct ListViewsArgs {
+    user: UserIdOrNameArg,
+}
+fn parse_field(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("invalid field {s}, expected key=value")),
+    }
+}
 #[derive(Debug, Clone, clap::Args)]
 #[clap(group(
     ArgGroup::new("user")
@@ -162,46 +471,114 @@ pub struct ProjectLogArg {
 }
 #[derive(Debug, Args, Clone)]
 pub struct PageArgs {
-    #[clap(long, default_value = "1")]
-    page: usize,
+    #[clap(long, default_value = "1", conflicts_with_all = ["after", "before"])]
+    page: NonZeroUsize,
     #[clap(long, default_value = "100")]
-    size: usize,
-}
-impl From<ProjectLogArg> for ProjectLog {
-    fn from(ProjectLogArg { project, content }: ProjectLogArg) -> Self {
-        Self::new(project, content)
-    }
+    size: NonZeroUsize,
+    /// list the entries following this cursor, as printed after a page
+    #[clap(long, conflicts_with = "before")]
+    after: Option<Cursor>,
+    /// list the entries preceding this cursor
+    #[clap(long)]
+    before: Option<Cursor>,
 }
 impl From<PageArgs> for Page {
-    fn from(PageArgs { page, size }: PageArgs) -> Self {
-        Self::new(page, size)
+    fn from(
+        PageArgs {
+            page,
+            size,
+            after,
+            before,
+        }: PageArgs,
+    ) -> Self {
+        match (after, before) {
+            (Some(after), _) => Self::after(after, size.get()),
+            (_, Some(before)) => Self::before(before, size.get()),
+            (None, None) => Self::new(page.get(), size.get()),
+        }
     }
 }
 
+/// Id of the user, looked up by name if needed
 async fn get_user_id<T>(
     UserIdOrNameArg { id, name }: UserIdOrNameArg,
     service: &T,
-) -> Option<UserId>
+) -> Result<UserId, LogServiceError>
 where
     T: LocalLogStoreService,
 {
     match (id, name) {
-        (Some(id), _) => Some(id),
-        (_, Some(name)) => service.get_user(name).await.map(|u| u.id()),
-        (_, _) => None,
+        (Some(id), _) => Ok(id),
+        (_, Some(name)) => service
+            .get_user(name)
+            .await?
+            .map(|u| u.id())
+            .ok_or(LogServiceError::UserNotFound),
+        (_, _) => Err(LogServiceError::UserNotFound),
     }
 }
+/// Id of the project, looked up by name among those the reader may read if
+/// needed
 async fn get_project_id<T>(
     ProjectIdOrNameArg { id, name }: ProjectIdOrNameArg,
+    reader: UserId,
     service: &T,
-) -> Option<ProjectId>
+) -> Result<ProjectId, LogServiceError>
 where
     T: LocalLogStoreService,
 {
     match (id, name) {
-        (Some(id), _) => Some(id),
-        (_, Some(name)) => service.get_project(name).await.map(|p| p.id()),
-        (_, _) => None,
+        (Some(id), _) => Ok(id),
+        (_, Some(name)) => match service.get_project(reader, name.clone()).await? {
+            Some(project) => Ok(project.id()),
+            None => {
+                suggest_projects(&name, reader, service).await;
+                Err(LogServiceError::ProjectNotFound)
+            }
+        },
+        (_, _) => Err(LogServiceError::ProjectNotFound),
+    }
+}
+/// Id of the team named so
+async fn get_team_id<T>(name: TeamName, service: &T) -> Result<TeamId, LogServiceError>
+where
+    T: LocalLogStoreService,
+{
+    match service.get_team(name).await? {
+        Some(team) => Ok(team.id()),
+        None => Err(LogServiceError::TeamNotFound),
+    }
+}
+/// Tells the reader which of their projects they may have meant instead of
+/// the missing one, if any
+async fn suggest_projects<T>(name: &ProjectName, reader: UserId, service: &T)
+where
+    T: LocalLogStoreService,
+{
+    let search = ProjectSearch::new(name.to_string()).limit(3);
+    let Ok(found) = service.search_projects(reader, search).await else {
+        return;
+    };
+    if !found.is_empty() {
+        let names = found.iter().map(|m| m.activity.project.name()).join(", ");
+        eprintln!("did you mean {names}?");
+    }
+}
+/// Prints the logs related to the new log, which may be about an issue
+/// already discussed elsewhere. Failing to find them does not fail the
+/// command.
+async fn print_related<T>(service: &T, user: UserId, log: EntryId, format: TextFormat)
+where
+    T: LocalLogStoreService,
+{
+    let Ok(related) = service.related_logs(user, log, RELATED_LOGS).await else {
+        return;
+    };
+    if !related.is_empty() {
+        println!("---\nrelated:");
+        for hit in related {
+            println!("{}", hit.rendered(format))
+        }
     }
 }
 #[tokio::main]
@@ -212,114 +589,594 @@ async fn main() {
         .with(filter::EnvFilter::from_default_env())
         .init();
 
-    let repo = SqliteRepo::new("/tmp/db.sqlite").await.unwrap();
-    let service = LogService::new(repo);
+    let config = Config::from_env();
+    // names in the arguments are parsed under the configured policy
+    if let Err(e) = config.naming.clone().install() {
+        exit_with(e.into());
+    }
+    let CliArgs {
+        cmd,
+        raw,
+        ephemeral,
+    } = CliArgs::parse();
+    let format = if raw {
+        TextFormat::Raw
+    } else {
+        TextFormat::Ansi
+    };
 
-    let CliArgs { cmd } = CliArgs::parse();
+    let database = match config.database {
+        _ if ephemeral => Database::Memory,
+        Database::None => Database::default(),
+        database => database,
+    };
+    if let CmdArgs::Db(cmd) = cmd {
+        if let Err(e) = run_db(cmd, database).await {
+            eprintln!("{e:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    let result = match database {
+        Database::Sqlite(path) => {
+            let repo = SqliteRepo::new(&path.to_string_lossy())
+                .await
+                .unwrap_or_else(|e| exit_with(e));
+            run(cmd, format, LogService::new(repo).with_origin(Origin::Cli)).await
+        }
+        #[cfg(feature = "postgres")]
+        Database::Postgres(url) => {
+            let repo = PostgresRepo::new(&url)
+                .await
+                .unwrap_or_else(|e| exit_with(e));
+            run(cmd, format, LogService::new(repo).with_origin(Origin::Cli)).await
+        }
+        #[cfg(feature = "journal")]
+        Database::Journal(path) => {
+            let repo = JournalRepo::open(&path).unwrap_or_else(|e| exit_with(e));
+            run(cmd, format, LogService::new(repo).with_origin(Origin::Cli)).await
+        }
+        Database::Memory => {
+            let repo = InMemoryRepo::new();
+            run(cmd, format, LogService::new(repo).with_origin(Origin::Cli)).await
+        }
+        Database::None => unreachable!(),
+    };
+    if let Err(e) = result {
+        eprintln!("{e:#}");
+        std::process::exit(exit_code(&e));
+    }
+}
 
+fn exit_with(error: anyhow::Error) -> ! {
+    eprintln!("{error:#}");
+    std::process::exit(1)
+}
+
+/// Schema commands work on the database itself, without applying the
+/// migrations first
+async fn run_db(cmd: DbArgs, database: Database) -> anyhow::Result<()> {
+    match database {
+        Database::Sqlite(path) => {
+            let repo = SqliteRepo::open(&path.to_string_lossy()).await?;
+            match cmd {
+                DbArgs::Migrate => println!("applied {} migration(s)", repo.migrate().await?),
+                DbArgs::Status => println!("{}", repo.schema_status().await?),
+                DbArgs::Rebuild => {
+                    repo.migrate().await?;
+                    println!("replayed {} event(s)", repo.rebuild().await?)
+                }
+            }
+        }
+        #[cfg(feature = "postgres")]
+        Database::Postgres(url) => {
+            let repo = PostgresRepo::open(&url).await?;
+            match cmd {
+                DbArgs::Migrate => println!("applied {} migration(s)", repo.migrate().await?),
+                DbArgs::Status => println!("{}", repo.schema_status().await?),
+                DbArgs::Rebuild => {
+                    repo.migrate().await?;
+                    println!("replayed {} event(s)", repo.rebuild().await?)
+                }
+            }
+        }
+        #[cfg(feature = "journal")]
+        Database::Journal(_) => match cmd {
+            DbArgs::Rebuild => println!("journals are rebuilt from their events when opened"),
+            _ => println!("journals have no schema"),
+        },
+        Database::Memory => println!("in-memory databases have no schema"),
+        Database::None => unreachable!(),
+    }
+    Ok(())
+}
+
+async fn run<S>(cmd: CmdArgs, format: TextFormat, service: S) -> anyhow::Result<()>
+where
+    S: LocalLogStoreService,
+{
     match cmd {
         CmdArgs::New(new) => match new {
             NewArgs::Log(NewLogArgs {
                 author,
                 project,
+                template,
+                fields,
                 text,
             }) => {
-                let project_id = get_project_id(project, &service).await;
-                let user_id = get_user_id(author, &service).await;
-                if project_id.is_none() {
-                    return println!("project not found");
-                }
-                if user_id.is_none() {
-                    return println!("user not found");
-                }
-                match service
-                    .add_log(user_id.unwrap(), project_id.unwrap(), text)
-                    .await
-                {
-                    Ok(log) => println!("{log}"),
-                    Err(e) => println!("{e}"),
-                }
+                let user_id = get_user_id(author, &service).await?;
+                let project_id = get_project_id(project, user_id, &service).await?;
+                let mut fields: LogFields = fields.into_iter().collect();
+                let text = match (template, text) {
+                    (_, Some(text)) => text,
+                    (Some(template), None) => {
+                        let template = service
+                            .template(template, user_id, Some(project_id))
+                            .await?;
+                        write_from_template(&template, &mut fields).map_err(anyhow::Error::msg)?
+                    }
+                    (None, None) => anyhow::bail!("no text"),
+                };
+                let log = service.add_log(user_id, project_id, text, fields).await?;
+                let id = log.id();
+                println!("{}", log.rendered(format));
+                print_related(&service, user_id, id, format).await
             }
-            NewArgs::User(UsernameArg { username }) => match service.new_user(username).await {
-                Ok(user) => println!("created {user}"),
-                Err(_) => println!("Could not create user"),
-            },
-            NewArgs::Project(UserProjectArgs {
+            NewArgs::User(UsernameArg { username }) => {
+                println!("created {}", service.new_user(username).await?)
+            }
+            NewArgs::Project(NewProjectArgs {
                 name: project,
                 owner,
+                log,
+                team,
             }) => {
-                if let Some(user_id) = get_user_id(owner, &service).await {
-                    match service.new_project(project, user_id).await {
-                        Ok(project) => println!("created {project}"),
-                        Err(e) => print!("{e}"),
+                let user_id = get_user_id(owner, &service).await?;
+                match (log, team) {
+                    (_, Some(team)) => {
+                        let team_id = get_team_id(team, &service).await?;
+                        let project = service.new_team_project(project, user_id, team_id).await?;
+                        println!("created {project}")
+                    }
+                    (None, None) => {
+                        println!("created {}", service.new_project(project, user_id).await?)
+                    }
+                    (Some(text), None) => {
+                        let (project, log) =
+                            service.new_project_with_log(project, user_id, text).await?;
+                        println!("created {project}\n{}", log.rendered(format))
                     }
-                } else {
-                    println!("user not found");
                 }
             }
+            NewArgs::Template(NewTemplateArgs {
+                name,
+                author,
+                project,
+                body,
+            }) => {
+                let user_id = get_user_id(author, &service).await?;
+                let project_id = match project {
+                    Some(project) => Some(get_project_id(project, user_id, &service).await?),
+                    None => None,
+                };
+                let template = service
+                    .new_template(name, user_id, project_id, body)
+                    .await?;
+                println!("created {template}")
+            }
+            NewArgs::View(NewViewArgs {
+                name,
+                author,
+                project,
+                query,
+            }) => {
+                let user_id = get_user_id(author, &service).await?;
+                let project_id = match project {
+                    Some(project) => Some(get_project_id(project, user_id, &service).await?),
+                    None => None,
+                };
+                let view = service
+                    .save_view(user_id, name, search_query(&query), project_id)
+                    .await?;
+                println!("saved {view}")
+            }
+            NewArgs::Field(NewFieldArgs {
+                name,
+                kind,
+                project,
+                user,
+                required,
+            }) => {
+                let user_id = get_user_id(user, &service).await?;
+                let project_id = get_project_id(project, user_id, &service).await?;
+                let field = service
+                    .declare_field(user_id, project_id, name, kind, required)
+                    .await?;
+                println!("declared {field}")
+            }
+            NewArgs::Member(NewMemberArgs {
+                member,
+                project,
+                role,
+                user,
+            }) => {
+                let user_id = get_user_id(user, &service).await?;
+                let project_id = get_project_id(project, user_id, &service).await?;
+                let member_id = get_user_id(member, &service).await?;
+                let member = service
+                    .add_member(user_id, project_id, member_id, role)
+                    .await?;
+                println!("added member {} as {}", member.user, member.role)
+            }
+            NewArgs::Team(NewTeamArgs { name, owner }) => {
+                let user_id = get_user_id(owner, &service).await?;
+                println!("created {}", service.new_team(name, user_id).await?)
+            }
+            NewArgs::TeamMember(NewTeamMemberArgs {
+                member,
+                team,
+                role,
+                user,
+            }) => {
+                let team_id = get_team_id(team, &service).await?;
+                let user_id = get_user_id(user, &service).await?;
+                let member_id = get_user_id(member, &service).await?;
+                let member = service
+                    .add_team_member(user_id, team_id, member_id, role)
+                    .await?;
+                println!("added team member {} as {}", member.user, member.role)
+            }
         },
         CmdArgs::List(list) => match list {
             ListArgs::Logs(ListLogsArgs {
                 project,
+                user,
+                filters,
+                by,
+                since,
+                until,
+                sort,
                 pagination,
             }) => {
-                if let Some(project_id) = get_project_id(project, &service).await {
-                    service.logs(project_id, pagination.into()).await.display()
-                } else {
-                    println!("project not found");
+                let user_id = get_user_id(user, &service).await?;
+                let mut query =
+                    LogQuery::project(get_project_id(project, user_id, &service).await?)
+                        .fields(filters)
+                        .sort(sort)
+                        .page(pagination.into());
+                if let Some(by) = by {
+                    let author = service
+                        .get_user(by)
+                        .await?
+                        .ok_or(LogServiceError::UserNotFound)?;
+                    query = query.by(author.id());
+                }
+                if let Some(since) = since {
+                    query = query.since(since);
+                }
+                if let Some(until) = until {
+                    query = query.until(until);
                 }
+                let logs = service.project_logs(user_id, query).await?;
+                println!("{}", logs.map(|log| log.rendered(format)))
+            }
+            ListArgs::Fields(ListFieldsArgs { project, user }) => {
+                let user_id = get_user_id(user, &service).await?;
+                let project_id = get_project_id(project, user_id, &service).await?;
+                for field in service.fields(user_id, project_id).await? {
+                    println!("{field}")
+                }
+            }
+            ListArgs::Members(ListFieldsArgs { project, user }) => {
+                let user_id = get_user_id(user, &service).await?;
+                let project_id = get_project_id(project, user_id, &service).await?;
+                for member in service.members(user_id, project_id).await? {
+                    println!("{member}")
+                }
+            }
+            ListArgs::Teams(ListViewsArgs { user }) => {
+                let user_id = get_user_id(user, &service).await?;
+                for team in service.teams_of(user_id).await? {
+                    println!("{team}")
+                }
+            }
+            ListArgs::TeamMembers(ListTeamArgs { team, user }) => {
+                let team_id = get_team_id(team, &service).await?;
+                let user_id = get_user_id(user, &service).await?;
+                for member in service.team_members(user_id, team_id).await? {
+                    println!("{member}")
+                }
+            }
+            ListArgs::TeamProjects(ListTeamArgs { team, user }) => {
+                let team_id = get_team_id(team, &service).await?;
+                let user_id = get_user_id(user, &service).await?;
+                for project in service.team_projects(user_id, team_id).await? {
+                    println!("{project}")
+                }
+            }
+            ListArgs::Templates(ListTemplatesArgs { user, project }) => {
+                let user_id = get_user_id(user, &service).await?;
+                let project_id = match project {
+                    Some(project) => Some(get_project_id(project, user_id, &service).await?),
+                    None => None,
+                };
+                for t in service.templates(user_id, project_id).await? {
+                    println!("{t} ({})\n{}\n", t.placeholders().join(", "), t.body())
+                }
+            }
+            ListArgs::Views(ListViewsArgs { user }) => {
+                let user_id = get_user_id(user, &service).await?;
+                for view in service.views(user_id).await? {
+                    match view.author() == user_id {
+                        true => println!("{view}"),
+                        false => println!("{view} (shared)"),
+                    }
+                }
+            }
+            ListArgs::Projects(ListProjectsArgs { page, user }) => {
+                let user_id = get_user_id(user, &service).await?;
+                println!(
+                    "{}",
+                    service.projects_of(user_id, user_id, page.into()).await?
+                )
             }
-            ListArgs::Projects(ListProjectsArgs {
-                page,
-                user: UserIdOrNameArg { id, name },
-            }) => match (id, name) {
-                (Some(id), _) => println!("{}", service.projects_of(id, page.into()).await),
-                (None, Some(name)) => service.projects_of_named(name, page.into()).await.display(),
-                (_, _) => println!("oops"),
-            },
             ListArgs::Users(page) => {
-                println!("{}", service.list_users(page.into()).await);
+                println!("{}", service.list_users(page.into()).await?);
             }
         },
         CmdArgs::Id(subarg) => match subarg {
-            IdArgs::User(UsernameArg { username }) => {}
-            IdArgs::Project(UserProjectArgs { name, owner }) => {}
+            IdArgs::User(UsernameArg { username }) => match service.get_user(username).await? {
+                Some(user) => println!("{}", user.id()),
+                None => return Err(LogServiceError::UserNotFound.into()),
+            },
+            IdArgs::Project(UserProjectArgs { name, owner }) => {
+                let owner_id = get_user_id(owner, &service).await?;
+                match service.get_project(owner_id, name).await? {
+                    Some(project) => println!("{}", project.id()),
+                    None => return Err(LogServiceError::ProjectNotFound.into()),
+                }
+            }
         },
         CmdArgs::Search(subarg) => match subarg {
-            SearchArgs::Logs(_) => {}
-            SearchArgs::Projects(_) => {}
+            SearchArgs::Logs(SearchLogsArgs {
+                user,
+                since,
+                until,
+                page,
+                size,
+                desc,
+            }) => {
+                let user_id = get_user_id(user, &service).await?;
+                let search =
+                    LogSearch::parse(&search_query(&desc)).map_err(LogServiceError::from)?;
+                let mut search = search.page(Page::new(page.get(), size.get()));
+                if let Some(since) = since {
+                    search = search.since(since);
+                }
+                if let Some(until) = until {
+                    search = search.until(until);
+                }
+                let hits = service.search_logs(user_id, search).await?;
+                println!("{}", hits.map(|hit| hit.rendered(format)))
+            }
+            SearchArgs::Projects(SearchProjectsArgs {
+                name,
+                owner,
+                user,
+                active_since,
+                limit,
+            }) => {
+                let user_id = get_user_id(user, &service).await?;
+                let mut search = ProjectSearch::new(name).limit(limit);
+                if let Some(owner) = owner {
+                    search = search.owner(get_user_id(owner, &service).await?);
+                }
+                if let Some(since) = active_since {
+                    search = search.active_since(since);
+                }
+                for found in service.search_projects(user_id, search).await? {
+                    println!("{found}")
+                }
+            }
         },
+        CmdArgs::View(ViewArgs {
+            name,
+            user,
+            page,
+            size,
+        }) => {
+            let user_id = get_user_id(user, &service).await?;
+            let view = service.view(name, user_id).await?;
+            let search = view.search().map_err(LogServiceError::from)?;
+            let hits = service
+                .search_logs(user_id, search.page(Page::new(page.get(), size.get())))
+                .await?;
+            println!("{}", hits.map(|hit| hit.rendered(format)))
+        }
+        CmdArgs::Pin(PinArgs { user, project, log }) => {
+            let user_id = get_user_id(user, &service).await?;
+            let project_id = get_project_id(project, user_id, &service).await?;
+            let log = service.pin(user_id, project_id, log).await?;
+            println!("pinned {}", log.rendered(format))
+        }
+        CmdArgs::Unpin(PinArgs { user, project, log }) => {
+            let user_id = get_user_id(user, &service).await?;
+            let project_id = get_project_id(project, user_id, &service).await?;
+            service.unpin(user_id, project_id, log).await?;
+            println!("unpinned {log}")
+        }
+        CmdArgs::Role(RoleArgs {
+            member,
+            role,
+            project,
+            user,
+        }) => {
+            let user_id = get_user_id(user, &service).await?;
+            let project_id = get_project_id(project, user_id, &service).await?;
+            let member_id = get_user_id(member, &service).await?;
+            let member = service
+                .set_role(user_id, project_id, member_id, role)
+                .await?;
+            println!("{member}")
+        }
+        CmdArgs::Transfer(TransferArgs {
+            project,
+            owner,
+            user,
+        }) => {
+            let user_id = get_user_id(user, &service).await?;
+            let project_id = get_project_id(project, user_id, &service).await?;
+            let owner_id = get_user_id(owner, &service).await?;
+            let project = service
+                .transfer_project(user_id, project_id, owner_id)
+                .await?;
+            println!("transferred {project}")
+        }
+        CmdArgs::Remove(RemoveArgs::Member(RemoveMemberArgs {
+            member,
+            project,
+            user,
+        })) => {
+            let user_id = get_user_id(user, &service).await?;
+            let project_id = get_project_id(project, user_id, &service).await?;
+            let member_id = get_user_id(member, &service).await?;
+            service
+                .remove_member(user_id, project_id, member_id)
+                .await?;
+            println!("removed member {member_id}")
+        }
+        CmdArgs::Export(export) => match export {
+            ExportArgs::Logs(ListFieldsArgs { project, user }) => {
+                let user_id = get_user_id(user, &service).await?;
+                let project_id = get_project_id(project, user_id, &service).await?;
+                export_ndjson(service.stream_logs(user_id, project_id).await?).await?
+            }
+            ExportArgs::Projects(ExportProjectsArgs { owner, user }) => {
+                let user_id = get_user_id(user, &service).await?;
+                let owner_id = get_user_id(owner, &service).await?;
+                export_ndjson(service.stream_projects_of(user_id, owner_id).await?).await?
+            }
+        },
+        CmdArgs::Audit(AuditArgs {
+            actor,
+            action,
+            target,
+            page,
+            size,
+        }) => {
+            let mut query = AuditQuery::default().page(Page::new(page.get(), size.get()));
+            if let Some(actor) = actor {
+                query = query.actor(get_user_id(actor, &service).await?);
+            }
+            if let Some(action) = action {
+                query = query.action(action);
+            }
+            if let Some(target) = target {
+                query = query.target(target);
+            }
+            println!("{}", service.audit_log(query).await?)
+        }
+        CmdArgs::Db(_) => unreachable!("handled before opening the database"),
     }
-    // store.save(&db);
+    Ok(())
 }
 
-trait DisplayMonad {
-    fn display(&self);
+/// The search written as the arguments, quoting back those the shell
+/// unquoted so that they stay phrases
+fn search_query(args: &[String]) -> String {
+    let quoted = |arg: &String| match arg.contains(char::is_whitespace) && !arg.contains('"') {
+        true => format!("\"{arg}\""),
+        false => arg.clone(),
+    };
+    args.iter().map(quoted).join(" ")
 }
 
-impl<T, E> DisplayMonad for Result<T, E>
-where
-    T: std::fmt::Display,
-    E: std::fmt::Display,
-{
-    fn display(&self) {
-        match self {
-            Ok(t) => println!("{t}"),
-            Err(e) => println!("{e}"),
-        }
+/// Exit code of a failed command, following sysexits(3) so scripts can tell
+/// a missing entry from a storage outage
+fn exit_code(error: &anyhow::Error) -> i32 {
+    use LogServiceError::*;
+    match error.downcast_ref::<LogServiceError>() {
+        Some(
+            ProjectNotFound | UserNotFound | LogNotFound | TemplateNotFound | ViewNotFound
+            | MemberNotFound | TeamNotFound | NotFound(_),
+        ) => 66,
+        Some(ProjectExists | UserExists | TemplateExists | TeamExists | Conflict(_)) => 73,
+        Some(
+            LogNotPinned
+            | TooManyPinnedLogs(_)
+            | InvalidField(_)
+            | InvalidUsername(_)
+            | InvalidProjectName(_)
+            | InvalidSearch(_)
+            | InvalidRole(_)
+            | MissingReference(_),
+        ) => 65,
+        Some(NoReadAccess(..) | NoWriteAccess(..) | NoTeamAccess(..)) => 77,
+        Some(Unavailable(_)) => 75,
+        Some(Corrupt(_)) => 74,
+        None => 1,
     }
 }
 
-impl<T> DisplayMonad for Option<T>
+/// Prints the entries as they are read, one JSON object per line. Stops at
+/// the first error.
+async fn export_ndjson<T, E>(
+    mut entries: impl Stream<Item = Result<T, E>> + Unpin,
+) -> anyhow::Result<()>
 where
-    T: std::fmt::Display,
+    T: serde::Serialize,
+    E: Into<anyhow::Error>,
 {
-    fn display(&self) {
-        match self {
-            Some(t) => println!("{t}"),
-            None => println!(" ø "),
-        }
+    use std::io::Write;
+    let mut out = std::io::stdout().lock();
+    while let Some(entry) = entries.next().await {
+        let entry = entry.map_err(|e| e.into().context("export interrupted"))?;
+        writeln!(out, "{}", serde_json::to_string(&entry)?)?;
+    }
+    Ok(())
+}
+
+/// Fills the template with the given fields, letting the user complete the
+/// missing ones in their editor. Fields read back from the edited text are
+/// added to `fields`.
+fn write_from_template(template: &Template, fields: &mut LogFields) -> Result<String, String> {
+    let prefilled = template.prefill(fields);
+    if template.missing(fields).is_empty() {
+        return Ok(prefilled);
+    }
+    let text = edit(&prefilled).map_err(|e| format!("could not edit the log: {e}"))?;
+    if text.trim().is_empty() || text.trim() == prefilled.trim() {
+        return Err("log left unchanged, aborting".into());
+    }
+    match template.extract(&text) {
+        Some(extracted) => fields.extend(extracted),
+        None => eprintln!(
+            "warning: log does not follow template {}, only fields given on the command line are kept",
+            template.name()
+        ),
+    }
+    Ok(text)
+}
+
+/// Opens `text` in $VISUAL or $EDITOR (vi by default) and returns the saved content
+fn edit(text: &str) -> std::io::Result<String> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut command = editor.split_whitespace();
+    let program = command.next().unwrap_or("vi");
+    let path = std::env::temp_dir().join(format!("reverie-{}.md", EntryId::default()));
+    std::fs::write(&path, text)?;
+    let status = std::process::Command::new(program)
+        .args(command)
+        .arg(&path)
+        .status();
+    let edited = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+    if !status?.success() {
+        return Err(std::io::Error::other(format!("{editor} failed")));
     }
+    edited
 }
 
 // we have projects
@@ -0,0 +1,153 @@
+use std::{fmt, str::FromStr};
+
+use derive_more::derive::Display;
+
+use super::model::{Date, UserId};
+use crate::Page;
+
+/// Longest before and after summaries, longer texts being cut
+const SUMMARY_LENGTH: usize = 80;
+
+/// Where a change was asked from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display)]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "dtos", serde(rename_all = "lowercase"))]
+pub enum Origin {
+    #[display("cli")]
+    Cli,
+    #[display("http")]
+    Http,
+    /// a program using the service directly
+    #[default]
+    #[display("library")]
+    Library,
+}
+impl FromStr for Origin {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cli" => Ok(Self::Cli),
+            "http" => Ok(Self::Http),
+            "library" => Ok(Self::Library),
+            _ => Err(format!(
+                "unknown origin {s} (expected cli, http or library)"
+            )),
+        }
+    }
+}
+
+/// Who changed what, when and from where, recorded once the change is done.
+///
+/// The summaries tell the state of the target before and after the change,
+/// `None` when it did not exist.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuditEntry {
+    pub actor: UserId,
+    /// kind of change, named like the events: `log_added`, `member_removed`...
+    pub action: String,
+    /// id of the changed entry
+    pub target: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub at: Date,
+    pub origin: Origin,
+}
+
+impl AuditEntry {
+    pub fn new(actor: UserId, action: &str, target: impl fmt::Display) -> Self {
+        Self {
+            actor,
+            action: action.to_string(),
+            target: target.to_string(),
+            before: None,
+            after: None,
+            at: Date::now(),
+            origin: Origin::default(),
+        }
+    }
+    pub fn before(self, before: impl fmt::Display) -> Self {
+        Self {
+            before: Some(summarize(before)),
+            ..self
+        }
+    }
+    pub fn after(self, after: impl fmt::Display) -> Self {
+        Self {
+            after: Some(summarize(after)),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}] {} {} {}",
+            self.at, self.origin, self.actor, self.action, self.target
+        )?;
+        match (&self.before, &self.after) {
+            (None, None) => Ok(()),
+            (before, after) => write!(
+                f,
+                ": {} -> {}",
+                before.as_deref().unwrap_or("-"),
+                after.as_deref().unwrap_or("-")
+            ),
+        }
+    }
+}
+
+/// First line of the text, cut to `SUMMARY_LENGTH` characters
+fn summarize(text: impl fmt::Display) -> String {
+    let text = text.to_string();
+    let text = text.trim();
+    let line = text.lines().next().unwrap_or_default();
+    match line.chars().count() > SUMMARY_LENGTH || line.len() < text.len() {
+        true => {
+            let cut: String = line.chars().take(SUMMARY_LENGTH).collect();
+            format!("{cut}…")
+        }
+        false => line.to_string(),
+    }
+}
+
+/// Audit entries meeting every criterion, newest first
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    pub actor: Option<UserId>,
+    pub action: Option<String>,
+    pub target: Option<String>,
+    pub page: Page,
+}
+
+impl AuditQuery {
+    pub fn actor(self, actor: UserId) -> Self {
+        Self {
+            actor: Some(actor),
+            ..self
+        }
+    }
+    pub fn action(self, action: impl Into<String>) -> Self {
+        Self {
+            action: Some(action.into()),
+            ..self
+        }
+    }
+    pub fn target(self, target: impl Into<String>) -> Self {
+        Self {
+            target: Some(target.into()),
+            ..self
+        }
+    }
+    pub fn page(self, page: Page) -> Self {
+        Self { page, ..self }
+    }
+    /// Whether the entry meets the criteria
+    pub(crate) fn selects(&self, entry: &AuditEntry) -> bool {
+        self.actor.is_none_or(|actor| entry.actor == actor)
+            && self.action.as_ref().is_none_or(|a| &entry.action == a)
+            && self.target.as_ref().is_none_or(|t| &entry.target == t)
+    }
+}
@@ -0,0 +1,83 @@
+use super::{
+    audit::AuditEntry,
+    field::FieldDefinition,
+    model::{Date, EntryId, Log, Member, Project, ProjectId, Role, User, UserId},
+    team::{Team, TeamMember},
+    template::Template,
+    view::View,
+};
+
+/// Mutation of the store.
+///
+/// Events are immutable and recorded in order: the tables read by the
+/// repositories are projections of the events and can be rebuilt from them.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "dtos", serde(rename_all = "snake_case"))]
+pub enum Event {
+    UserCreated(User),
+    ProjectCreated(Project),
+    TeamCreated(Team),
+    /// adds a user to the members of a team
+    TeamMemberAdded(TeamMember),
+    /// adds a user to the members of a project
+    MemberAdded(Member),
+    MemberRoleChanged {
+        project: ProjectId,
+        user: UserId,
+        role: Role,
+    },
+    MemberRemoved {
+        project: ProjectId,
+        user: UserId,
+    },
+    /// hands the project over to another owner
+    ProjectTransferred {
+        project: ProjectId,
+        owner: UserId,
+    },
+    LogAdded {
+        project: ProjectId,
+        log: Log,
+    },
+    LogPinned {
+        project: ProjectId,
+        log: EntryId,
+        by: UserId,
+        at: Date,
+    },
+    LogUnpinned {
+        project: ProjectId,
+        log: EntryId,
+    },
+    /// declares or replaces a field of a project
+    FieldDeclared(FieldDefinition),
+    TemplateCreated(Template),
+    /// saves a view, replacing the view with the same id
+    ViewSaved(View),
+    /// records who made a change, after the events of the change
+    Audited(AuditEntry),
+}
+
+impl Event {
+    /// Name of the event, as stored next to its data
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::UserCreated(_) => "user_created",
+            Event::ProjectCreated(_) => "project_created",
+            Event::TeamCreated(_) => "team_created",
+            Event::TeamMemberAdded(_) => "team_member_added",
+            Event::MemberAdded(_) => "member_added",
+            Event::MemberRoleChanged { .. } => "member_role_changed",
+            Event::MemberRemoved { .. } => "member_removed",
+            Event::ProjectTransferred { .. } => "project_transferred",
+            Event::LogAdded { .. } => "log_added",
+            Event::LogPinned { .. } => "log_pinned",
+            Event::LogUnpinned { .. } => "log_unpinned",
+            Event::FieldDeclared(_) => "field_declared",
+            Event::TemplateCreated(_) => "template_created",
+            Event::ViewSaved(_) => "view_saved",
+            Event::Audited(_) => "audited",
+        }
+    }
+}
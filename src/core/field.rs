@@ -0,0 +1,204 @@
+use std::str::FromStr;
+
+use derive_more::derive::{Display, Error};
+use itertools::Itertools;
+
+use super::{model::ProjectId, template::LogFields};
+
+/// Type of the values of a custom field
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "dtos", serde(rename_all = "lowercase"))]
+pub enum FieldKind {
+    String,
+    Number,
+    /// one of the listed values
+    Enum(Vec<String>),
+    /// `YYYY-MM-DD`
+    Date,
+    /// a user, given by name or id and stored by id
+    User,
+}
+impl std::fmt::Display for FieldKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldKind::String => write!(f, "string"),
+            FieldKind::Number => write!(f, "number"),
+            FieldKind::Enum(values) => write!(f, "enum:{}", values.join(",")),
+            FieldKind::Date => write!(f, "date"),
+            FieldKind::User => write!(f, "user"),
+        }
+    }
+}
+impl FromStr for FieldKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "string" => Ok(Self::String),
+            None if s == "number" => Ok(Self::Number),
+            None if s == "date" => Ok(Self::Date),
+            None if s == "user" => Ok(Self::User),
+            Some(("enum", values)) => {
+                let values: Vec<String> = values
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty())
+                    .map(String::from)
+                    .unique()
+                    .collect();
+                if values.is_empty() {
+                    Err("enum without values".into())
+                } else {
+                    Ok(Self::Enum(values))
+                }
+            }
+            _ => Err(format!(
+                "unknown field kind {s} (expected string, number, date, user or enum:a,b,c)"
+            )),
+        }
+    }
+}
+
+/// A custom field declared by a project
+#[derive(Debug, Clone, Display)]
+#[display("{name}: {kind}{}", if *required { " (required)" } else { "" })]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldDefinition {
+    pub project: ProjectId,
+    pub name: String,
+    pub kind: FieldKind,
+    pub required: bool,
+}
+
+#[derive(Debug, Display, Error)]
+#[display("invalid field {} ({})", self.0, self.1)]
+pub struct InvalidField(pub String, pub String);
+
+fn is_date(value: &str) -> bool {
+    let parts: Vec<&str> = value.split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return false;
+    };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return false;
+    }
+    let (Ok(year), Ok(month), Ok(day)) = (
+        year.parse::<u32>(),
+        month.parse::<u32>(),
+        day.parse::<u32>(),
+    ) else {
+        return false;
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days).contains(&day)
+}
+
+impl FieldDefinition {
+    /// Checks a single value against the kind of the field. User values are
+    /// checked by the service, which needs to look them up.
+    pub fn check(&self, value: &str) -> Result<(), InvalidField> {
+        let invalid = |reason: String| Err(InvalidField(self.name.clone(), reason));
+        match &self.kind {
+            FieldKind::String | FieldKind::User => Ok(()),
+            FieldKind::Number => match value.trim().parse::<f64>() {
+                Ok(number) if number.is_finite() => Ok(()),
+                _ => invalid(format!("{value} is not a number")),
+            },
+            FieldKind::Enum(values) if values.iter().any(|v| v == value) => Ok(()),
+            FieldKind::Enum(values) => invalid(format!("expected one of {}", values.join(", "))),
+            FieldKind::Date if is_date(value) => Ok(()),
+            FieldKind::Date => invalid(format!("{value} is not a YYYY-MM-DD date")),
+        }
+    }
+}
+
+/// Checks log fields against the fields declared by a project: unknown
+/// fields, missing required fields and values of the wrong kind are
+/// rejected. A project without declared fields accepts any field.
+pub fn validate(schema: &[FieldDefinition], fields: &LogFields) -> Result<(), InvalidField> {
+    if schema.is_empty() {
+        return Ok(());
+    }
+    if let Some(unknown) = fields
+        .keys()
+        .find(|name| !schema.iter().any(|d| &d.name == *name))
+    {
+        return Err(InvalidField(
+            unknown.clone(),
+            "not declared by the project".into(),
+        ));
+    }
+    for definition in schema {
+        match fields.get(&definition.name) {
+            Some(value) => definition.check(value)?,
+            None if definition.required => {
+                return Err(InvalidField(definition.name.clone(), "required".into()))
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum FieldOp {
+    #[display("=")]
+    Eq,
+    #[display("!=")]
+    Ne,
+    #[display("<")]
+    Lt,
+    #[display("<=")]
+    Le,
+    #[display(">")]
+    Gt,
+    #[display(">=")]
+    Ge,
+}
+
+/// Condition on a log field, written `name<op>value` e.g. `severity>=2`
+#[derive(Debug, Clone, Display)]
+#[display("{name}{op}{value}")]
+pub struct FieldFilter {
+    pub name: String,
+    pub op: FieldOp,
+    pub value: String,
+    /// compare values as numbers instead of text
+    pub numeric: bool,
+}
+impl FromStr for FieldFilter {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some(at) = s.find(['=', '!', '<', '>']) else {
+            return Err(format!("invalid filter {s}, expected name=value"));
+        };
+        let (name, rest) = s.split_at(at);
+        let (op, value) = [
+            (">=", FieldOp::Ge),
+            ("<=", FieldOp::Le),
+            ("!=", FieldOp::Ne),
+            ("=", FieldOp::Eq),
+            (">", FieldOp::Gt),
+            ("<", FieldOp::Lt),
+        ]
+        .into_iter()
+        .find_map(|(token, op)| rest.strip_prefix(token).map(|value| (op, value)))
+        .ok_or_else(|| format!("invalid operator in filter {s}"))?;
+        if name.trim().is_empty() {
+            return Err(format!("invalid filter {s}, field name is missing"));
+        }
+        Ok(Self {
+            name: name.trim().to_string(),
+            op,
+            value: value.to_string(),
+            numeric: false,
+        })
+    }
+}
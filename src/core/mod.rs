@@ -1,4 +1,14 @@
+pub mod audit;
+pub mod event;
+pub mod field;
 pub mod model;
+pub mod naming;
 pub mod pagination;
+pub mod query;
 pub mod repo;
+pub mod search;
 pub mod service;
+mod similarity;
+pub mod team;
+pub mod template;
+pub mod view;
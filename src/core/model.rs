@@ -4,13 +4,23 @@ use std::{
 };
 
 use derive_more::derive::{Display, Error};
+use itertools::Itertools;
+
+use super::{
+    naming::Naming,
+    pagination::{Cursor, Keyed, Paged},
+    team::TeamId,
+    template::LogFields,
+};
 
 #[macro_export]
 macro_rules! create_id {
     ($name:ident) => {
-        #[derive(Debug, Clone, Copy)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
         #[cfg_attr(feature = "sqlx", derive(sqlx::Type, sqlx::FromRow))]
         #[cfg_attr(feature = "sqlx", sqlx(transparent))]
+        #[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(feature = "dtos", serde(transparent))]
         pub struct $name(sqlx::types::Uuid);
 
         impl std::fmt::Display for $name {
@@ -66,12 +76,20 @@ create_id!(EntryId);
 #[derive(Debug, Copy, Clone)]
 #[cfg_attr(feature = "sqlx", derive(sqlx::Type))]
 #[cfg_attr(feature = "sqlx", sqlx(transparent))]
-pub struct Version(u16);
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+pub struct Version(i32);
+impl Version {
+    /// Version of the entry once updated
+    pub(crate) fn next(self) -> Self {
+        Self(self.0 + 1)
+    }
+}
 /// hardcoded in lib
 pub type Revision = i16; // make static string
 #[derive(Debug, Copy, Clone)]
 #[cfg_attr(feature = "sqlx", derive(sqlx::Type))]
 #[cfg_attr(feature = "sqlx", sqlx(transparent))]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
 pub struct Date(i64);
 impl Date {
     pub fn now() -> Self {
@@ -85,10 +103,65 @@ impl Date {
     pub fn as_i64(&self) -> i64 {
         self.0
     }
+}
+/// `YYYY-MM-DD HH:MM:SS` in UTC
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let seconds = self.0.div_euclid(1_000_000_000);
+        let (days, time) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+        // inverse of the days since 1970-01-01 computed by `Date::from_str`, see
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let days = days + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days - era * 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let month = if month < 10 { month + 3 } else { month - 9 };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+            time / 3600,
+            time % 3600 / 60,
+            time % 60
+        )
+    }
+}
 
-    fn _iso8601(&self) -> String {
-        // https://stackoverflow.com/questions/64146345/how-do-i-convert-a-systemtime-to-iso-8601-in-rust
-        todo!()
+#[derive(Debug, Display, Error)]
+#[display("invalid date {_0}, expected YYYY-MM-DD")]
+pub struct InvalidDate(#[error(not(source))] pub String);
+/// Midnight UTC of a `YYYY-MM-DD` day
+impl FromStr for Date {
+    type Err = InvalidDate;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidDate(s.to_string());
+        let mut parts = s.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+        let (Some(Some(year)), Some(Some(month)), Some(Some(day))) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        let in_range =
+            (1..=9999).contains(&year) && (1..=12).contains(&month) && (1..=31).contains(&day);
+        if !in_range {
+            return Err(invalid());
+        }
+        // days since 1970-01-01 of the proleptic Gregorian calendar, see
+        // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146097 + day_of_era - 719468;
+        // nanoseconds only reach year 2262
+        days.checked_mul(86_400 * 1_000_000_000)
+            .map(Self)
+            .ok_or_else(invalid)
     }
 }
 
@@ -100,9 +173,10 @@ impl From<(&str, &'static str)> for InvalidUsername {
         Self(username.to_string(), reason)
     }
 }
-#[derive(Debug, Clone, Display)]
+#[derive(Debug, Clone, Display, PartialEq, Eq)]
 #[cfg_attr(feature = "sqlx", derive(sqlx::Decode, sqlx::Encode, sqlx::FromRow))]
 #[cfg_attr(feature = "sqlx", sqlx(transparent))]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
 pub struct Username(String);
 #[cfg(feature = "sqlx")]
 impl sqlx::Type<sqlx::Sqlite> for Username {
@@ -110,24 +184,53 @@ impl sqlx::Type<sqlx::Sqlite> for Username {
         <String as sqlx::Type<sqlx::Sqlite>>::type_info()
     }
 }
+#[cfg(feature = "postgres")]
+impl sqlx::Type<sqlx::Postgres> for Username {
+    fn type_info() -> <sqlx::Postgres as sqlx::Database>::TypeInfo {
+        <String as sqlx::Type<sqlx::Postgres>>::type_info()
+    }
+}
+/// Parses under the installed [`NamePolicy`], reserved words included: they
+/// can be written wherever a name is expected, but never name a user.
+/// Lookups by a reserved word find nothing, and [`Username::claimable`]
+/// refuses them.
 impl FromStr for Username {
     type Err = InvalidUsername;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s == "me" {
-            return Ok(Self("me".to_string()));
+        let policy = &Naming::current().users;
+        if policy.is_reserved(s) {
+            return Ok(Self(s.to_string()));
         }
-        let chars_count = s.chars().count();
-        if chars_count < 4 {
-            Err((s, "too short").into())
-        } else if 24 < chars_count {
-            Err((s, "too long").into())
-        } else {
-            Ok(Self(s.to_string()))
+        policy
+            .check(s)
+            .map(Self)
+            .map_err(|reason| (s, reason).into())
+    }
+}
+impl Username {
+    /// Key under which user names are unique
+    pub(crate) fn key(&self) -> String {
+        Naming::current().users.key(&self.0)
+    }
+    /// Whether the name is a word of the installed policy which stands for
+    /// something else
+    pub fn is_reserved(&self) -> bool {
+        Naming::current().users.is_reserved(&self.0)
+    }
+    /// The name, if a new user can take it
+    pub(crate) fn claimable(&self) -> Result<Self, InvalidUsername> {
+        let policy = &Naming::current().users;
+        if policy.is_reserved(&self.0) {
+            return Err((self.0.as_str(), "reserved").into());
         }
+        // names deserialized without being parsed are checked here
+        let name = policy.check(&self.0);
+        name.map(Self)
+            .map_err(|reason| (self.0.as_str(), reason).into())
     }
 }
 #[derive(Debug, Display)]
-#[display("invalid project name {} (reason: {})", 0, 1)]
+#[display("invalid project name {} (reason: {})", self.0, self.1)]
 pub struct InvalidProjectName(pub String, pub &'static str);
 impl std::error::Error for InvalidProjectName {}
 impl From<(&str, &'static str)> for InvalidProjectName {
@@ -137,24 +240,51 @@ impl From<(&str, &'static str)> for InvalidProjectName {
 }
 #[cfg_attr(feature = "sqlx", derive(sqlx::Type))]
 #[cfg_attr(feature = "sqlx", sqlx(transparent))]
-#[derive(Debug, Display, Clone)]
+#[derive(Debug, Display, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
 pub struct ProjectName(String);
+/// Parses under the installed [`NamePolicy`], reserved words included: they
+/// can be written wherever a name is expected, but never name a project.
+/// Lookups by a reserved word find nothing, and [`ProjectName::claimable`]
+/// refuses them.
 impl FromStr for ProjectName {
     type Err = InvalidProjectName;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let chars_count = s.chars().count();
-        if chars_count < 3 {
-            Err((s, "too short").into())
-        } else if 64 < chars_count {
-            Err((s, "too long").into())
-        } else {
-            Ok(Self(s.to_string()))
+        let policy = &Naming::current().projects;
+        if policy.is_reserved(s) {
+            return Ok(Self(s.to_string()));
+        }
+        policy
+            .check(s)
+            .map(Self)
+            .map_err(|reason| (s, reason).into())
+    }
+}
+impl ProjectName {
+    /// Key under which project names are unique
+    pub(crate) fn key(&self) -> String {
+        Naming::current().projects.key(&self.0)
+    }
+    /// Whether the name is a word of the installed policy which stands for
+    /// something else
+    pub fn is_reserved(&self) -> bool {
+        Naming::current().projects.is_reserved(&self.0)
+    }
+    /// The name, if a new project can take it
+    pub(crate) fn claimable(&self) -> Result<Self, InvalidProjectName> {
+        let policy = &Naming::current().projects;
+        if policy.is_reserved(&self.0) {
+            return Err((self.0.as_str(), "reserved").into());
         }
+        let name = policy.check(&self.0);
+        name.map(Self)
+            .map_err(|reason| (self.0.as_str(), reason).into())
     }
 }
 #[derive(Debug, Clone, Display)]
 #[display("User #{id} ({name})")]
-#[cfg_attr(feature = "sqlx", derive(sqlx::Type, sqlx::FromRow, sqlx::Encode))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
 pub struct User {
     id: UserId,
     pub(crate) name: Username,
@@ -190,14 +320,23 @@ impl User {
         &self.name
     }
 }
+impl Keyed for User {
+    fn cursor(&self) -> Cursor {
+        Cursor::new(self.id.0)
+    }
+}
 #[derive(Debug, Clone, Display)]
 #[display("Project #{id} - {name} (owned by {})", meta.author)]
-#[cfg_attr(feature = "sqlx", derive(sqlx::Type, sqlx::FromRow, sqlx::Encode))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
 pub struct Project {
     id: ProjectId,
     #[sqlx(flatten)]
     pub(crate) meta: Metadata,
     pub(crate) name: ProjectName,
+    /// team owning the project with its owner, `None` for a personal project
+    #[cfg_attr(feature = "dtos", serde(default))]
+    pub(crate) team: Option<TeamId>,
 }
 impl Project {
     pub fn new(name: ProjectName, author: UserId) -> Self {
@@ -210,18 +349,94 @@ impl Project {
                 created: Date::now(),
             },
             name,
+            team: None,
         }
     }
+    /// The project owned by the team
+    pub fn with_team(self, team: Option<TeamId>) -> Self {
+        Self { team, ..self }
+    }
     pub fn id(&self) -> ProjectId {
         self.id
     }
     pub fn name(&self) -> &ProjectName {
         &self.name
     }
+    pub fn owner(&self) -> UserId {
+        self.meta.author
+    }
+    pub fn team(&self) -> Option<TeamId> {
+        self.team
+    }
+}
+
+impl Keyed for Project {
+    fn cursor(&self) -> Cursor {
+        Cursor::new(self.id.0)
+    }
+}
+
+/// What a user can do on a project, each role allowing what the previous
+/// ones do
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Display)]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "dtos", serde(rename_all = "lowercase"))]
+pub enum Role {
+    /// reads the logs, fields and templates
+    #[display("reader")]
+    Reader,
+    /// writes and pins logs, and adds templates
+    #[default]
+    #[display("writer")]
+    Writer,
+    /// declares fields and manages the members and their roles
+    #[display("maintainer")]
+    Maintainer,
+    /// the one user who can hand the project over to someone else
+    #[display("owner")]
+    Owner,
+}
+impl FromStr for Role {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reader" => Ok(Self::Reader),
+            "writer" => Ok(Self::Writer),
+            "maintainer" => Ok(Self::Maintainer),
+            "owner" => Ok(Self::Owner),
+            _ => Err(format!(
+                "unknown role {s} (expected reader, writer, maintainer or owner)"
+            )),
+        }
+    }
 }
 
+/// A user taking part in a project owned by someone else, with their role
 #[derive(Debug, Clone)]
-#[cfg_attr(feature = "sqlx", derive(sqlx::Type, sqlx::FromRow))]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+pub struct Member {
+    pub project: ProjectId,
+    pub user: UserId,
+    /// members added before roles existed write the project
+    #[cfg_attr(feature = "dtos", serde(default))]
+    pub role: Role,
+    /// who added the user to the project
+    pub by: UserId,
+    pub created: Date,
+}
+
+/// A user of a project, as listed to its readers
+#[derive(Debug, Clone, Display)]
+#[display("{user} {role}")]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProjectMember {
+    pub user: User,
+    pub role: Role,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
 pub struct Metadata {
     pub(crate) revision: Revision,
     pub(crate) version: Version,
@@ -240,12 +455,16 @@ impl Metadata {
 }
 #[derive(Debug, Clone, Display)]
 #[display("Log #{id} by user {}: {text}", meta.author)]
-#[cfg_attr(feature = "sqlx", derive(sqlx::Type, sqlx::FromRow))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
 pub struct Log {
     id: EntryId,
     #[sqlx(flatten)]
     pub(crate) meta: Metadata,
     pub(crate) text: String,
+    /// loaded separately from the `log_field` table
+    #[sqlx(skip)]
+    pub(crate) fields: LogFields,
 }
 impl Log {
     pub fn new(text: String, author: UserId) -> Self {
@@ -258,9 +477,65 @@ impl Log {
                 created: Date::now(),
             },
             text,
+            fields: LogFields::new(),
         }
     }
+    pub fn with_fields(mut self, fields: LogFields) -> Self {
+        self.fields = fields;
+        self
+    }
     pub fn id(&self) -> EntryId {
         self.id
     }
+    pub fn author(&self) -> UserId {
+        self.meta.author
+    }
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    pub fn fields(&self) -> &LogFields {
+        &self.fields
+    }
+}
+
+impl Keyed for Log {
+    fn cursor(&self) -> Cursor {
+        Cursor::new(self.id.0)
+    }
+}
+
+/// Maximum number of pinned logs per project
+pub const MAX_PINNED_LOGS: usize = 5;
+
+/// Logs of a project as shown to users: pinned entries first, then the page
+/// of the timeline
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize))]
+pub struct ProjectLogs<T = Log> {
+    pub pinned: Vec<T>,
+    #[cfg_attr(feature = "dtos", serde(flatten))]
+    pub logs: Paged<T>,
+}
+impl<T> ProjectLogs<T> {
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> ProjectLogs<U> {
+        ProjectLogs {
+            pinned: self.pinned.into_iter().map(&mut f).collect(),
+            logs: self.logs.map(f),
+        }
+    }
+}
+impl<T> std::fmt::Display for ProjectLogs<T>
+where
+    T: std::fmt::Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.pinned.is_empty() {
+            writeln!(
+                f,
+                "---\n * Pinned *\n{}",
+                self.pinned.iter().map(|log| format!("📌 {log}")).join("\n")
+            )?;
+        }
+        write!(f, "{}", self.logs)
+    }
 }
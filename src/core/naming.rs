@@ -0,0 +1,146 @@
+use std::sync::OnceLock;
+
+use derive_more::derive::{Display, Error};
+use unicode_normalization::UnicodeNormalization;
+
+/// Policies in force, installed once at startup
+static NAMING: OnceLock<Naming> = OnceLock::new();
+
+/// What makes a valid name, and when two names are the same.
+///
+/// Names are stored in NFC, and compared once in NFKC, lowercased when the
+/// policy is case-insensitive: `Alice` and `alice` are then the same user.
+/// Slashes and control characters, which break URL paths, are never
+/// allowed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamePolicy {
+    /// fewest characters of a name
+    pub min_length: usize,
+    /// most characters of a name
+    pub max_length: usize,
+    /// whether letters and digits are not only ASCII ones
+    pub unicode: bool,
+    /// characters allowed besides letters and digits, neither first nor
+    /// last in a name
+    pub symbols: String,
+    /// words standing for something else, like `me`, which can be written
+    /// wherever a name is expected but nobody can take
+    pub reserved: Vec<String>,
+    /// whether names differing only by case are different names
+    pub case_sensitive: bool,
+}
+
+impl NamePolicy {
+    pub fn users() -> Self {
+        Self {
+            min_length: 4,
+            max_length: 24,
+            unicode: true,
+            symbols: "-_.".into(),
+            reserved: vec!["me".into()],
+            case_sensitive: false,
+        }
+    }
+    pub fn projects() -> Self {
+        Self {
+            min_length: 3,
+            max_length: 64,
+            unicode: true,
+            symbols: "-_. ".into(),
+            reserved: vec![],
+            case_sensitive: false,
+        }
+    }
+    /// The policy allowing the symbols, except those never allowed
+    pub fn with_symbols(self, symbols: &str) -> Self {
+        let symbols = symbols
+            .chars()
+            .filter(|c| *c != '/' && !c.is_control() && !c.is_alphanumeric())
+            .collect();
+        Self { symbols, ..self }
+    }
+
+    /// The name normalized, or why the policy refuses it
+    pub fn check(&self, name: &str) -> Result<String, &'static str> {
+        let name: String = name.nfc().collect();
+        let length = name.chars().count();
+        if length < self.min_length {
+            return Err("too short");
+        }
+        if self.max_length < length {
+            return Err("too long");
+        }
+        if name.contains('/') || name.chars().any(char::is_control) {
+            return Err("contains slashes or control characters");
+        }
+        let alphanumeric = |c: char| match self.unicode {
+            true => c.is_alphanumeric(),
+            false => c.is_ascii_alphanumeric(),
+        };
+        if !name
+            .chars()
+            .all(|c| alphanumeric(c) || self.symbols.contains(c))
+        {
+            return Err("contains characters other than letters, digits and allowed symbols");
+        }
+        let ends = [name.chars().next(), name.chars().last()];
+        if !ends.into_iter().flatten().all(alphanumeric) {
+            return Err("starts or ends with a symbol");
+        }
+        Ok(name)
+    }
+    /// Key under which names are unique
+    pub fn key(&self, name: &str) -> String {
+        let name = name.nfkc();
+        match self.case_sensitive {
+            true => name.collect(),
+            false => name.flat_map(char::to_lowercase).collect(),
+        }
+    }
+    /// Identifies how the policy keys names: keys computed under a policy
+    /// with another fingerprint must be computed again
+    pub fn fingerprint(&self) -> String {
+        let (major, minor, patch) = unicode_normalization::UNICODE_VERSION;
+        let case = match self.case_sensitive {
+            true => "case-sensitive",
+            false => "case-insensitive",
+        };
+        format!("nfkc unicode-{major}.{minor}.{patch} {case}")
+    }
+    pub fn is_reserved(&self, name: &str) -> bool {
+        let key = self.key(name);
+        self.reserved.iter().any(|word| self.key(word) == key)
+    }
+}
+
+#[derive(Debug, Display, Error)]
+#[display("names were parsed before their policy was installed")]
+pub struct NamingAlreadyInstalled;
+
+/// Policies of the names of users and projects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Naming {
+    pub users: NamePolicy,
+    pub projects: NamePolicy,
+}
+
+impl Default for Naming {
+    fn default() -> Self {
+        Self {
+            users: NamePolicy::users(),
+            projects: NamePolicy::projects(),
+        }
+    }
+}
+
+impl Naming {
+    /// Makes the policies those of every name parsed from now on, failing
+    /// when some were installed or used before
+    pub fn install(self) -> Result<(), NamingAlreadyInstalled> {
+        NAMING.set(self).map_err(|_| NamingAlreadyInstalled)
+    }
+    /// The installed policies, the default ones if none were
+    pub fn current() -> &'static Naming {
+        NAMING.get_or_init(Naming::default)
+    }
+}
@@ -1,24 +1,123 @@
+use std::{cmp::Ordering, str::FromStr};
+
 use itertools::Itertools;
-use tracing::trace;
+
+use super::model::ModelError;
+
+/// Opaque position of an entry in a listing, taken from its time-ordered id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::Type))]
+#[cfg_attr(feature = "sqlx", sqlx(transparent))]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "dtos", serde(into = "String", try_from = "String"))]
+pub struct Cursor(uuid::Uuid);
+impl Cursor {
+    pub(crate) fn new(id: uuid::Uuid) -> Self {
+        Self(id)
+    }
+}
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.simple())
+    }
+}
+impl From<Cursor> for String {
+    fn from(cursor: Cursor) -> Self {
+        cursor.to_string()
+    }
+}
+impl TryFrom<String> for Cursor {
+    type Error = ModelError;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+impl FromStr for Cursor {
+    type Err = ModelError;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match uuid::Uuid::try_parse(input) {
+            Ok(id) if id.get_version_num() == 7 => Ok(Self(id)),
+            _ => Err(ModelError::InvalidId),
+        }
+    }
+}
+
+/// Entries listed in the order of their cursor
+pub trait Keyed {
+    fn cursor(&self) -> Cursor;
+}
+
+/// Order of a listing, by cursor hence by creation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// oldest first
+    #[default]
+    Asc,
+    /// newest first
+    Desc,
+}
+impl FromStr for SortOrder {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "asc" => Ok(Self::Asc),
+            "desc" => Ok(Self::Desc),
+            _ => Err(format!("invalid sort order {s}, expected asc or desc")),
+        }
+    }
+}
+
+/// Where a page selected by a cursor starts
+#[derive(Debug, Clone, Copy)]
+pub enum PageCursor {
+    /// the entries following the one of the cursor
+    After(Cursor),
+    /// the entries preceding the one of the cursor
+    Before(Cursor),
+}
 
 #[cfg_attr(feature = "dtos", derive(serde::Deserialize))]
 #[derive(Debug, Clone)]
 pub struct Page {
     page: usize,
     size: usize,
+    #[cfg_attr(feature = "dtos", serde(skip))]
+    cursor: Option<PageCursor>,
 }
 
 impl Page {
     pub fn new(page: usize, size: usize) -> Self {
         assert!(page > 0);
         assert!(size >= 1);
-        Self { page, size }
+        Self {
+            page,
+            size,
+            cursor: None,
+        }
+    }
+    /// The `size` entries following the one of the cursor
+    pub fn after(cursor: Cursor, size: usize) -> Self {
+        Self {
+            cursor: Some(PageCursor::After(cursor)),
+            ..Self::new(1, size)
+        }
+    }
+    /// The `size` entries preceding the one of the cursor
+    pub fn before(cursor: Cursor, size: usize) -> Self {
+        Self {
+            cursor: Some(PageCursor::Before(cursor)),
+            ..Self::new(1, size)
+        }
+    }
+    pub fn cursor(&self) -> Option<PageCursor> {
+        self.cursor
     }
     pub fn number(&self) -> usize {
         self.page
     }
+    /// Entries to skip, none for pages selected by a cursor
     pub fn offset(&self) -> usize {
-        if self.page <= 1 {
+        if self.page <= 1 || self.cursor.is_some() {
             0
         } else {
             (self.page - 1) * self.size
@@ -27,11 +126,23 @@ impl Page {
     pub fn page_size(&self) -> usize {
         self.size
     }
+    /// Same page with at most `max` entries
+    pub fn limited(self, max: usize) -> Self {
+        Self {
+            size: self.size.min(max),
+            ..self
+        }
+    }
+    /// Rows to fetch for the page: one more than its size, telling whether
+    /// there are entries beyond it
+    pub fn fetch_size(&self) -> usize {
+        self.size + 1
+    }
 }
 
 impl Default for Page {
     fn default() -> Self {
-        Self { page: 1, size: 10 }
+        Self::new(1, 10)
     }
 }
 
@@ -39,20 +150,81 @@ impl Default for Page {
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "dtos", derive(serde::Serialize))]
 pub struct Paged<T> {
+    /// 0 when the page was selected by a cursor
     pub page: usize,
+    pub page_size: usize,
+    /// Number of entries over all the pages
+    pub total: usize,
+    pub page_count: usize,
+    pub has_next: bool,
+    /// To get the following page with `Page::after`
+    #[cfg_attr(feature = "dtos", serde(skip_serializing_if = "Option::is_none"))]
+    pub next: Option<Cursor>,
+    /// To get the preceding page with `Page::before`
+    #[cfg_attr(feature = "dtos", serde(skip_serializing_if = "Option::is_none"))]
+    pub prev: Option<Cursor>,
     pub data: Vec<T>,
 }
 impl<T> Default for Paged<T> {
     fn default() -> Self {
+        Paged::new(vec![], &Page::default(), 0)
+    }
+}
+impl<T> Paged<T> {
+    /// The requested page, holding `data` out of `total` entries
+    pub fn new(data: Vec<T>, page: &Page, total: usize) -> Self {
+        let page_count = total.div_ceil(page.page_size());
         Paged {
-            page: 1,
-            data: vec![],
+            page: page.number(),
+            page_size: page.page_size(),
+            total,
+            page_count,
+            has_next: page.number() < page_count,
+            next: None,
+            prev: None,
+            data,
+        }
+    }
+    /// Converts every element of the page, keeping the page info
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Paged<U> {
+        Paged {
+            page: self.page,
+            page_size: self.page_size,
+            total: self.total,
+            page_count: self.page_count,
+            has_next: self.has_next,
+            next: self.next,
+            prev: self.prev,
+            data: self.data.into_iter().map(f).collect(),
         }
     }
 }
-impl<T> Paged<T> {
-    fn _len(&self) -> usize {
-        self.data.len()
+impl<T: Keyed> Paged<T> {
+    /// The page made of the rows fetched for it, `Page::fetch_size` at most,
+    /// in the order of their cursor or in reverse order for `Before` pages
+    pub fn from_rows(mut rows: Vec<T>, page: &Page, total: usize) -> Self {
+        let more = rows.len() > page.page_size();
+        rows.truncate(page.page_size());
+        let (number, has_next, has_prev) = match page.cursor() {
+            None => (page.number(), more, page.number() > 1),
+            Some(PageCursor::After(_)) => (0, more, true),
+            Some(PageCursor::Before(_)) => {
+                rows.reverse();
+                (0, true, more)
+            }
+        };
+        let next = rows.last().filter(|_| has_next).map(Keyed::cursor);
+        let prev = rows.first().filter(|_| has_prev).map(Keyed::cursor);
+        Paged {
+            page: number,
+            page_size: page.page_size(),
+            total,
+            page_count: total.div_ceil(page.page_size()),
+            has_next,
+            next,
+            prev,
+            data: rows,
+        }
     }
 }
 impl<T> std::fmt::Display for Paged<T>
@@ -60,12 +232,20 @@ where
     T: std::fmt::Display,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "---\n * Page {:02} *\n{}\n---",
-            self.page,
-            self.data.iter().join("\n")
-        )
+        if self.page == 0 {
+            write!(f, "---\n * {} of {} *", self.data.len(), self.total)?;
+        } else {
+            write!(
+                f,
+                "---\n * Page {:02}/{:02} ({} in total) *",
+                self.page, self.page_count, self.total
+            )?;
+        }
+        write!(f, "\n{}\n---", self.data.iter().join("\n"))?;
+        match self.next {
+            Some(next) => write!(f, "\nnext: --after {next}"),
+            None => Ok(()),
+        }
     }
 }
 
@@ -74,8 +254,6 @@ pub trait Paginable {
     type Output;
     /// Returns the **subset** of data corresponding to the page requested
     fn get_page(&self, page: &Page) -> Paged<Self::Output>;
-    /// Returns list with the page info (does not limit shorten the list)
-    fn to_paged(self, page: Page) -> Paged<Self::Output>;
 }
 
 // defualt vec impl
@@ -85,37 +263,51 @@ where
 {
     type Output = T;
     fn get_page(&self, page: &Page) -> Paged<Self::Output> {
-        let Page { page, size } = page;
-        if self.len() < *size {
-            return Paged {
-                page: 1,
-                data: self.to_vec(),
-            };
-        }
-        let pages_count = (self.len() / size) + 1;
-        let page = if *page > pages_count {
-            pages_count
-        } else {
-            *page
-        };
-        let offset = (page - 1) * size;
-        Paged {
-            page,
-            data: self[offset..(offset + size)].to_vec(),
-        }
+        let data = self
+            .iter()
+            .skip(page.offset())
+            .take(page.page_size())
+            .cloned()
+            .collect();
+        Paged::new(data, page, self.len())
     }
-    fn to_paged(self, page: Page) -> Paged<Self::Output> {
-        assert!(page.page_size() >= 1, "required page size is 0. WTF.");
-        assert!(
-            self.len() <= page.page_size(),
-            "There are more elements than required by page size: ps:{}, len:{}",
-            page.page_size(),
-            self.len()
-        );
-        trace!("Returning page from list of {} elements", self.len());
-        Paged {
-            page: page.number(),
-            data: self,
+}
+
+/// The page of the rows, given in the order of their cursor and listed in
+/// `order`, counting all of them but only cloning the ones on the page
+pub(crate) fn page_of<'a, T: Clone + Keyed + 'a>(
+    rows: impl Iterator<Item = &'a T>,
+    page: Page,
+    order: SortOrder,
+) -> Paged<T> {
+    let mut rows: Vec<&T> = rows.collect();
+    let later = match order {
+        SortOrder::Asc => Ordering::Greater,
+        SortOrder::Desc => {
+            rows.reverse();
+            Ordering::Less
         }
-    }
+    };
+    let fetched = match page.cursor() {
+        None => rows
+            .iter()
+            .skip(page.offset())
+            .take(page.fetch_size())
+            .map(|row| (*row).clone())
+            .collect(),
+        Some(PageCursor::After(cursor)) => rows
+            .iter()
+            .filter(|row| row.cursor().cmp(&cursor) == later)
+            .take(page.fetch_size())
+            .map(|row| (*row).clone())
+            .collect(),
+        Some(PageCursor::Before(cursor)) => rows
+            .iter()
+            .rev()
+            .filter(|row| row.cursor().cmp(&cursor) == later.reverse())
+            .take(page.fetch_size())
+            .map(|row| (*row).clone())
+            .collect(),
+    };
+    Paged::from_rows(fetched, &page, rows.len())
 }
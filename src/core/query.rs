@@ -0,0 +1,440 @@
+use std::str::FromStr;
+
+use itertools::Itertools;
+
+use super::{
+    field::FieldFilter,
+    model::{Date, Log, Project, ProjectId, ProjectName, UserId},
+    pagination::{Page, SortOrder},
+    search::{Criterion, QuerySyntaxError, SearchQuery, Term},
+};
+
+/// Which logs of a project to list, and how. Repositories translate it to a
+/// single query so that new listing needs only mean new criteria here.
+///
+/// ```
+/// # use std::str::FromStr;
+/// # use reverie::{FieldFilter, LogQuery, Page, ProjectId, SortOrder};
+/// let query = LogQuery::project(ProjectId::default())
+///     .field(FieldFilter::from_str("env=prod").unwrap())
+///     .sort(SortOrder::Desc)
+///     .page(Page::new(1, 20));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LogQuery {
+    pub(crate) project: ProjectId,
+    pub(crate) author: Option<UserId>,
+    pub(crate) since: Option<Date>,
+    pub(crate) until: Option<Date>,
+    pub(crate) fields: Vec<FieldFilter>,
+    pub(crate) sort: SortOrder,
+    pub(crate) page: Page,
+}
+
+impl LogQuery {
+    /// Every log of the project, oldest first, on the default page
+    pub fn project(project: ProjectId) -> Self {
+        Self {
+            project,
+            author: None,
+            since: None,
+            until: None,
+            fields: vec![],
+            sort: SortOrder::default(),
+            page: Page::default(),
+        }
+    }
+    /// Only the logs written by the user
+    pub fn by(self, author: UserId) -> Self {
+        Self {
+            author: Some(author),
+            ..self
+        }
+    }
+    /// Only the logs created at or after the date
+    pub fn since(self, date: Date) -> Self {
+        Self {
+            since: Some(date),
+            ..self
+        }
+    }
+    /// Only the logs created before the date
+    pub fn until(self, date: Date) -> Self {
+        Self {
+            until: Some(date),
+            ..self
+        }
+    }
+    /// Only the logs whose field matches, on top of the previous filters
+    pub fn field(mut self, filter: FieldFilter) -> Self {
+        self.fields.push(filter);
+        self
+    }
+    pub fn fields(mut self, filters: impl IntoIterator<Item = FieldFilter>) -> Self {
+        self.fields.extend(filters);
+        self
+    }
+    pub fn sort(self, sort: SortOrder) -> Self {
+        Self { sort, ..self }
+    }
+    pub fn page(self, page: Page) -> Self {
+        Self { page, ..self }
+    }
+
+    /// No criterion but the project: the whole timeline is listed
+    pub fn is_unfiltered(&self) -> bool {
+        self.author.is_none()
+            && self.since.is_none()
+            && self.until.is_none()
+            && self.fields.is_empty()
+    }
+    /// Whether the log, known to be of the project, meets every criterion
+    /// but the field filters, which depend on the storage of the values
+    pub(crate) fn selects(&self, log: &Log) -> bool {
+        let created = log.meta.created.as_i64();
+        self.author.is_none_or(|author| log.meta.author == author)
+            && self.since.is_none_or(|since| since.as_i64() <= created)
+            && self.until.is_none_or(|until| created < until.as_i64())
+    }
+}
+
+/// Logs meeting every term of a [`SearchQuery`], best matches first, across
+/// the projects the searching user can read.
+///
+/// Pages are numbered: ranked results have no cursor to seek.
+#[derive(Debug, Clone)]
+pub struct LogSearch {
+    pub(crate) query: SearchQuery,
+    /// set by the service from the readable projects and the `project:`
+    /// terms, a search never reaches other projects
+    pub(crate) projects: Vec<ProjectId>,
+    /// set by the service from the `author:` terms
+    pub(crate) authors: Vec<UserId>,
+    pub(crate) excluded_authors: Vec<UserId>,
+    pub(crate) since: Option<Date>,
+    pub(crate) until: Option<Date>,
+    pub(crate) page: Page,
+    /// logs need only one of the words and phrases, see `LogSearch::any_of`
+    pub(crate) any: bool,
+}
+
+impl LogSearch {
+    pub fn new(query: SearchQuery) -> Self {
+        let mut search = Self {
+            query: SearchQuery::default(),
+            projects: vec![],
+            authors: vec![],
+            excluded_authors: vec![],
+            since: None,
+            until: None,
+            page: Page::default(),
+            any: false,
+        };
+        for term in &query.terms {
+            search = match term.criterion {
+                Criterion::After(date) => search.since(date),
+                Criterion::Before(date) => search.until(date),
+                _ => search,
+            };
+        }
+        Self { query, ..search }
+    }
+    /// Logs holding any of the words, those holding more of them first
+    pub(crate) fn any_of(words: impl IntoIterator<Item = String>) -> Self {
+        let terms = words.into_iter().map(|word| Term {
+            negated: false,
+            criterion: Criterion::Word(word),
+            span: 0..0,
+        });
+        let query = SearchQuery {
+            source: String::new(),
+            terms: terms.collect(),
+        };
+        Self {
+            any: true,
+            ..Self::new(query)
+        }
+    }
+    /// The search written in the query language
+    pub fn parse(query: &str) -> Result<Self, QuerySyntaxError> {
+        Ok(Self::new(SearchQuery::from_str(query)?))
+    }
+    /// Only the logs created at or after the date, on top of `after:` terms
+    pub fn since(self, date: Date) -> Self {
+        let since = self.since.filter(|since| since.as_i64() > date.as_i64());
+        Self {
+            since: since.or(Some(date)),
+            ..self
+        }
+    }
+    /// Only the logs created before the date, on top of `before:` terms
+    pub fn until(self, date: Date) -> Self {
+        let until = self.until.filter(|until| until.as_i64() < date.as_i64());
+        Self {
+            until: until.or(Some(date)),
+            ..self
+        }
+    }
+    pub fn page(self, page: Page) -> Self {
+        Self { page, ..self }
+    }
+
+    /// Lowercased words of the text the logs must contain, punctuation being
+    /// left out so that user input never breaks the syntax of a full-text
+    /// engine
+    pub(crate) fn terms(&self) -> Vec<String> {
+        self.phrases(false).into_iter().flatten().unique().collect()
+    }
+    /// Words and phrases the logs must contain, or must not when `negated`,
+    /// each as its lowercased words
+    pub(crate) fn phrases(&self, negated: bool) -> Vec<Vec<String>> {
+        self.query
+            .terms
+            .iter()
+            .filter(|term| term.negated == negated)
+            .filter_map(|term| match &term.criterion {
+                Criterion::Word(text) | Criterion::Phrase(text) => {
+                    Some(words(text).map(str::to_lowercase).collect::<Vec<_>>())
+                }
+                _ => None,
+            })
+            .filter(|phrase| !phrase.is_empty())
+            .collect()
+    }
+    /// Whether the search has no criterion a log can meet, such as a query
+    /// of punctuation only, rather than listing every log
+    pub(crate) fn is_void(&self) -> bool {
+        self.query.terms.iter().all(|term| match &term.criterion {
+            Criterion::Word(text) | Criterion::Phrase(text) => words(text).next().is_none(),
+            _ => false,
+        })
+    }
+    /// Conditions on the log fields, with whether the logs must not meet them
+    pub(crate) fn fields(&self) -> Vec<(&FieldFilter, bool)> {
+        self.query
+            .terms
+            .iter()
+            .filter_map(|term| match &term.criterion {
+                Criterion::Field(filter) => Some((filter, term.negated)),
+                _ => None,
+            })
+            .collect()
+    }
+    /// Whether the log is in the searched projects, authors and dates
+    pub(crate) fn selects(&self, project: ProjectId, log: &Log) -> bool {
+        let created = log.meta.created.as_i64();
+        let author = log.meta.author;
+        self.projects.contains(&project)
+            && (self.authors.is_empty() || self.authors.contains(&author))
+            && !self.excluded_authors.contains(&author)
+            && self.since.is_none_or(|since| since.as_i64() <= created)
+            && self.until.is_none_or(|until| created < until.as_i64())
+    }
+}
+
+/// A log found by a [`LogSearch`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize))]
+pub struct LogHit {
+    #[sqlx(flatten)]
+    pub log: Log,
+    /// name of the project of the log
+    pub project: ProjectName,
+    /// excerpt of the text around the matches as markdown: the matches are
+    /// in `**bold**` and the rest is escaped
+    pub snippet: String,
+    /// relevance of the log, higher is better
+    pub score: f64,
+}
+
+impl std::fmt::Display for LogHit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} #{}: {}", self.project, self.log.id(), self.snippet)
+    }
+}
+
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+}
+
+/// Whether the word of a text matches a search term, taken as a prefix so
+/// that plurals and the like are found
+fn matches_term(word: &str, term: &str) -> bool {
+    word.to_lowercase().starts_with(term)
+}
+
+/// Whether the words of the phrase follow each other in the text
+pub(crate) fn contains_phrase(text: &str, phrase: &[String]) -> bool {
+    let words: Vec<&str> = words(text).collect();
+    words.windows(phrase.len()).any(|window| {
+        window
+            .iter()
+            .zip(phrase)
+            .all(|(word, term)| matches_term(word, term))
+    })
+}
+
+/// Relevance of the text for the terms: the share of its words matching one
+pub(crate) fn score(text: &str, terms: &[String]) -> f64 {
+    let words: Vec<&str> = words(text).collect();
+    let matching = words
+        .iter()
+        .filter(|word| terms.iter().any(|term| matches_term(word, term)))
+        .count();
+    matching as f64 / words.len().max(1) as f64
+}
+
+/// Start of a match in the excerpts made by full-text engines, a private use
+/// character which log texts do not hold unlike `**`
+pub(crate) const MATCH_START: char = '\u{E000}';
+/// End of a match, see `MATCH_START`
+pub(crate) const MATCH_END: char = '\u{E001}';
+
+/// Excerpt whose matches are between `MATCH_START` and `MATCH_END` as
+/// markdown: the matches in bold, and the rest on a single line with its
+/// punctuation escaped so that it is never styled
+pub(crate) fn highlighted(excerpt: &str) -> String {
+    let mut markdown = String::with_capacity(excerpt.len() * 5 / 4);
+    for c in excerpt.split_whitespace().join(" ").chars() {
+        match c {
+            MATCH_START | MATCH_END => markdown.push_str("**"),
+            c if c.is_ascii_punctuation() => {
+                markdown.push('\\');
+                markdown.push(c);
+            }
+            c => markdown.push(c),
+        }
+    }
+    markdown
+}
+
+/// Excerpt of at most `SNIPPET_WORDS` words of the text, starting a few words
+/// before the first match, with the matching words in bold
+pub(crate) fn snippet(text: &str, terms: &[String]) -> String {
+    const SNIPPET_WORDS: usize = 16;
+    let words: Vec<&str> = words(text).collect();
+    let is_match = |word: &str| terms.iter().any(|term| matches_term(word, term));
+    let first = words.iter().position(|word| is_match(word)).unwrap_or(0);
+    let start = first.saturating_sub(3);
+    let end = (start + SNIPPET_WORDS).min(words.len());
+    let mut snippet: Vec<String> = words[start..end]
+        .iter()
+        .map(|word| match is_match(word) {
+            true => format!("{MATCH_START}{word}{MATCH_END}"),
+            false => word.to_string(),
+        })
+        .collect();
+    if start > 0 {
+        snippet.insert(0, "…".into());
+    }
+    if end < words.len() {
+        snippet.push("…".into());
+    }
+    highlighted(&snippet.join(" "))
+}
+
+/// Projects whose name is close to `text`: starting with it, containing it
+/// or differing from it by a few typos, closest first
+#[derive(Debug, Clone)]
+pub struct ProjectSearch {
+    pub(crate) text: String,
+    pub(crate) owner: Option<UserId>,
+    pub(crate) active_since: Option<Date>,
+    pub(crate) limit: usize,
+}
+
+impl ProjectSearch {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            owner: None,
+            active_since: None,
+            limit: 10,
+        }
+    }
+    /// Only the projects of the user
+    pub fn owner(self, owner: UserId) -> Self {
+        Self {
+            owner: Some(owner),
+            ..self
+        }
+    }
+    /// Only the projects created or logged to at or after the date
+    pub fn active_since(self, date: Date) -> Self {
+        Self {
+            active_since: Some(date),
+            ..self
+        }
+    }
+    /// At most `limit` projects
+    pub fn limit(self, limit: usize) -> Self {
+        Self { limit, ..self }
+    }
+}
+
+/// A project with the date of its last log, or of its creation if it has no
+/// log
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize))]
+pub struct ProjectActivity {
+    #[sqlx(flatten)]
+    pub project: Project,
+    pub last_activity: Date,
+}
+
+/// A project found by a [`ProjectSearch`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize))]
+pub struct ProjectMatch {
+    #[cfg_attr(feature = "dtos", serde(flatten))]
+    pub activity: ProjectActivity,
+    /// 0 when the name starts with the searched text, 1 when it contains
+    /// it, one more per typo otherwise
+    pub distance: usize,
+}
+
+impl std::fmt::Display for ProjectMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.activity.project)
+    }
+}
+
+/// Distance between the searched text and a project name, ignoring case, see
+/// [`ProjectMatch::distance`]. `None` when the name has more typos than
+/// tolerated for a text of that length.
+pub(crate) fn name_distance(text: &str, name: &str) -> Option<usize> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    if name.starts_with(&text) {
+        return Some(0);
+    }
+    if name.windows(text.len().max(1)).any(|window| window == text) {
+        return Some(1);
+    }
+    // typos in the whole name, or in its start for a prefix being typed
+    let prefix = &name[..text.len().min(name.len())];
+    let typos = edit_distance(&text, &name).min(edit_distance(&text, prefix));
+    let tolerated = (1 + text.len() / 5).min(3);
+    (typos <= tolerated).then_some(1 + typos)
+}
+
+/// Number of insertions, deletions, substitutions and transpositions of
+/// adjacent characters turning `a` into `b` (optimal string alignment)
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let substitution = rows[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            row[j] = substitution.min(rows[i - 1][j] + 1).min(row[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
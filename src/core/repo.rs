@@ -1,84 +1,313 @@
 use std::future::Future;
 
 use derive_more::derive::Display;
+use futures_util::stream::BoxStream;
 
 use crate::{Page, Paged, ProjectName};
 
-use super::model::{Log, Project, ProjectId, User, UserId, Username};
+use super::{
+    audit::{AuditEntry, AuditQuery},
+    field::FieldDefinition,
+    model::{EntryId, Log, Member, Project, ProjectId, Role, User, UserId, Username},
+    query::{LogHit, LogQuery, LogSearch, ProjectActivity, ProjectSearch},
+    team::{Team, TeamId, TeamMember, TeamName},
+    template::{LogFields, Template, TemplateName},
+    view::View,
+};
 
 pub struct CreateAuthorRequest {
     pub username: Username,
 }
-#[derive(Debug, Display)]
-#[display("Could not create author: {}", 0)]
-pub struct CreateAuthorError(pub String);
-impl std::error::Error for CreateAuthorError {}
-
-#[derive(Debug, Display)]
-#[display("Could not create project: {}", 0)]
-pub struct CreateProjectError(pub String);
-impl std::error::Error for CreateProjectError {}
 
+/// Failure of a repository operation
 #[derive(Debug, Display)]
-#[display("Could not create log: {}", 0)]
-pub struct CreateLogError(pub String);
-impl std::error::Error for CreateLogError {}
+pub enum RepoError {
+    /// the entry does not exist
+    #[display("not found: {_0}")]
+    NotFound(String),
+    /// a unique name or id is already taken
+    #[display("conflict: {_0}")]
+    Conflict(String),
+    /// the entry refers to another one which does not exist
+    #[display("missing reference: {_0}")]
+    ForeignKey(String),
+    /// the storage can not be used for now: locked, unreachable, out of space
+    #[display("storage unavailable: {_0}")]
+    Unavailable(String),
+    /// stored data can not be read back
+    #[display("corrupt storage: {_0}")]
+    Corrupt(String),
+}
+impl std::error::Error for RepoError {}
 
-#[derive(Debug, Display)]
-#[display("Could not process query: {}", 0)]
-pub struct RepoQueryError(pub String);
-impl std::error::Error for RepoQueryError {}
+/// Entries read from the repository as they are needed, instead of loaded
+/// all at once
+pub type EntryStream<T> = BoxStream<'static, Result<T, RepoError>>;
 
 pub struct CreateProjectRequest {
     pub owner: UserId,
     pub project_name: ProjectName,
+    /// team owning the project with its owner
+    pub team: Option<TeamId>,
 }
 pub struct CreateLogRequest {
     pub author: UserId,
     pub project: ProjectId,
     pub text: String,
+    pub fields: LogFields,
+}
+pub struct CreateTemplateRequest {
+    pub author: UserId,
+    /// `None` for a personal template
+    pub project: Option<ProjectId>,
+    pub name: TemplateName,
+    pub body: String,
+}
+pub struct AddMemberRequest {
+    pub by: UserId,
+    pub project: ProjectId,
+    pub user: UserId,
+    pub role: Role,
+}
+pub struct CreateTeamRequest {
+    pub owner: UserId,
+    pub name: TeamName,
+}
+pub struct AddTeamMemberRequest {
+    pub by: UserId,
+    pub team: TeamId,
+    pub user: UserId,
+    pub role: Role,
+}
+pub struct PinLogRequest {
+    pub by: UserId,
+    pub project: ProjectId,
+    pub log: EntryId,
 }
 
 pub trait AuthorRepository: Clone + Send + Sync + 'static {
-    // todo define author repo err
     fn create_author(
         &self,
         request: CreateAuthorRequest,
-    ) -> impl Future<Output = Result<User, CreateAuthorError>> + Send;
-    fn get_user_by_name(&self, username: &Username) -> impl Future<Output = Option<User>> + Send;
-    fn get_user_by_id(&self, id: UserId) -> impl Future<Output = Option<User>> + Send;
+    ) -> impl Future<Output = Result<User, RepoError>> + Send;
+    fn get_user_by_name(
+        &self,
+        username: &Username,
+    ) -> impl Future<Output = Result<Option<User>, RepoError>> + Send;
+    fn get_user_by_id(
+        &self,
+        id: UserId,
+    ) -> impl Future<Output = Result<Option<User>, RepoError>> + Send;
     #[cfg(feature = "admin")]
-    fn list_users(&self, page: Page) -> impl Future<Output = Paged<User>> + Send;
+    fn list_users(&self, page: Page)
+        -> impl Future<Output = Result<Paged<User>, RepoError>> + Send;
 }
 
 pub trait ProjectRepository: Clone + Send + Sync + 'static {
-    // todo define author repo err
     fn create_project(
         &self,
         request: CreateProjectRequest,
-    ) -> impl Future<Output = Result<Project, CreateProjectError>> + Send;
+    ) -> impl Future<Output = Result<Project, RepoError>> + Send;
     fn get_project_by_name(
         &self,
         name: &ProjectName,
-    ) -> impl Future<Output = Option<Project>> + Send;
-    fn get_project_by_id(&self, id: ProjectId) -> impl Future<Output = Option<Project>> + Send;
+    ) -> impl Future<Output = Result<Option<Project>, RepoError>> + Send;
+    fn get_project_by_id(
+        &self,
+        id: ProjectId,
+    ) -> impl Future<Output = Result<Option<Project>, RepoError>> + Send;
     fn list_user_projects(
         &self,
         id: UserId,
         page: Page,
-    ) -> impl Future<Output = Paged<Project>> + Send;
+    ) -> impl Future<Output = Result<Paged<Project>, RepoError>> + Send;
+    /// Projects meeting the owner and activity criteria of the search,
+    /// whatever their name, oldest first
+    fn list_project_activity(
+        &self,
+        search: &ProjectSearch,
+    ) -> impl Future<Output = Result<Vec<ProjectActivity>, RepoError>> + Send;
+    /// Every project of the user, oldest first
+    fn stream_user_projects(&self, id: UserId) -> EntryStream<Project>;
+    /// Declares a custom field on the project, replacing any field with the
+    /// same name
+    fn set_project_field(
+        &self,
+        definition: FieldDefinition,
+    ) -> impl Future<Output = Result<(), RepoError>> + Send;
+    fn list_project_fields(
+        &self,
+        project: ProjectId,
+    ) -> impl Future<Output = Result<Vec<FieldDefinition>, RepoError>> + Send;
+    /// Adds the user to the members of the project. Adding a member again
+    /// is a conflict
+    fn add_project_member(
+        &self,
+        request: AddMemberRequest,
+    ) -> impl Future<Output = Result<Member, RepoError>> + Send;
+    /// Changes the role of a member of the project. Returns false if the
+    /// user is not a member
+    fn set_member_role(
+        &self,
+        project: ProjectId,
+        user: UserId,
+        role: Role,
+    ) -> impl Future<Output = Result<bool, RepoError>> + Send;
+    /// Returns false if the user was not a member of the project
+    fn remove_project_member(
+        &self,
+        project: ProjectId,
+        user: UserId,
+    ) -> impl Future<Output = Result<bool, RepoError>> + Send;
+    /// Makes the user the owner of the project
+    fn transfer_project(
+        &self,
+        project: ProjectId,
+        owner: UserId,
+    ) -> impl Future<Output = Result<(), RepoError>> + Send;
+    /// Members of the project, oldest first
+    fn list_project_members(
+        &self,
+        project: ProjectId,
+    ) -> impl Future<Output = Result<Vec<Member>, RepoError>> + Send;
+    /// Memberships of the user in the projects of others, oldest first
+    fn list_user_memberships(
+        &self,
+        user: UserId,
+    ) -> impl Future<Output = Result<Vec<Member>, RepoError>> + Send;
+}
+
+pub trait TeamRepository: Clone + Send + Sync + 'static {
+    fn create_team(
+        &self,
+        request: CreateTeamRequest,
+    ) -> impl Future<Output = Result<Team, RepoError>> + Send;
+    fn get_team_by_name(
+        &self,
+        name: &TeamName,
+    ) -> impl Future<Output = Result<Option<Team>, RepoError>> + Send;
+    fn get_team_by_id(
+        &self,
+        id: TeamId,
+    ) -> impl Future<Output = Result<Option<Team>, RepoError>> + Send;
+    /// Teams the user owns or belongs to, oldest first
+    fn list_user_teams(
+        &self,
+        user: UserId,
+    ) -> impl Future<Output = Result<Vec<Team>, RepoError>> + Send;
+    /// Adds the user to the members of the team. Adding a member again is
+    /// a conflict
+    fn add_team_member(
+        &self,
+        request: AddTeamMemberRequest,
+    ) -> impl Future<Output = Result<TeamMember, RepoError>> + Send;
+    /// Members of the team, oldest first
+    fn list_team_members(
+        &self,
+        team: TeamId,
+    ) -> impl Future<Output = Result<Vec<TeamMember>, RepoError>> + Send;
+    /// Projects of the team, oldest first
+    fn list_team_projects(
+        &self,
+        team: TeamId,
+    ) -> impl Future<Output = Result<Vec<Project>, RepoError>> + Send;
 }
 
 pub trait LogRepository: Clone + Send + Sync + 'static {
     fn create_log(
         &self,
         request: CreateLogRequest,
-    ) -> impl Future<Output = Result<Log, CreateLogError>> + Send;
+    ) -> impl Future<Output = Result<Log, RepoError>> + Send;
     // fn update_log(&self, request: UpdateLogRequest)
     //     -> impl Future<Output = Result<Log, ()>> + Send;
-    fn list_project_logs(
+    /// The page of the logs of the project meeting every criterion of the
+    /// query, in its order. Numeric field filters compare numbers.
+    fn query_logs(
+        &self,
+        query: &LogQuery,
+    ) -> impl Future<Output = Result<Paged<Log>, RepoError>> + Send;
+    /// The page of the logs of the searched projects holding every term of
+    /// the search, most relevant first
+    fn search_logs(
+        &self,
+        search: &LogSearch,
+    ) -> impl Future<Output = Result<Paged<LogHit>, RepoError>> + Send;
+    /// Every log of the project, oldest first
+    fn stream_project_logs(&self, project: ProjectId) -> EntryStream<Log>;
+    fn get_project_log(
         &self,
         project: ProjectId,
-        page: Page,
-    ) -> impl Future<Output = Result<Paged<Log>, RepoQueryError>> + Send;
+        id: EntryId,
+    ) -> impl Future<Output = Result<Option<Log>, RepoError>> + Send;
+    /// Pins a log of the project. Pinning an already pinned log does nothing;
+    /// fails with `Conflict` if the project has `MAX_PINNED_LOGS` pinned logs
+    /// already, counted atomically with the pin
+    fn pin_log(&self, request: PinLogRequest)
+        -> impl Future<Output = Result<(), RepoError>> + Send;
+    /// Returns false if the log was not pinned
+    fn unpin_log(
+        &self,
+        project: ProjectId,
+        log: EntryId,
+    ) -> impl Future<Output = Result<bool, RepoError>> + Send;
+    /// Pinned logs of the project, most recently pinned first
+    fn list_pinned_logs(
+        &self,
+        project: ProjectId,
+    ) -> impl Future<Output = Result<Vec<Log>, RepoError>> + Send;
+}
+
+pub trait TemplateRepository: Clone + Send + Sync + 'static {
+    fn create_template(
+        &self,
+        request: CreateTemplateRequest,
+    ) -> impl Future<Output = Result<Template, RepoError>> + Send;
+    /// Personal templates of the user, followed by the templates of the
+    /// project if any
+    fn list_templates(
+        &self,
+        user: UserId,
+        project: Option<ProjectId>,
+    ) -> impl Future<Output = Result<Vec<Template>, RepoError>> + Send;
+}
+
+pub trait ViewRepository: Clone + Send + Sync + 'static {
+    /// Saves the view, replacing the view with the same id
+    fn save_view(&self, view: View) -> impl Future<Output = Result<(), RepoError>> + Send;
+    /// Views of the user, followed by the views shared with the projects,
+    /// by name
+    fn list_views(
+        &self,
+        user: UserId,
+        projects: &[ProjectId],
+    ) -> impl Future<Output = Result<Vec<View>, RepoError>> + Send;
+}
+
+pub trait AuditRepository: Clone + Send + Sync + 'static {
+    /// Appends the entry to the audit trail, which is never changed
+    fn record_audit(&self, entry: AuditEntry)
+        -> impl Future<Output = Result<(), RepoError>> + Send;
+    /// The page of the audit entries meeting the query, newest first
+    fn list_audit(
+        &self,
+        query: &AuditQuery,
+    ) -> impl Future<Output = Result<Paged<AuditEntry>, RepoError>> + Send;
+}
+
+/// Groups several repository operations so that they apply atomically.
+///
+/// `begin` returns a repository whose operations run in a transaction: they
+/// see the changes made before them in the transaction, while other clones
+/// of the repository only see them once committed. Dropping the transaction
+/// without committing it rolls it back.
+pub trait UnitOfWork: Clone + Send + Sync + 'static {
+    /// Starts a transaction. Transactions do not nest: calling `begin` on a
+    /// repository returned by `begin` fails
+    fn begin(&self) -> impl Future<Output = Result<Self, RepoError>> + Send;
+    /// Makes the changes of the transaction visible to everyone
+    fn commit(self) -> impl Future<Output = Result<(), RepoError>> + Send;
+    /// Drops the changes of the transaction
+    fn rollback(self) -> impl Future<Output = Result<(), RepoError>> + Send;
+    /// Whether the repository is a transaction returned by `begin`
+    fn in_transaction(&self) -> bool;
 }
@@ -0,0 +1,199 @@
+use std::{ops::Range, str::FromStr};
+
+use derive_more::derive::Error;
+
+use super::{
+    field::{FieldFilter, FieldOp},
+    model::{Date, ProjectName, Username},
+    template::is_field_name,
+};
+
+/// A search written in the query language: words and `"quoted phrases"` the
+/// logs must contain, and `key:value` criteria. Any term can be excluded
+/// with a leading `-`, except dates.
+///
+/// | term                | logs                                        |
+/// |---------------------|---------------------------------------------|
+/// | `disk`, `"disk full"` | holding the word, or the words in a row   |
+/// | `author:alice`      | written by the user                         |
+/// | `project:infra`     | of the project                              |
+/// | `after:2024-11-01`  | written on or after the day                 |
+/// | `before:2024-12-01` | written before the day                      |
+/// | `kind:task`         | whose field `kind` is `task`                |
+/// | `severity:>=2`      | whose field compares, see [`FieldFilter`]   |
+///
+/// ```
+/// # use std::str::FromStr;
+/// # use reverie::{Criterion, SearchQuery};
+/// let query = SearchQuery::from_str(r#"author:alice -tag:noise "disk full""#).unwrap();
+/// assert_eq!(query.terms.len(), 3);
+/// assert!(query.terms[1].negated);
+/// let phrase = &query.terms[2].criterion;
+/// assert!(matches!(phrase, Criterion::Phrase(phrase) if phrase == "disk full"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    /// the query as written, to point at its terms in errors
+    pub source: String,
+    pub terms: Vec<Term>,
+}
+
+/// A term of a [`SearchQuery`]
+#[derive(Debug, Clone)]
+pub struct Term {
+    /// the logs must not meet the criterion
+    pub negated: bool,
+    pub criterion: Criterion,
+    /// bytes of the term in the query
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Criterion {
+    Word(String),
+    Phrase(String),
+    Author(Username),
+    Project(ProjectName),
+    After(Date),
+    Before(Date),
+    Field(FieldFilter),
+}
+
+/// A query which can not be run, pointing at the faulty term
+#[derive(Debug, Clone, Error)]
+pub struct QuerySyntaxError {
+    #[error(not(source))]
+    pub query: String,
+    /// byte of the query where the error is
+    pub position: usize,
+    #[error(not(source))]
+    pub reason: String,
+}
+
+impl QuerySyntaxError {
+    pub fn new(query: &str, position: usize, reason: impl Into<String>) -> Self {
+        Self {
+            query: query.to_string(),
+            position,
+            reason: reason.into(),
+        }
+    }
+    /// Column of the error, counted in characters from 1
+    pub fn column(&self) -> usize {
+        self.query[..self.position].chars().count() + 1
+    }
+}
+
+impl std::fmt::Display for QuerySyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let column = self.column();
+        writeln!(f, "invalid search at column {column}: {}", self.reason)?;
+        writeln!(f, "  {}", self.query)?;
+        write!(f, "  {:>column$}", "^")
+    }
+}
+
+impl FromStr for SearchQuery {
+    type Err = QuerySyntaxError;
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let error = |at: usize, reason: &str| QuerySyntaxError::new(source, at, reason);
+        let mut terms = vec![];
+        let mut rest = source.char_indices().peekable();
+        while let Some(&(start, c)) = rest.peek() {
+            if c.is_whitespace() {
+                rest.next();
+                continue;
+            }
+            let negated = c == '-';
+            if negated {
+                rest.next();
+            }
+            let at = rest.peek().map_or(source.len(), |(at, _)| *at);
+            // a bare token, up to a space or the opening quote of a phrase
+            let mut end = at;
+            while let Some(&(i, c)) = rest.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                end = i + c.len_utf8();
+                rest.next();
+            }
+            let token = &source[at..end];
+            let quoted = match rest.peek() {
+                Some(&(quote, '"')) if token.is_empty() || token.ends_with(':') => {
+                    rest.next();
+                    let closing = source[quote + 1..]
+                        .find('"')
+                        .ok_or_else(|| error(quote, "unterminated quote"))?;
+                    let phrase = &source[quote + 1..quote + 1 + closing];
+                    end = quote + closing + 2;
+                    while rest.peek().is_some_and(|(i, _)| *i < end) {
+                        rest.next();
+                    }
+                    Some(phrase)
+                }
+                Some(&(quote, '"')) => return Err(error(quote, "quotes must start a term")),
+                _ => None,
+            };
+            if token.is_empty() && quoted.is_none() {
+                return Err(error(start, "nothing to exclude after -"));
+            }
+            let criterion = match (token.strip_suffix(':'), quoted) {
+                (None, Some(phrase)) => Criterion::Phrase(phrase.to_string()),
+                (Some(key), Some(value)) => criterion(source, at, key, value)?,
+                (_, None) => match token.split_once(':') {
+                    Some((key, value)) if !key.is_empty() => criterion(source, at, key, value)?,
+                    _ => Criterion::Word(token.to_string()),
+                },
+            };
+            if negated && matches!(criterion, Criterion::After(_) | Criterion::Before(_)) {
+                return Err(error(start, "dates can not be excluded"));
+            }
+            terms.push(Term {
+                negated,
+                criterion,
+                span: start..end,
+            });
+        }
+        Ok(Self {
+            source: source.to_string(),
+            terms,
+        })
+    }
+}
+
+/// The criterion of a `key:value` term starting at `at` in the query
+fn criterion(
+    query: &str,
+    at: usize,
+    key: &str,
+    value: &str,
+) -> Result<Criterion, QuerySyntaxError> {
+    let value_at = at + key.len() + 1;
+    let error = |reason: String| QuerySyntaxError::new(query, value_at, reason);
+    let invalid = |e: &dyn std::fmt::Display| error(e.to_string());
+    if value.is_empty() {
+        return Err(error(format!("missing value after {key}:")));
+    }
+    let criterion = match key {
+        "author" => Criterion::Author(Username::from_str(value).map_err(|e| invalid(&e))?),
+        "project" => Criterion::Project(ProjectName::from_str(value).map_err(|e| invalid(&e))?),
+        "after" => Criterion::After(Date::from_str(value).map_err(|e| invalid(&e))?),
+        "before" => Criterion::Before(Date::from_str(value).map_err(|e| invalid(&e))?),
+        name if is_field_name(name) => {
+            let filter = match value.starts_with(['=', '!', '<', '>']) {
+                true => format!("{name}{value}"),
+                false => format!("{name}={value}"),
+            };
+            let mut filter = FieldFilter::from_str(&filter).map_err(error)?;
+            filter.numeric = !matches!(filter.op, FieldOp::Eq | FieldOp::Ne)
+                && filter.value.trim().parse::<f64>().is_ok();
+            Criterion::Field(filter)
+        }
+        _ => {
+            let reason = format!("invalid field name {key}");
+            return Err(QuerySyntaxError::new(query, at, reason));
+        }
+    };
+    Ok(criterion)
+}
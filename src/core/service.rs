@@ -11,35 +11,312 @@
 //         .map(|ref author| ApiSuccess::new(StatusCode::CREATED, author.into()))
 // }
 
-use std::future::Future;
+use std::{future::Future, str::FromStr};
 
-use crate::{Page, Paged};
+use futures_util::{future, stream::BoxStream, StreamExt, TryStreamExt};
+use itertools::Itertools;
+
+use crate::{Keyed, Page, Paged};
 
 use super::{
+    audit::{AuditEntry, AuditQuery, Origin},
+    field::{self, FieldDefinition, FieldKind, InvalidField},
     model::{
-        InvalidProjectName, InvalidUsername, Log, Project, ProjectId, ProjectName, User, UserId,
-        Username,
+        EntryId, InvalidProjectName, InvalidUsername, Log, Member, Project, ProjectId, ProjectLogs,
+        ProjectMember, ProjectName, Role, User, UserId, Username, MAX_PINNED_LOGS,
     },
+    pagination::{page_of, SortOrder},
+    query::{self, LogHit, LogQuery, LogSearch, ProjectMatch, ProjectSearch},
     repo::{
-        AuthorRepository, CreateAuthorError, CreateAuthorRequest, CreateLogError, CreateLogRequest,
-        CreateProjectError, CreateProjectRequest, LogRepository, ProjectRepository, RepoQueryError,
+        AddMemberRequest, AddTeamMemberRequest, AuditRepository, AuthorRepository,
+        CreateAuthorRequest, CreateLogRequest, CreateProjectRequest, CreateTeamRequest,
+        CreateTemplateRequest, LogRepository, PinLogRequest, ProjectRepository, RepoError,
+        TeamRepository, TemplateRepository, UnitOfWork, ViewRepository,
     },
+    search::{Criterion, QuerySyntaxError},
+    similarity::{self, Bm25},
+    team::{Team, TeamId, TeamMember, TeamName},
+    template::{is_field_name, LogFields, Template, TemplateName},
+    view::{View, ViewName},
 };
 
+/// Words of a log looked for in the others to find related ones
+const RELATED_TERMS: usize = 16;
+/// Logs sharing the most words with a log among which related ones are ranked
+const RELATED_CANDIDATES: usize = 200;
+
+/// Why the owner's role can not be given nor taken
+const OWNER_BY_TRANSFER: &str = "the owner only changes by transferring the project";
+
 #[derive(Debug, Clone)]
 pub struct LogService<R>
 where
-    R: AuthorRepository + ProjectRepository + LogRepository,
+    R: AuthorRepository
+        + AuditRepository
+        + ProjectRepository
+        + LogRepository
+        + TeamRepository
+        + TemplateRepository
+        + ViewRepository
+        + UnitOfWork,
 {
     repo: R,
+    /// where the changes made through the service come from, as audited
+    origin: Origin,
 }
 
 impl<R> LogService<R>
 where
-    R: AuthorRepository + ProjectRepository + LogRepository,
+    R: AuthorRepository
+        + AuditRepository
+        + ProjectRepository
+        + LogRepository
+        + TeamRepository
+        + TemplateRepository
+        + ViewRepository
+        + UnitOfWork,
 {
     pub fn new(repo: R) -> Self {
-        Self { repo }
+        Self {
+            repo,
+            origin: Origin::default(),
+        }
+    }
+
+    /// The service auditing its changes as coming from `origin`
+    pub fn with_origin(self, origin: Origin) -> Self {
+        Self { origin, ..self }
+    }
+
+    /// Runs `work` on a service whose repository operations all happen in
+    /// one transaction, committed if `work` succeeds and rolled back
+    /// otherwise. Within a transaction, `work` joins it.
+    pub async fn atomically<T, F, Fut>(&self, work: F) -> Result<T, LogServiceError>
+    where
+        F: FnOnce(LogService<R>) -> Fut,
+        Fut: Future<Output = Result<T, LogServiceError>>,
+    {
+        if self.repo.in_transaction() {
+            return work(self.clone()).await;
+        }
+        let transaction = self.repo.begin().await?;
+        let service = LogService::new(transaction.clone()).with_origin(self.origin);
+        match work(service).await {
+            Ok(done) => {
+                transaction.commit().await?;
+                Ok(done)
+            }
+            Err(e) => {
+                transaction.rollback().await?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Appends the change to the audit trail, in the transaction making it
+    async fn audit(&self, mut entry: AuditEntry) -> Result<(), LogServiceError> {
+        entry.origin = self.origin;
+        Ok(self.repo.record_audit(entry).await?)
+    }
+
+    /// Name of the user with the role, as summarized in the audit trail
+    async fn member_summary(&self, user: UserId, role: Role) -> Result<String, LogServiceError> {
+        let user = self
+            .repo
+            .get_user_by_id(user)
+            .await?
+            .ok_or(LogServiceError::UserNotFound)?;
+        Ok(format!("{} {role}", user.name()))
+    }
+
+    /// The user of the name, none for reserved words which name nobody
+    async fn user_named(&self, name: &Username) -> Result<Option<User>, LogServiceError> {
+        if name.is_reserved() {
+            return Ok(None);
+        }
+        Ok(self.repo.get_user_by_name(name).await?)
+    }
+
+    /// The project of the name, none for reserved words which name nothing
+    async fn project_named(&self, name: &ProjectName) -> Result<Option<Project>, LogServiceError> {
+        if name.is_reserved() {
+            return Ok(None);
+        }
+        Ok(self.repo.get_project_by_name(name).await?)
+    }
+
+    /// Finds a user given either by id or by name
+    async fn resolve_user(&self, user: &str) -> Result<Option<User>, LogServiceError> {
+        let user = match UserId::from_str(user) {
+            Ok(id) => self.repo.get_user_by_id(id).await?,
+            Err(_) => match Username::from_str(user) {
+                Ok(name) => self.user_named(&name).await?,
+                Err(_) => None,
+            },
+        };
+        Ok(user)
+    }
+
+    /// Role of the user on the project, `None` if they are not a member.
+    /// Members of the team of the project get their role in the team, at
+    /// most maintainer, unless the project gives them another one.
+    async fn role(&self, user: UserId, project: &Project) -> Result<Option<Role>, LogServiceError> {
+        if project.meta.author == user {
+            return Ok(Some(Role::Owner));
+        }
+        let members = self.repo.list_project_members(project.id()).await?;
+        if let Some(member) = members.iter().find(|m| m.user == user) {
+            return Ok(Some(member.role));
+        }
+        let Some(team) = project.team else {
+            return Ok(None);
+        };
+        let team = self.repo.get_team_by_id(team).await?;
+        let team = team.ok_or(LogServiceError::TeamNotFound)?;
+        let role = self.team_role(user, &team).await?;
+        Ok(role.map(|role| role.min(Role::Maintainer)))
+    }
+
+    /// Role of the user in the team, `None` if they are not a member
+    async fn team_role(&self, user: UserId, team: &Team) -> Result<Option<Role>, LogServiceError> {
+        if team.meta.author == user {
+            return Ok(Some(Role::Owner));
+        }
+        let members = self.repo.list_team_members(team.id()).await?;
+        Ok(members.iter().find(|m| m.user == user).map(|m| m.role))
+    }
+
+    /// Checks that the user has at least the role in the team, returning the
+    /// team
+    async fn authorize_team(
+        &self,
+        user: UserId,
+        team: TeamId,
+        needed: Role,
+    ) -> Result<Team, LogServiceError> {
+        let team = self
+            .repo
+            .get_team_by_id(team)
+            .await?
+            .ok_or(LogServiceError::TeamNotFound)?;
+        if self.team_role(user, &team).await? >= Some(needed) {
+            return Ok(team);
+        }
+        let user = self
+            .repo
+            .get_user_by_id(user)
+            .await?
+            .ok_or(LogServiceError::UserNotFound)?;
+        Err(LogServiceError::NoTeamAccess(user.name, team.name, needed))
+    }
+
+    /// Checks that the user has at least the role on the project, returning
+    /// the project
+    async fn authorize(
+        &self,
+        user: UserId,
+        project: ProjectId,
+        needed: Role,
+    ) -> Result<Project, LogServiceError> {
+        let project = self
+            .repo
+            .get_project_by_id(project)
+            .await?
+            .ok_or(LogServiceError::ProjectNotFound)?;
+        if self.role(user, &project).await? >= Some(needed) {
+            return Ok(project);
+        }
+        let user = self
+            .repo
+            .get_user_by_id(user)
+            .await?
+            .ok_or(LogServiceError::UserNotFound)?;
+        Err(match needed {
+            Role::Reader => LogServiceError::NoReadAccess(user.name, project.name),
+            _ => LogServiceError::NoWriteAccess(user.name, project.name),
+        })
+    }
+
+    /// Projects whose logs the user can read: the ones they own, then the
+    /// ones they are a member of, then the other projects of their teams
+    async fn readable_projects(&self, user: UserId) -> Result<Vec<ProjectId>, LogServiceError> {
+        let mut projects: Vec<ProjectId> = self
+            .repo
+            .stream_user_projects(user)
+            .map_ok(|project| project.id())
+            .try_collect()
+            .await?;
+        let memberships = self.repo.list_user_memberships(user).await?;
+        projects.extend(memberships.into_iter().map(|m| m.project));
+        for team in self.repo.list_user_teams(user).await? {
+            for project in self.repo.list_team_projects(team.id()).await? {
+                if !projects.contains(&project.id()) {
+                    projects.push(project.id());
+                }
+            }
+        }
+        Ok(projects)
+    }
+
+    /// Restricts the search to the readable projects and resolves the names
+    /// of its terms, `author:me` standing for the reader
+    async fn resolve_search(
+        &self,
+        reader: UserId,
+        mut search: LogSearch,
+    ) -> Result<LogSearch, LogServiceError> {
+        let (mut wanted, mut excluded) = (vec![], vec![]);
+        for term in &search.query.terms {
+            let unknown =
+                |what: String| QuerySyntaxError::new(&search.query.source, term.span.start, what);
+            match &term.criterion {
+                Criterion::Author(name) => {
+                    let id = match name.to_string() == "me" {
+                        true => reader,
+                        false => {
+                            let user = self.user_named(name).await?;
+                            user.ok_or_else(|| unknown(format!("unknown user {name}")))?
+                                .id()
+                        }
+                    };
+                    match term.negated {
+                        true => search.excluded_authors.push(id),
+                        false => search.authors.push(id),
+                    }
+                }
+                Criterion::Project(name) => {
+                    let project = self.project_named(name).await?;
+                    let id = project
+                        .ok_or_else(|| unknown(format!("unknown project {name}")))?
+                        .id();
+                    match term.negated {
+                        true => excluded.push(id),
+                        false => wanted.push(id),
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut projects = self.readable_projects(reader).await?;
+        projects.retain(|p| (wanted.is_empty() || wanted.contains(p)) && !excluded.contains(p));
+        search.projects = projects;
+        Ok(search)
+    }
+
+    /// Stores user fields by id, so they can be filtered whatever the way the
+    /// user was designated
+    async fn normalize_user_field(
+        &self,
+        definition: &FieldDefinition,
+        value: &mut String,
+    ) -> Result<(), LogServiceError> {
+        if definition.kind == FieldKind::User {
+            let user = self.resolve_user(value).await?.ok_or_else(|| {
+                InvalidField(definition.name.clone(), format!("unknown user {value}"))
+            })?;
+            *value = user.id().to_string();
+        }
+        Ok(())
     }
 }
 
@@ -65,50 +342,115 @@ pub enum LogServiceError {
     UserNotFound,
     #[error("User exists")]
     UserExists,
+    #[error("Log not found")]
+    LogNotFound,
+    #[error("Log is not pinned")]
+    LogNotPinned,
+    #[error("A project can not have more than {0} pinned logs")]
+    TooManyPinnedLogs(usize),
+    #[error("Template not found")]
+    TemplateNotFound,
+    #[error("Template exists")]
+    TemplateExists,
+    #[error("View not found")]
+    ViewNotFound,
+    #[error("Member not found")]
+    MemberNotFound,
+    #[error("Team not found")]
+    TeamNotFound,
+    #[error("Team exists")]
+    TeamExists,
+    #[error("{0}")]
+    InvalidField(InvalidField),
     #[error("{0} has no read access on {1}")]
     NoReadAccess(Username, ProjectName),
     #[error("{0} has no write access on {1}")]
     NoWriteAccess(Username, ProjectName),
+    #[error("{0} is not a {2} of team {1}")]
+    NoTeamAccess(Username, TeamName, Role),
     #[error("{0}")]
     InvalidUsername(InvalidUsername),
     #[error("{0}")]
     InvalidProjectName(InvalidProjectName),
-    #[error("error: {0}")]
-    TechnicalError(Box<dyn std::error::Error>),
+    #[error("{0}")]
+    InvalidSearch(QuerySyntaxError),
+    #[error("{0}")]
+    InvalidRole(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Missing reference: {0}")]
+    MissingReference(String),
+    #[error("Storage unavailable, try again later: {0}")]
+    Unavailable(String),
+    #[error("Corrupt storage: {0}")]
+    Corrupt(String),
 }
 impl From<InvalidUsername> for LogServiceError {
     fn from(value: InvalidUsername) -> Self {
         Self::InvalidUsername(value)
     }
 }
-impl From<CreateAuthorError> for LogServiceError {
-    fn from(value: CreateAuthorError) -> Self {
-        Self::TechnicalError(Box::new(value))
+impl From<InvalidProjectName> for LogServiceError {
+    fn from(value: InvalidProjectName) -> Self {
+        Self::InvalidProjectName(value)
     }
 }
-impl From<CreateProjectError> for LogServiceError {
-    fn from(value: CreateProjectError) -> Self {
-        Self::TechnicalError(Box::new(value))
+impl From<QuerySyntaxError> for LogServiceError {
+    fn from(value: QuerySyntaxError) -> Self {
+        Self::InvalidSearch(value)
     }
 }
-impl From<CreateLogError> for LogServiceError {
-    fn from(value: CreateLogError) -> Self {
-        Self::TechnicalError(Box::new(value))
+impl From<InvalidField> for LogServiceError {
+    fn from(value: InvalidField) -> Self {
+        Self::InvalidField(value)
     }
 }
-impl From<RepoQueryError> for LogServiceError {
-    fn from(value: RepoQueryError) -> Self {
-        Self::TechnicalError(Box::new(value))
+impl From<RepoError> for LogServiceError {
+    fn from(value: RepoError) -> Self {
+        match value {
+            RepoError::NotFound(e) => Self::NotFound(e),
+            RepoError::Conflict(e) => Self::Conflict(e),
+            RepoError::ForeignKey(e) => Self::MissingReference(e),
+            RepoError::Unavailable(e) => Self::Unavailable(e),
+            RepoError::Corrupt(e) => Self::Corrupt(e),
+        }
     }
 }
 
 impl<R> LocalLogStoreService for LogService<R>
 where
-    R: AuthorRepository + ProjectRepository + LogRepository,
+    R: AuthorRepository
+        + AuditRepository
+        + ProjectRepository
+        + LogRepository
+        + TeamRepository
+        + TemplateRepository
+        + ViewRepository
+        + UnitOfWork,
 {
     async fn new_user(&self, username: Username) -> Result<User, LogServiceError> {
-        let request = CreateAuthorRequest { username };
-        Ok(self.repo.create_author(request).await?)
+        self.atomically(|service| async move {
+            let username = username.claimable()?;
+            if service.repo.get_user_by_name(&username).await?.is_some() {
+                return Err(LogServiceError::UserExists);
+            }
+            let request = CreateAuthorRequest { username };
+            // the name may have been taken since checked
+            let user = service
+                .repo
+                .create_author(request)
+                .await
+                .map_err(|e| match e {
+                    RepoError::Conflict(_) => LogServiceError::UserExists,
+                    e => e.into(),
+                })?;
+            let entry = AuditEntry::new(user.id(), "user_created", user.id()).after(user.name());
+            service.audit(entry).await?;
+            Ok(user)
+        })
+        .await
     }
     // async fn project_info(&self, name: &str) -> Result<ProjectDetails, ()> {
     //     self.repo.get_project_by_name(name).await.ok_or(())
@@ -119,11 +461,47 @@ where
         name: ProjectName,
         owner: UserId,
     ) -> Result<Project, LogServiceError> {
-        let request = CreateProjectRequest {
-            owner,
-            project_name: name,
-        };
-        Ok(self.repo.create_project(request).await?)
+        self.atomically(|service| async move {
+            let name = name.claimable()?;
+            if service.repo.get_project_by_name(&name).await?.is_some() {
+                return Err(LogServiceError::ProjectExists);
+            }
+            let request = CreateProjectRequest {
+                owner,
+                project_name: name,
+                team: None,
+            };
+            let project = service
+                .repo
+                .create_project(request)
+                .await
+                .map_err(|e| match e {
+                    RepoError::Conflict(_) => LogServiceError::ProjectExists,
+                    RepoError::ForeignKey(_) => LogServiceError::UserNotFound,
+                    e => e.into(),
+                })?;
+            let entry =
+                AuditEntry::new(owner, "project_created", project.id()).after(project.name());
+            service.audit(entry).await?;
+            Ok(project)
+        })
+        .await
+    }
+
+    async fn new_project_with_log(
+        &self,
+        name: ProjectName,
+        owner: UserId,
+        text: String,
+    ) -> Result<(Project, Log), LogServiceError> {
+        self.atomically(|service| async move {
+            let project = service.new_project(name, owner).await?;
+            let log = service
+                .add_log(owner, project.id(), text, LogFields::new())
+                .await?;
+            Ok((project, log))
+        })
+        .await
     }
 
     async fn add_log(
@@ -131,79 +509,1016 @@ where
         by: UserId,
         on: ProjectId,
         text: String,
+        mut fields: LogFields,
     ) -> Result<Log, LogServiceError> {
-        let request = CreateLogRequest {
-            author: by,
-            project: on,
-            text,
+        self.atomically(|service| async move {
+            service.authorize(by, on, Role::Writer).await?;
+            let schema = service.repo.list_project_fields(on).await?;
+            for definition in &schema {
+                if let Some(value) = fields.get_mut(&definition.name) {
+                    service.normalize_user_field(definition, value).await?;
+                }
+            }
+            field::validate(&schema, &fields)?;
+            let request = CreateLogRequest {
+                author: by,
+                project: on,
+                text,
+                fields,
+            };
+            let log = service.repo.create_log(request).await?;
+            service
+                .audit(AuditEntry::new(by, "log_added", log.id()).after(log.text()))
+                .await?;
+            Ok(log)
+        })
+        .await
+    }
+    async fn query_logs(
+        &self,
+        reader: UserId,
+        mut query: LogQuery,
+    ) -> Result<Paged<Log>, LogServiceError> {
+        self.authorize(reader, query.project, Role::Reader).await?;
+        let schema = self.repo.list_project_fields(query.project).await?;
+        for filter in query.fields.iter_mut() {
+            let definition = schema.iter().find(|d| d.name == filter.name);
+            match definition {
+                Some(definition) if definition.kind == FieldKind::Number => {
+                    definition.check(&filter.value)?;
+                    filter.numeric = true;
+                }
+                Some(definition) => {
+                    self.normalize_user_field(definition, &mut filter.value)
+                        .await?
+                }
+                None if schema.is_empty() => {}
+                None => Err(InvalidField(
+                    filter.name.clone(),
+                    "not declared by the project".into(),
+                ))?,
+            }
+        }
+        Ok(self.repo.query_logs(&query).await?)
+    }
+    async fn search_logs(
+        &self,
+        reader: UserId,
+        search: LogSearch,
+    ) -> Result<Paged<LogHit>, LogServiceError> {
+        let search = self.resolve_search(reader, search).await?;
+        if search.projects.is_empty() || search.is_void() {
+            return Ok(Paged::new(vec![], &search.page, 0));
+        }
+        Ok(self.repo.search_logs(&search).await?)
+    }
+    async fn related_logs(
+        &self,
+        reader: UserId,
+        log: EntryId,
+        limit: usize,
+    ) -> Result<Vec<LogHit>, LogServiceError> {
+        let projects = self.readable_projects(reader).await?;
+        let mut entry = None;
+        for &project in &projects {
+            if let Some(found) = self.repo.get_project_log(project, log).await? {
+                entry = self
+                    .repo
+                    .get_project_by_id(project)
+                    .await?
+                    .map(|p| (p.name, found));
+                break;
+            }
+        }
+        let entry = entry.ok_or(LogServiceError::LogNotFound)?;
+        // only the logs found by the full-text search of the longest words of
+        // the entry are ranked, rather than every readable log
+        let terms = similarity::terms(entry.1.text()).into_iter().unique();
+        let terms = terms.sorted_by_key(|term| std::cmp::Reverse(term.chars().count()));
+        let mut search =
+            LogSearch::any_of(terms.take(RELATED_TERMS)).page(Page::new(1, RELATED_CANDIDATES));
+        search.projects = projects;
+        if search.is_void() {
+            return Ok(vec![]);
+        }
+        let candidates = self.repo.search_logs(&search).await?.data.into_iter();
+        let others = candidates.filter(|hit| hit.log.id() != log);
+        // the entry first, the others ranked by their similarity with it
+        let logs: Vec<(ProjectName, Log)> = std::iter::once(entry)
+            .chain(others.map(|hit| (hit.project, hit.log)))
+            .collect();
+        let bm25 = Bm25::new(logs.iter().map(|(_, log)| log.text()));
+        let mut related: Vec<LogHit> = bm25
+            .similar(0)
+            .into_iter()
+            .map(|(other, score, shared)| {
+                let (project, log) = logs[other].clone();
+                LogHit {
+                    snippet: query::snippet(log.text(), &shared),
+                    log,
+                    project,
+                    score,
+                }
+            })
+            .collect();
+        // most similar first, then newest first
+        related.sort_by(|a, b| {
+            let newer = b.log.cursor().cmp(&a.log.cursor());
+            b.score.total_cmp(&a.score).then(newer)
+        });
+        related.truncate(limit);
+        Ok(related)
+    }
+    async fn declare_field(
+        &self,
+        by: UserId,
+        project: ProjectId,
+        name: String,
+        kind: FieldKind,
+        required: bool,
+    ) -> Result<FieldDefinition, LogServiceError> {
+        self.atomically(|service| async move {
+            service.authorize(by, project, Role::Maintainer).await?;
+            if !is_field_name(&name) {
+                Err(InvalidField(
+                    name.clone(),
+                    "only letters, digits, _ and - are allowed".into(),
+                ))?;
+            }
+            let previous = service
+                .repo
+                .list_project_fields(project)
+                .await?
+                .into_iter()
+                .find(|d| d.name == name);
+            let definition = FieldDefinition {
+                project,
+                name,
+                kind,
+                required,
+            };
+            service.repo.set_project_field(definition.clone()).await?;
+            let mut entry = AuditEntry::new(by, "field_declared", project).after(&definition);
+            if let Some(previous) = previous {
+                entry = entry.before(previous);
+            }
+            service.audit(entry).await?;
+            Ok(definition)
+        })
+        .await
+    }
+    async fn fields(
+        &self,
+        reader: UserId,
+        project: ProjectId,
+    ) -> Result<Vec<FieldDefinition>, LogServiceError> {
+        self.authorize(reader, project, Role::Reader).await?;
+        Ok(self.repo.list_project_fields(project).await?)
+    }
+
+    async fn new_template(
+        &self,
+        name: TemplateName,
+        author: UserId,
+        project: Option<ProjectId>,
+        body: String,
+    ) -> Result<Template, LogServiceError> {
+        self.atomically(|service| async move {
+            if let Some(project) = project {
+                service.authorize(author, project, Role::Writer).await?;
+            }
+            let exists = service
+                .repo
+                .list_templates(author, project)
+                .await?
+                .into_iter()
+                .any(|t| t.name == name && t.project == project);
+            if exists {
+                return Err(LogServiceError::TemplateExists);
+            }
+            let request = CreateTemplateRequest {
+                author,
+                project,
+                name,
+                body,
+            };
+            let template = service.repo.create_template(request).await?;
+            let entry = AuditEntry::new(author, "template_created", template.id());
+            service.audit(entry.after(template.name())).await?;
+            Ok(template)
+        })
+        .await
+    }
+    async fn templates(
+        &self,
+        user: UserId,
+        project: Option<ProjectId>,
+    ) -> Result<Vec<Template>, LogServiceError> {
+        if let Some(project) = project {
+            self.authorize(user, project, Role::Reader).await?;
+        }
+        Ok(self.repo.list_templates(user, project).await?)
+    }
+    async fn template(
+        &self,
+        name: TemplateName,
+        user: UserId,
+        project: Option<ProjectId>,
+    ) -> Result<Template, LogServiceError> {
+        if let Some(project) = project {
+            self.authorize(user, project, Role::Reader).await?;
+        }
+        // project templates take precedence over personal ones
+        self.repo
+            .list_templates(user, project)
+            .await?
+            .into_iter()
+            .filter(|t| t.name == name)
+            .max_by_key(|t| t.project.is_some())
+            .ok_or(LogServiceError::TemplateNotFound)
+    }
+    async fn save_view(
+        &self,
+        author: UserId,
+        name: ViewName,
+        query: String,
+        project: Option<ProjectId>,
+    ) -> Result<View, LogServiceError> {
+        self.atomically(|service| async move {
+            LogSearch::parse(&query)?;
+            if let Some(project) = project {
+                service.authorize(author, project, Role::Reader).await?;
+            }
+            let saved = service.repo.list_views(author, &[]).await?;
+            let (view, previous) = match saved.into_iter().find(|v| v.name == name) {
+                Some(view) => (view.clone().updated(query, project), Some(view)),
+                None => (View::new(name, author, query, project), None),
+            };
+            service.repo.save_view(view.clone()).await?;
+            let mut entry = AuditEntry::new(author, "view_saved", view.id()).after(&view);
+            if let Some(previous) = previous {
+                entry = entry.before(previous);
+            }
+            service.audit(entry).await?;
+            Ok(view)
+        })
+        .await
+    }
+    async fn views(&self, user: UserId) -> Result<Vec<View>, LogServiceError> {
+        let projects = self.readable_projects(user).await?;
+        Ok(self.repo.list_views(user, &projects).await?)
+    }
+    async fn view(&self, name: ViewName, user: UserId) -> Result<View, LogServiceError> {
+        // the views of the user come first and hide the shared ones
+        self.views(user)
+            .await?
+            .into_iter()
+            .find(|v| v.name == name)
+            .ok_or(LogServiceError::ViewNotFound)
+    }
+    async fn logs(
+        &self,
+        reader: UserId,
+        project: ProjectId,
+        page: Page,
+    ) -> Result<Paged<Log>, LogServiceError> {
+        self.authorize(reader, project, Role::Reader).await?;
+        let query = LogQuery::project(project).page(page);
+        Ok(self.repo.query_logs(&query).await?)
+    }
+    async fn stream_logs(
+        &self,
+        reader: UserId,
+        project: ProjectId,
+    ) -> Result<BoxStream<'static, Result<Log, LogServiceError>>, LogServiceError> {
+        self.authorize(reader, project, Role::Reader).await?;
+        Ok(self
+            .repo
+            .stream_project_logs(project)
+            .map_err(LogServiceError::from)
+            .boxed())
+    }
+    async fn project_logs(
+        &self,
+        reader: UserId,
+        query: LogQuery,
+    ) -> Result<ProjectLogs, LogServiceError> {
+        let (project, unfiltered) = (query.project, query.is_unfiltered());
+        let logs = self.query_logs(reader, query).await?;
+        let pinned = if unfiltered {
+            self.repo.list_pinned_logs(project).await?
+        } else {
+            vec![]
         };
-        Ok(self.repo.create_log(request).await?)
+        Ok(ProjectLogs { pinned, logs })
+    }
+    async fn pin(&self, by: UserId, on: ProjectId, log: EntryId) -> Result<Log, LogServiceError> {
+        self.atomically(|service| async move {
+            service.authorize(by, on, Role::Writer).await?;
+            let entry = service
+                .repo
+                .get_project_log(on, log)
+                .await?
+                .ok_or(LogServiceError::LogNotFound)?;
+            let request = PinLogRequest {
+                by,
+                project: on,
+                log,
+            };
+            match service.repo.pin_log(request).await {
+                Err(RepoError::Conflict(_)) => {
+                    return Err(LogServiceError::TooManyPinnedLogs(MAX_PINNED_LOGS))
+                }
+                pinned => pinned?,
+            }
+            service
+                .audit(AuditEntry::new(by, "log_pinned", log))
+                .await?;
+            Ok(entry)
+        })
+        .await
     }
-    async fn logs(&self, project: ProjectId, page: Page) -> Result<Paged<Log>, LogServiceError> {
-        Ok(self.repo.list_project_logs(project, page).await?)
+    async fn unpin(&self, by: UserId, on: ProjectId, log: EntryId) -> Result<(), LogServiceError> {
+        self.atomically(|service| async move {
+            service.authorize(by, on, Role::Writer).await?;
+            if !service.repo.unpin_log(on, log).await? {
+                return Err(LogServiceError::LogNotPinned);
+            }
+            service
+                .audit(AuditEntry::new(by, "log_unpinned", log))
+                .await
+        })
+        .await
     }
-    async fn projects_of(&self, user: UserId, page: Page) -> Paged<Project> {
-        self.repo.list_user_projects(user, page).await
+    async fn add_member(
+        &self,
+        by: UserId,
+        project: ProjectId,
+        user: UserId,
+        role: Role,
+    ) -> Result<Member, LogServiceError> {
+        self.atomically(|service| async move {
+            let project = service.authorize(by, project, Role::Maintainer).await?;
+            if role == Role::Owner {
+                return Err(LogServiceError::InvalidRole(OWNER_BY_TRANSFER.into()));
+            }
+            let member = service
+                .repo
+                .get_user_by_id(user)
+                .await?
+                .ok_or(LogServiceError::UserNotFound)?;
+            if project.meta.author == user {
+                let owner = format!("{} owns {}", member.name, project.name);
+                return Err(LogServiceError::Conflict(owner));
+            }
+            let request = AddMemberRequest {
+                by,
+                project: project.id(),
+                user,
+                role,
+            };
+            let added = service.repo.add_project_member(request).await?;
+            let entry = AuditEntry::new(by, "member_added", project.id());
+            service
+                .audit(entry.after(format!("{} {role}", member.name())))
+                .await?;
+            Ok(added)
+        })
+        .await
+    }
+    async fn set_role(
+        &self,
+        by: UserId,
+        project: ProjectId,
+        user: UserId,
+        role: Role,
+    ) -> Result<ProjectMember, LogServiceError> {
+        self.atomically(|service| async move {
+            let project = service.authorize(by, project, Role::Maintainer).await?;
+            if role == Role::Owner || project.meta.author == user {
+                return Err(LogServiceError::InvalidRole(OWNER_BY_TRANSFER.into()));
+            }
+            let previous = service
+                .repo
+                .list_project_members(project.id())
+                .await?
+                .into_iter()
+                .find(|m| m.user == user);
+            if !service
+                .repo
+                .set_member_role(project.id(), user, role)
+                .await?
+            {
+                return Err(LogServiceError::MemberNotFound);
+            }
+            let user = service
+                .repo
+                .get_user_by_id(user)
+                .await?
+                .ok_or(LogServiceError::UserNotFound)?;
+            let mut entry = AuditEntry::new(by, "member_role_changed", project.id())
+                .after(format!("{} {role}", user.name()));
+            if let Some(previous) = previous {
+                entry = entry.before(format!("{} {}", user.name(), previous.role));
+            }
+            service.audit(entry).await?;
+            Ok(ProjectMember { user, role })
+        })
+        .await
+    }
+    async fn remove_member(
+        &self,
+        by: UserId,
+        project: ProjectId,
+        user: UserId,
+    ) -> Result<(), LogServiceError> {
+        self.atomically(|service| async move {
+            // members can leave by themselves
+            let needed = match by == user {
+                true => Role::Reader,
+                false => Role::Maintainer,
+            };
+            let project = service.authorize(by, project, needed).await?;
+            if project.meta.author == user {
+                return Err(LogServiceError::InvalidRole(OWNER_BY_TRANSFER.into()));
+            }
+            let member = service
+                .repo
+                .list_project_members(project.id())
+                .await?
+                .into_iter()
+                .find(|m| m.user == user)
+                .ok_or(LogServiceError::MemberNotFound)?;
+            if !service
+                .repo
+                .remove_project_member(project.id(), user)
+                .await?
+            {
+                return Err(LogServiceError::MemberNotFound);
+            }
+            let removed = service.member_summary(user, member.role).await?;
+            let entry = AuditEntry::new(by, "member_removed", project.id()).before(removed);
+            service.audit(entry).await
+        })
+        .await
+    }
+    async fn transfer_project(
+        &self,
+        by: UserId,
+        project: ProjectId,
+        owner: UserId,
+    ) -> Result<Project, LogServiceError> {
+        let project = self.authorize(by, project, Role::Owner).await?;
+        let Some(new_owner) = self.repo.get_user_by_id(owner).await? else {
+            return Err(LogServiceError::UserNotFound);
+        };
+        if owner == by {
+            return Ok(project);
+        }
+        let id = project.id();
+        self.atomically(|service| async move {
+            service.repo.remove_project_member(id, owner).await?;
+            service.repo.transfer_project(id, owner).await?;
+            let previous = AddMemberRequest {
+                by: owner,
+                project: id,
+                user: by,
+                role: Role::Maintainer,
+            };
+            service.repo.add_project_member(previous).await?;
+            let previous = service.member_summary(by, Role::Owner).await?;
+            let entry = AuditEntry::new(by, "project_transferred", id)
+                .before(previous)
+                .after(format!("{} {}", new_owner.name(), Role::Owner));
+            service.audit(entry).await
+        })
+        .await?;
+        self.repo
+            .get_project_by_id(id)
+            .await?
+            .ok_or(LogServiceError::ProjectNotFound)
+    }
+    async fn members(
+        &self,
+        reader: UserId,
+        project: ProjectId,
+    ) -> Result<Vec<ProjectMember>, LogServiceError> {
+        let project = self.authorize(reader, project, Role::Reader).await?;
+        let mut roles = vec![(project.meta.author, Role::Owner)];
+        let members = self.repo.list_project_members(project.id()).await?;
+        roles.extend(members.into_iter().map(|m| (m.user, m.role)));
+        let mut members = vec![];
+        for (user, role) in roles {
+            let user = self.repo.get_user_by_id(user).await?;
+            let user = user.ok_or(LogServiceError::UserNotFound)?;
+            members.push(ProjectMember { user, role });
+        }
+        Ok(members)
+    }
+    async fn new_team(&self, name: TeamName, owner: UserId) -> Result<Team, LogServiceError> {
+        self.atomically(|service| async move {
+            if service.repo.get_team_by_name(&name).await?.is_some() {
+                return Err(LogServiceError::TeamExists);
+            }
+            let request = CreateTeamRequest { owner, name };
+            let team = service
+                .repo
+                .create_team(request)
+                .await
+                .map_err(|e| match e {
+                    RepoError::Conflict(_) => LogServiceError::TeamExists,
+                    RepoError::ForeignKey(_) => LogServiceError::UserNotFound,
+                    e => e.into(),
+                })?;
+            let entry = AuditEntry::new(owner, "team_created", team.id()).after(team.name());
+            service.audit(entry).await?;
+            Ok(team)
+        })
+        .await
+    }
+    async fn get_team(&self, name: TeamName) -> Result<Option<Team>, LogServiceError> {
+        Ok(self.repo.get_team_by_name(&name).await?)
+    }
+    async fn teams_of(&self, user: UserId) -> Result<Vec<Team>, LogServiceError> {
+        Ok(self.repo.list_user_teams(user).await?)
+    }
+    async fn add_team_member(
+        &self,
+        by: UserId,
+        team: TeamId,
+        user: UserId,
+        role: Role,
+    ) -> Result<TeamMember, LogServiceError> {
+        self.atomically(|service| async move {
+            let team = service.authorize_team(by, team, Role::Maintainer).await?;
+            if role == Role::Owner {
+                let owner = "the owner of a team is the user who created it";
+                return Err(LogServiceError::InvalidRole(owner.into()));
+            }
+            let member = service
+                .repo
+                .get_user_by_id(user)
+                .await?
+                .ok_or(LogServiceError::UserNotFound)?;
+            if team.meta.author == user {
+                let owner = format!("{} owns {}", member.name, team.name);
+                return Err(LogServiceError::Conflict(owner));
+            }
+            let request = AddTeamMemberRequest {
+                by,
+                team: team.id(),
+                user,
+                role,
+            };
+            let added = service.repo.add_team_member(request).await?;
+            let entry = AuditEntry::new(by, "team_member_added", team.id());
+            service
+                .audit(entry.after(format!("{} {role}", member.name())))
+                .await?;
+            Ok(added)
+        })
+        .await
+    }
+    async fn team_members(
+        &self,
+        reader: UserId,
+        team: TeamId,
+    ) -> Result<Vec<ProjectMember>, LogServiceError> {
+        let team = self.authorize_team(reader, team, Role::Reader).await?;
+        let mut roles = vec![(team.meta.author, Role::Owner)];
+        let members = self.repo.list_team_members(team.id()).await?;
+        roles.extend(members.into_iter().map(|m| (m.user, m.role)));
+        let mut members = vec![];
+        for (user, role) in roles {
+            let user = self.repo.get_user_by_id(user).await?;
+            let user = user.ok_or(LogServiceError::UserNotFound)?;
+            members.push(ProjectMember { user, role });
+        }
+        Ok(members)
+    }
+    async fn new_team_project(
+        &self,
+        name: ProjectName,
+        owner: UserId,
+        team: TeamId,
+    ) -> Result<Project, LogServiceError> {
+        self.atomically(|service| async move {
+            let team = service
+                .authorize_team(owner, team, Role::Maintainer)
+                .await?;
+            let name = name.claimable()?;
+            if service.repo.get_project_by_name(&name).await?.is_some() {
+                return Err(LogServiceError::ProjectExists);
+            }
+            let request = CreateProjectRequest {
+                owner,
+                project_name: name,
+                team: Some(team.id()),
+            };
+            let project = service
+                .repo
+                .create_project(request)
+                .await
+                .map_err(|e| match e {
+                    RepoError::Conflict(_) => LogServiceError::ProjectExists,
+                    e => e.into(),
+                })?;
+            let entry = AuditEntry::new(owner, "project_created", project.id());
+            let created = format!("{} (team {})", project.name(), team.name());
+            service.audit(entry.after(created)).await?;
+            Ok(project)
+        })
+        .await
+    }
+    async fn team_projects(
+        &self,
+        reader: UserId,
+        team: TeamId,
+    ) -> Result<Vec<Project>, LogServiceError> {
+        let team = self.authorize_team(reader, team, Role::Reader).await?;
+        Ok(self.repo.list_team_projects(team.id()).await?)
+    }
+    async fn projects_of(
+        &self,
+        reader: UserId,
+        user: UserId,
+        page: Page,
+    ) -> Result<Paged<Project>, LogServiceError> {
+        if reader == user {
+            return Ok(self.repo.list_user_projects(user, page).await?);
+        }
+        let readable = self.readable_projects(reader).await?;
+        let projects: Vec<Project> = self
+            .repo
+            .stream_user_projects(user)
+            .try_filter(|project| future::ready(readable.contains(&project.id())))
+            .try_collect()
+            .await?;
+        Ok(page_of(projects.iter(), page, SortOrder::Asc))
+    }
+    async fn stream_projects_of(
+        &self,
+        reader: UserId,
+        user: UserId,
+    ) -> Result<BoxStream<'static, Result<Project, LogServiceError>>, LogServiceError> {
+        let projects = self
+            .repo
+            .stream_user_projects(user)
+            .map_err(LogServiceError::from);
+        if reader == user {
+            return Ok(projects.boxed());
+        }
+        let readable = self.readable_projects(reader).await?;
+        Ok(projects
+            .try_filter(move |project| future::ready(readable.contains(&project.id())))
+            .boxed())
+    }
+    async fn search_projects(
+        &self,
+        reader: UserId,
+        search: ProjectSearch,
+    ) -> Result<Vec<ProjectMatch>, LogServiceError> {
+        let readable = self.readable_projects(reader).await?;
+        let mut matches: Vec<ProjectMatch> = self
+            .repo
+            .list_project_activity(&search)
+            .await?
+            .into_iter()
+            .filter(|activity| readable.contains(&activity.project.id()))
+            .filter_map(|activity| {
+                let distance =
+                    query::name_distance(&search.text, &activity.project.name.to_string())?;
+                Some(ProjectMatch { activity, distance })
+            })
+            .collect();
+        // closest first, then the most recently active
+        matches.sort_by_key(|m| {
+            (
+                m.distance,
+                std::cmp::Reverse(m.activity.last_activity.as_i64()),
+            )
+        });
+        matches.truncate(search.limit);
+        Ok(matches)
     }
     #[cfg(feature = "admin")]
-    async fn list_users(&self, page: Page) -> Paged<User> {
-        self.repo.list_users(page).await
+    async fn list_users(&self, page: Page) -> Result<Paged<User>, LogServiceError> {
+        Ok(self.repo.list_users(page).await?)
+    }
+    async fn audit_log(&self, query: AuditQuery) -> Result<Paged<AuditEntry>, LogServiceError> {
+        Ok(self.repo.list_audit(&query).await?)
     }
 
     async fn projects_of_named(
         &self,
+        reader: UserId,
         name: Username,
         page: Page,
     ) -> Result<Paged<Project>, LogServiceError> {
         let user = self
-            .repo
-            .get_user_by_name(&name)
-            .await
+            .user_named(&name)
+            .await?
             .ok_or(LogServiceError::UserNotFound)?;
-        Ok(self.projects_of(user.id(), page).await)
+        self.projects_of(reader, user.id(), page).await
     }
-    async fn get_user(&self, username: Username) -> Option<User> {
-        self.repo.get_user_by_name(&username).await
+    async fn get_user(&self, username: Username) -> Result<Option<User>, LogServiceError> {
+        self.user_named(&username).await
     }
-    async fn get_project(&self, name: ProjectName) -> Option<Project> {
-        self.repo.get_project_by_name(&name).await
+    async fn get_project(
+        &self,
+        reader: UserId,
+        name: ProjectName,
+    ) -> Result<Option<Project>, LogServiceError> {
+        match self.project_named(&name).await? {
+            Some(project) => Ok(Some(
+                self.authorize(reader, project.id(), Role::Reader).await?,
+            )),
+            None => Ok(None),
+        }
     }
 }
 
+/// Operations of the store. Those on a project are done by a user, whose
+/// [`Role`] must allow them: `NoReadAccess` and `NoWriteAccess` tell them
+/// apart from a missing project.
 pub trait LocalLogStoreService {
     fn new_user(
         &self,
         username: Username,
     ) -> impl Future<Output = Result<User, LogServiceError>> + Send;
-    /// Return informations about the project + stats
+    // Return informations about the project + stats
     // fn project_info(&self, name: &str) -> impl Future<Output = Result<Project, ()>> + Send;
 
-    fn get_user(&self, username: Username) -> impl Future<Output = Option<User>> + Send;
-    fn get_project(&self, name: ProjectName) -> impl Future<Output = Option<Project>> + Send;
+    fn get_user(
+        &self,
+        username: Username,
+    ) -> impl Future<Output = Result<Option<User>, LogServiceError>> + Send;
+    /// the project of the name, if the reader may read it
+    fn get_project(
+        &self,
+        reader: UserId,
+        name: ProjectName,
+    ) -> impl Future<Output = Result<Option<Project>, LogServiceError>> + Send;
 
     /// create a new project by name
     fn new_project(
         &self,
         name: ProjectName,
         owner: UserId,
-    ) -> impl Future<Output = Result<Project, LogServiceError>>;
+    ) -> impl Future<Output = Result<Project, LogServiceError>> + Send;
+    /// create a new project with its first log, both or neither
+    fn new_project_with_log(
+        &self,
+        name: ProjectName,
+        owner: UserId,
+        text: String,
+    ) -> impl Future<Output = Result<(Project, Log), LogServiceError>> + Send;
     /// add a log to the project
     fn add_log(
         &self,
         by_user: UserId,
         on_project: ProjectId,
         text: String,
-    ) -> impl Future<Output = Result<Log, LogServiceError>>;
+        fields: LogFields,
+    ) -> impl Future<Output = Result<Log, LogServiceError>> + Send;
+    /// logs of the project meeting every criterion of the query, the field
+    /// filters being checked against the fields declared by the project
+    fn query_logs(
+        &self,
+        reader: UserId,
+        query: LogQuery,
+    ) -> impl Future<Output = Result<Paged<Log>, LogServiceError>> + Send;
+    /// logs of the projects the user can read meeting every term of the
+    /// search, best matches first
+    fn search_logs(
+        &self,
+        reader: UserId,
+        search: LogSearch,
+    ) -> impl Future<Output = Result<Paged<LogHit>, LogServiceError>> + Send;
+    /// at most `limit` logs of the projects the user can read whose text is
+    /// similar to the log, most similar first, scored from 0 to about 1.
+    /// Only the logs the full-text search finds sharing the most words with
+    /// it are compared.
+    fn related_logs(
+        &self,
+        reader: UserId,
+        log: EntryId,
+        limit: usize,
+    ) -> impl Future<Output = Result<Vec<LogHit>, LogServiceError>> + Send;
+    /// declare (or redefine) a custom field of the project logs
+    fn declare_field(
+        &self,
+        by_user: UserId,
+        project: ProjectId,
+        name: String,
+        kind: FieldKind,
+        required: bool,
+    ) -> impl Future<Output = Result<FieldDefinition, LogServiceError>> + Send;
+    /// custom fields declared by the project
+    fn fields(
+        &self,
+        reader: UserId,
+        project: ProjectId,
+    ) -> impl Future<Output = Result<Vec<FieldDefinition>, LogServiceError>> + Send;
+    /// create a template, personal when no project is given
+    fn new_template(
+        &self,
+        name: TemplateName,
+        author: UserId,
+        project: Option<ProjectId>,
+        body: String,
+    ) -> impl Future<Output = Result<Template, LogServiceError>> + Send;
+    /// templates available to the user (on the project if any)
+    fn templates(
+        &self,
+        user: UserId,
+        project: Option<ProjectId>,
+    ) -> impl Future<Output = Result<Vec<Template>, LogServiceError>> + Send;
+    /// find a template by name, looking into the project templates first
+    fn template(
+        &self,
+        name: TemplateName,
+        user: UserId,
+        project: Option<ProjectId>,
+    ) -> impl Future<Output = Result<Template, LogServiceError>> + Send;
+    /// save a search under a name, replacing the view of the author with
+    /// the same name, and share it with the project if any
+    fn save_view(
+        &self,
+        author: UserId,
+        name: ViewName,
+        query: String,
+        project: Option<ProjectId>,
+    ) -> impl Future<Output = Result<View, LogServiceError>> + Send;
+    /// views of the user, followed by the views shared with the projects
+    /// they can read
+    fn views(
+        &self,
+        user: UserId,
+    ) -> impl Future<Output = Result<Vec<View>, LogServiceError>> + Send;
+    /// find a view by name, looking into the views of the user first
+    fn view(
+        &self,
+        name: ViewName,
+        user: UserId,
+    ) -> impl Future<Output = Result<View, LogServiceError>> + Send;
     fn logs(
         &self,
+        reader: UserId,
         project: ProjectId,
         page: Page,
     ) -> impl Future<Output = Result<Paged<Log>, LogServiceError>> + Send;
-    fn projects_of(&self, user: UserId, page: Page) -> impl Future<Output = Paged<Project>> + Send;
+    /// every log of the project, oldest first, read as the stream is polled
+    /// once the reader is known to have access
+    fn stream_logs(
+        &self,
+        reader: UserId,
+        project: ProjectId,
+    ) -> impl Future<
+        Output = Result<BoxStream<'static, Result<Log, LogServiceError>>, LogServiceError>,
+    > + Send;
+    /// logs of the query, following the pinned logs of the project when the
+    /// query lists its whole timeline
+    fn project_logs(
+        &self,
+        reader: UserId,
+        query: LogQuery,
+    ) -> impl Future<Output = Result<ProjectLogs, LogServiceError>> + Send;
+    /// pin a log on top of the project logs
+    fn pin(
+        &self,
+        by_user: UserId,
+        on_project: ProjectId,
+        log: EntryId,
+    ) -> impl Future<Output = Result<Log, LogServiceError>> + Send;
+    fn unpin(
+        &self,
+        by_user: UserId,
+        on_project: ProjectId,
+        log: EntryId,
+    ) -> impl Future<Output = Result<(), LogServiceError>> + Send;
+    /// projects owned by the user, among those the reader may read
+    fn projects_of(
+        &self,
+        reader: UserId,
+        user: UserId,
+        page: Page,
+    ) -> impl Future<Output = Result<Paged<Project>, LogServiceError>> + Send;
+    /// every project of the user the reader may read, oldest first, read as
+    /// the stream is polled
+    fn stream_projects_of(
+        &self,
+        reader: UserId,
+        user: UserId,
+    ) -> impl Future<
+        Output = Result<BoxStream<'static, Result<Project, LogServiceError>>, LogServiceError>,
+    > + Send;
+    /// add a user to the members of the project with their role, which
+    /// only its maintainers can
+    fn add_member(
+        &self,
+        by_user: UserId,
+        project: ProjectId,
+        user: UserId,
+        role: Role,
+    ) -> impl Future<Output = Result<Member, LogServiceError>> + Send;
+    /// change the role of a member of the project, which only its
+    /// maintainers can
+    fn set_role(
+        &self,
+        by_user: UserId,
+        project: ProjectId,
+        user: UserId,
+        role: Role,
+    ) -> impl Future<Output = Result<ProjectMember, LogServiceError>> + Send;
+    /// remove a member of the project: a maintainer removing them, or the
+    /// member leaving
+    fn remove_member(
+        &self,
+        by_user: UserId,
+        project: ProjectId,
+        user: UserId,
+    ) -> impl Future<Output = Result<(), LogServiceError>> + Send;
+    /// hand the project over to another user, the previous owner staying a
+    /// maintainer. Only the owner can.
+    fn transfer_project(
+        &self,
+        by_user: UserId,
+        project: ProjectId,
+        owner: UserId,
+    ) -> impl Future<Output = Result<Project, LogServiceError>> + Send;
+    /// owner of the project followed by its members, oldest first, with
+    /// their roles
+    fn members(
+        &self,
+        reader: UserId,
+        project: ProjectId,
+    ) -> impl Future<Output = Result<Vec<ProjectMember>, LogServiceError>> + Send;
+    /// create a team owned by the user
+    fn new_team(
+        &self,
+        name: TeamName,
+        owner: UserId,
+    ) -> impl Future<Output = Result<Team, LogServiceError>> + Send;
+    fn get_team(
+        &self,
+        name: TeamName,
+    ) -> impl Future<Output = Result<Option<Team>, LogServiceError>> + Send;
+    /// teams the user owns or belongs to, oldest first
+    fn teams_of(
+        &self,
+        user: UserId,
+    ) -> impl Future<Output = Result<Vec<Team>, LogServiceError>> + Send;
+    /// add a user to the members of the team with the role they get on its
+    /// projects, which only its maintainers can
+    fn add_team_member(
+        &self,
+        by_user: UserId,
+        team: TeamId,
+        user: UserId,
+        role: Role,
+    ) -> impl Future<Output = Result<TeamMember, LogServiceError>> + Send;
+    /// owner of the team followed by its members, oldest first, with their
+    /// roles
+    fn team_members(
+        &self,
+        reader: UserId,
+        team: TeamId,
+    ) -> impl Future<Output = Result<Vec<ProjectMember>, LogServiceError>> + Send;
+    /// create a project of the team, owned by one of its maintainers
+    fn new_team_project(
+        &self,
+        name: ProjectName,
+        owner: UserId,
+        team: TeamId,
+    ) -> impl Future<Output = Result<Project, LogServiceError>> + Send;
+    /// projects of the team, oldest first
+    fn team_projects(
+        &self,
+        reader: UserId,
+        team: TeamId,
+    ) -> impl Future<Output = Result<Vec<Project>, LogServiceError>> + Send;
+    /// projects the reader may read whose name is close to the searched
+    /// text, closest first
+    fn search_projects(
+        &self,
+        reader: UserId,
+        search: ProjectSearch,
+    ) -> impl Future<Output = Result<Vec<ProjectMatch>, LogServiceError>> + Send;
     fn projects_of_named(
         &self,
+        reader: UserId,
         user: Username,
         page: Page,
     ) -> impl Future<Output = Result<Paged<Project>, LogServiceError>> + Send;
-    fn list_users(&self, page: Page) -> impl Future<Output = Paged<User>> + Send;
+    fn list_users(
+        &self,
+        page: Page,
+    ) -> impl Future<Output = Result<Paged<User>, LogServiceError>> + Send;
+    /// changes made through the service meeting the query, newest first.
+    /// Callers decide who may read them
+    fn audit_log(
+        &self,
+        query: AuditQuery,
+    ) -> impl Future<Output = Result<Paged<AuditEntry>, LogServiceError>> + Send;
 }
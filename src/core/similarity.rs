@@ -0,0 +1,103 @@
+use std::collections::{HashMap, HashSet};
+
+/// Words too common to tell texts apart
+const STOP_WORDS: [&str; 32] = [
+    "about", "after", "again", "all", "and", "are", "but", "can", "for", "from", "had", "has",
+    "have", "not", "now", "off", "our", "out", "that", "the", "then", "there", "they", "this",
+    "too", "was", "were", "what", "when", "which", "will", "with",
+];
+
+/// Saturation of the term frequencies
+const K1: f64 = 1.2;
+/// Weight of the length of the texts
+const B: f64 = 0.75;
+/// Similarity below which texts only share a few common words
+const MIN_SIMILARITY: f64 = 0.1;
+
+/// Lowercased words of the text which can tell it apart from others
+pub(crate) fn terms(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() > 2)
+        .map(str::to_lowercase)
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+        .collect()
+}
+
+/// BM25 index of a set of texts, ranking them by similarity with one of
+/// them: the rarer the words they share, the more related they are
+pub(crate) struct Bm25 {
+    /// term frequencies and length of each text
+    texts: Vec<(HashMap<String, usize>, usize)>,
+    /// number of texts holding each term
+    frequencies: HashMap<String, usize>,
+    average_length: f64,
+}
+
+impl Bm25 {
+    pub(crate) fn new<'a>(texts: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut frequencies: HashMap<String, usize> = HashMap::new();
+        let texts: Vec<_> = texts
+            .into_iter()
+            .map(|text| {
+                let terms = terms(text);
+                let mut counts: HashMap<String, usize> = HashMap::new();
+                for term in &terms {
+                    *counts.entry(term.clone()).or_default() += 1;
+                }
+                for term in counts.keys() {
+                    *frequencies.entry(term.clone()).or_default() += 1;
+                }
+                (counts, terms.len())
+            })
+            .collect();
+        let total: usize = texts.iter().map(|(_, length)| length).sum();
+        let average_length = total as f64 / texts.len().max(1) as f64;
+        Self {
+            texts,
+            frequencies,
+            average_length,
+        }
+    }
+
+    fn idf(&self, term: &str) -> f64 {
+        let count = self.texts.len() as f64;
+        let frequency = self.frequencies.get(term).copied().unwrap_or(0) as f64;
+        (1.0 + (count - frequency + 0.5) / (frequency + 0.5)).ln()
+    }
+
+    /// Score of the text at `index` for the terms
+    fn score(&self, index: usize, terms: &HashSet<&String>) -> f64 {
+        let (counts, length) = &self.texts[index];
+        let norm = K1 * (1.0 - B + B * *length as f64 / self.average_length.max(1.0));
+        terms
+            .iter()
+            .filter_map(|term| {
+                let tf = *counts.get(*term)? as f64;
+                Some(self.idf(term) * tf * (K1 + 1.0) / (tf + norm))
+            })
+            .sum()
+    }
+
+    /// Similarity of the other texts related to the text at `index`, with
+    /// the terms they share. It is relative to the score of the text itself
+    /// so that it ranges from 0 to about 1.
+    pub(crate) fn similar(&self, index: usize) -> Vec<(usize, f64, Vec<String>)> {
+        let terms: HashSet<&String> = self.texts[index].0.keys().collect();
+        let itself = self.score(index, &terms);
+        if itself <= 0.0 {
+            return vec![];
+        }
+        (0..self.texts.len())
+            .filter(|other| *other != index)
+            .filter_map(|other| {
+                let score = self.score(other, &terms) / itself;
+                let shared = terms
+                    .iter()
+                    .filter(|term| self.texts[other].0.contains_key(**term))
+                    .map(|term| term.to_string())
+                    .collect();
+                (score >= MIN_SIMILARITY).then_some((other, score, shared))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,84 @@
+use std::str::FromStr;
+
+use derive_more::derive::{Display, Error};
+
+use super::model::{Date, Metadata, ModelError, Role, UserId};
+use crate::create_id;
+
+create_id!(TeamId);
+
+#[derive(Debug, Display, Error)]
+#[display("invalid team name {} (reason: {})", self.0, self.1)]
+pub struct InvalidTeamName(pub String, pub &'static str);
+impl From<(&str, &'static str)> for InvalidTeamName {
+    fn from((name, reason): (&str, &'static str)) -> Self {
+        Self(name.to_string(), reason)
+    }
+}
+#[derive(Debug, Clone, Display, PartialEq, Eq)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::Type))]
+#[cfg_attr(feature = "sqlx", sqlx(transparent))]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+pub struct TeamName(String);
+impl FromStr for TeamName {
+    type Err = InvalidTeamName;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chars_count = s.chars().count();
+        if chars_count < 3 {
+            Err((s, "too short").into())
+        } else if 32 < chars_count {
+            Err((s, "too long").into())
+        } else if s.contains('/') || s.chars().any(char::is_whitespace) {
+            Err((s, "contains whitespaces or slashes").into())
+        } else {
+            Ok(Self(s.to_string()))
+        }
+    }
+}
+
+/// Users owning projects together.
+///
+/// The members of a team get their role in the team on each of its
+/// projects, unless the project gives them another one. Its owner, who
+/// created it, maintains them.
+#[derive(Debug, Clone, Display)]
+#[display("Team #{id} - {name} (owned by {})", meta.author)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+pub struct Team {
+    id: TeamId,
+    #[sqlx(flatten)]
+    pub(crate) meta: Metadata,
+    pub(crate) name: TeamName,
+}
+
+impl Team {
+    pub fn new(name: TeamName, owner: UserId) -> Self {
+        Self {
+            id: TeamId::default(),
+            meta: Metadata::new(owner),
+            name,
+        }
+    }
+    pub fn id(&self) -> TeamId {
+        self.id
+    }
+    pub fn owner(&self) -> UserId {
+        self.meta.author
+    }
+    pub fn name(&self) -> &TeamName {
+        &self.name
+    }
+}
+
+/// A user belonging to a team, with the role they get on its projects
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+pub struct TeamMember {
+    pub team: TeamId,
+    pub user: UserId,
+    pub role: Role,
+    /// who added the user to the team
+    pub by: UserId,
+    pub created: Date,
+}
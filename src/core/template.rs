@@ -0,0 +1,218 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use derive_more::derive::{Display, Error};
+
+use super::model::{Metadata, ModelError, ProjectId, UserId};
+use crate::create_id;
+
+create_id!(TemplateId);
+
+/// Values of the fields of a log, by field name
+pub type LogFields = BTreeMap<String, String>;
+
+const OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+
+#[derive(Debug, Display, Error)]
+#[display("invalid template name {} (reason: {})", self.0, self.1)]
+pub struct InvalidTemplateName(pub String, pub &'static str);
+impl From<(&str, &'static str)> for InvalidTemplateName {
+    fn from((name, reason): (&str, &'static str)) -> Self {
+        Self(name.to_string(), reason)
+    }
+}
+#[derive(Debug, Clone, Display, PartialEq, Eq)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::Type))]
+#[cfg_attr(feature = "sqlx", sqlx(transparent))]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+pub struct TemplateName(String);
+impl FromStr for TemplateName {
+    type Err = InvalidTemplateName;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chars_count = s.chars().count();
+        if chars_count < 1 {
+            Err((s, "too short").into())
+        } else if 32 < chars_count {
+            Err((s, "too long").into())
+        } else if s.chars().any(char::is_whitespace) {
+            Err((s, "contains whitespaces").into())
+        } else {
+            Ok(Self(s.to_string()))
+        }
+    }
+}
+
+/// Text with `{{field}}` placeholders used to write recurring logs.
+///
+/// A template belongs either to its author (`project` is `None`) or to a
+/// project.
+#[derive(Debug, Clone, Display)]
+#[display("Template {name} #{id}")]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+pub struct Template {
+    id: TemplateId,
+    #[sqlx(flatten)]
+    pub(crate) meta: Metadata,
+    pub(crate) name: TemplateName,
+    pub(crate) project: Option<ProjectId>,
+    pub(crate) body: String,
+}
+
+enum Part<'a> {
+    Text(&'a str),
+    Field(&'a str),
+}
+
+pub(crate) fn is_field_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Splits a template body into literal text and placeholders. Braces which
+/// do not enclose a valid field name are kept as text.
+fn parts(body: &str) -> Vec<Part<'_>> {
+    let mut parts = vec![];
+    let mut rest = body;
+    let mut text_start = 0;
+    let mut offset = 0;
+    while let Some(open) = rest.find(OPEN) {
+        let after_open = &rest[open + OPEN.len()..];
+        let Some(close) = after_open.find(CLOSE) else {
+            break;
+        };
+        let name = after_open[..close].trim();
+        let consumed = open + OPEN.len() + close + CLOSE.len();
+        if is_field_name(name) {
+            if text_start < offset + open {
+                parts.push(Part::Text(&body[text_start..offset + open]));
+            }
+            parts.push(Part::Field(name));
+            text_start = offset + consumed;
+            offset += consumed;
+            rest = &rest[consumed..];
+        } else {
+            offset += open + OPEN.len();
+            rest = after_open;
+        }
+    }
+    if text_start < body.len() {
+        parts.push(Part::Text(&body[text_start..]));
+    }
+    parts
+}
+
+impl Template {
+    pub fn new(
+        name: TemplateName,
+        author: UserId,
+        project: Option<ProjectId>,
+        body: String,
+    ) -> Self {
+        Self {
+            id: TemplateId::default(),
+            meta: Metadata::new(author),
+            name,
+            project,
+            body,
+        }
+    }
+    pub fn id(&self) -> TemplateId {
+        self.id
+    }
+    pub fn name(&self) -> &TemplateName {
+        &self.name
+    }
+    pub fn project(&self) -> Option<ProjectId> {
+        self.project
+    }
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    /// Names of the placeholders, in order of first appearance
+    pub fn placeholders(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = vec![];
+        for part in parts(&self.body) {
+            if let Part::Field(name) = part {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    /// Placeholders without a value in `fields`
+    pub fn missing(&self, fields: &LogFields) -> Vec<String> {
+        self.placeholders()
+            .into_iter()
+            .filter(|name| !fields.contains_key(*name))
+            .map(String::from)
+            .collect()
+    }
+
+    /// Replaces the placeholders having a value in `fields`, leaving the
+    /// others untouched so they can be filled by hand
+    pub fn prefill(&self, fields: &LogFields) -> String {
+        parts(&self.body)
+            .into_iter()
+            .map(|part| match part {
+                Part::Text(text) => text.to_string(),
+                Part::Field(name) => fields
+                    .get(name)
+                    .cloned()
+                    .unwrap_or_else(|| format!("{OPEN}{name}{CLOSE}")),
+            })
+            .collect()
+    }
+
+    /// Reads back the field values from a text written from this template.
+    ///
+    /// Returns `None` when the literal parts of the template were modified.
+    /// Placeholders left unfilled are not part of the result.
+    pub fn extract(&self, text: &str) -> Option<LogFields> {
+        let text = text.trim_end();
+        let mut fields = LogFields::new();
+        let mut position = 0;
+        let mut pending: Option<&str> = None;
+        let mut insert = |name: &str, value: &str| {
+            let value = value.trim();
+            let unfilled = value
+                .strip_prefix(OPEN)
+                .and_then(|v| v.strip_suffix(CLOSE))
+                .is_some_and(|v| v.trim() == name);
+            if !unfilled && !value.is_empty() {
+                fields.entry(name.to_string()).or_insert(value.to_string());
+            }
+        };
+        for part in parts(self.body.trim_end()) {
+            match part {
+                Part::Field(name) => {
+                    // adjacent placeholders can not be told apart: the first
+                    // one gets the whole value
+                    pending.get_or_insert(name);
+                }
+                Part::Text(literal) => {
+                    if let Some(name) = pending.take() {
+                        let found = text[position..].find(literal)?;
+                        insert(name, &text[position..position + found]);
+                        position += found + literal.len();
+                    } else if text[position..].starts_with(literal) {
+                        position += literal.len();
+                    } else {
+                        return None;
+                    }
+                }
+            }
+        }
+        match pending {
+            Some(name) => insert(name, &text[position..]),
+            None if position < text.len() => return None,
+            None => {}
+        }
+        Some(fields)
+    }
+}
@@ -0,0 +1,106 @@
+use std::str::FromStr;
+
+use derive_more::derive::{Display, Error};
+
+use super::{
+    model::{Metadata, ModelError, ProjectId, UserId},
+    query::LogSearch,
+    search::QuerySyntaxError,
+};
+use crate::create_id;
+
+create_id!(ViewId);
+
+#[derive(Debug, Display, Error)]
+#[display("invalid view name {} (reason: {})", self.0, self.1)]
+pub struct InvalidViewName(pub String, pub &'static str);
+impl From<(&str, &'static str)> for InvalidViewName {
+    fn from((name, reason): (&str, &'static str)) -> Self {
+        Self(name.to_string(), reason)
+    }
+}
+#[derive(Debug, Clone, Display, PartialEq, Eq)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::Type))]
+#[cfg_attr(feature = "sqlx", sqlx(transparent))]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+pub struct ViewName(String);
+impl FromStr for ViewName {
+    type Err = InvalidViewName;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chars_count = s.chars().count();
+        if chars_count < 1 {
+            Err((s, "too short").into())
+        } else if 32 < chars_count {
+            Err((s, "too long").into())
+        } else if s.contains('/') || s.chars().any(char::is_whitespace) {
+            Err((s, "contains whitespaces or slashes").into())
+        } else {
+            Ok(Self(s.to_string()))
+        }
+    }
+}
+
+/// Search saved under a name to be run again, such as `blockers` for
+/// `author:me kind:blocker -status:done`.
+///
+/// A view belongs to its author, who can share it with the members of a
+/// project. Whoever runs it searches the projects they can read, `me`
+/// standing for them.
+#[derive(Debug, Clone, Display)]
+#[display("View {name}: {query}")]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "dtos", derive(serde::Serialize, serde::Deserialize))]
+pub struct View {
+    id: ViewId,
+    #[sqlx(flatten)]
+    pub(crate) meta: Metadata,
+    pub(crate) name: ViewName,
+    /// search in the query language of [`crate::SearchQuery`]
+    pub(crate) query: String,
+    /// project whose members see the view, `None` for a personal view
+    pub(crate) project: Option<ProjectId>,
+}
+
+impl View {
+    pub fn new(name: ViewName, author: UserId, query: String, project: Option<ProjectId>) -> Self {
+        Self {
+            id: ViewId::default(),
+            meta: Metadata::new(author),
+            name,
+            query,
+            project,
+        }
+    }
+    /// The view saved again with another query and sharing, keeping its id
+    pub(crate) fn updated(self, query: String, project: Option<ProjectId>) -> Self {
+        let meta = Metadata {
+            version: self.meta.version.next(),
+            ..self.meta
+        };
+        Self {
+            meta,
+            query,
+            project,
+            ..self
+        }
+    }
+    pub fn id(&self) -> ViewId {
+        self.id
+    }
+    pub fn author(&self) -> UserId {
+        self.meta.author
+    }
+    pub fn name(&self) -> &ViewName {
+        &self.name
+    }
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+    pub fn project(&self) -> Option<ProjectId> {
+        self.project
+    }
+    /// The first page of the search of the view
+    pub fn search(&self) -> Result<LogSearch, QuerySyntaxError> {
+        LogSearch::parse(&self.query)
+    }
+}
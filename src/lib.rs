@@ -3,15 +3,39 @@ mod core;
 // make pagination public
 pub use adapters::Config;
 pub use adapters::Database;
+pub use adapters::InMemoryRepo;
+#[cfg(feature = "journal")]
+pub use adapters::JournalRepo;
+#[cfg(feature = "postgres")]
+pub use adapters::PostgresRepo;
+pub use adapters::SchemaStatus;
 pub use adapters::SqliteRepo;
+pub use adapters::{Rendered, RenderedHit, TextFormat};
+pub use core::audit::{AuditEntry, AuditQuery, Origin};
+pub use core::event::Event;
+pub use core::field::{FieldDefinition, FieldFilter, FieldKind};
+pub use core::model::Date;
+pub use core::model::EntryId;
+pub use core::model::Log;
+pub use core::model::Member;
 pub use core::model::Project;
 pub use core::model::ProjectId;
+pub use core::model::ProjectLogs;
+pub use core::model::ProjectMember;
 pub use core::model::ProjectName;
+pub use core::model::Role;
 pub use core::model::UserId;
 pub use core::model::Username;
-pub use core::pagination::{Page, Paged, Paginable};
-pub use core::repo::{CreateAuthorRequest, CreateLogRequest, CreateProjectRequest};
-pub use core::service::{LocalLogStoreService, LogService};
+pub use core::model::MAX_PINNED_LOGS;
+pub use core::naming::{NamePolicy, Naming, NamingAlreadyInstalled};
+pub use core::pagination::{Cursor, Keyed, Page, Paged, Paginable, SortOrder};
+pub use core::query::{LogHit, LogQuery, LogSearch, ProjectActivity, ProjectMatch, ProjectSearch};
+pub use core::repo::{CreateAuthorRequest, CreateLogRequest, CreateProjectRequest, UnitOfWork};
+pub use core::search::{Criterion, QuerySyntaxError, SearchQuery, Term};
+pub use core::service::{LocalLogStoreService, LogService, LogServiceError};
+pub use core::team::{Team, TeamId, TeamMember, TeamName};
+pub use core::template::{LogFields, Template, TemplateName};
+pub use core::view::{View, ViewName};
 
 // #[derive(Debug)]
 // pub struct ContentId(u64);
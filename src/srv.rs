@@ -1,38 +1,93 @@
 use axum::{
-    extract::Query,
-    http::StatusCode,
-    routing::{get, post},
+    async_trait,
+    body::Body,
+    extract::{FromRequestParts, Path, Query, State},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post, put},
     Json, Router,
 };
-use axum_macros::debug_handler;
-use reverie::{LocalLogStoreService, LogService, Page, Paged, ProjectId, SqliteRepo, UserId};
-use serde::Deserialize;
-use std::{
-    net::SocketAddr,
-    sync::{Arc, Mutex},
-    time::Duration,
+use futures_util::{stream, StreamExt, TryStreamExt};
+use itertools::Itertools;
+#[cfg(feature = "journal")]
+use reverie::JournalRepo;
+#[cfg(feature = "postgres")]
+use reverie::PostgresRepo;
+use reverie::{
+    AuditQuery, Config, Cursor, Database, Date, EntryId, FieldFilter, InMemoryRepo,
+    LocalLogStoreService, Log, LogFields, LogQuery, LogSearch, LogService, LogServiceError, Origin,
+    Page, ProjectId, ProjectLogs, ProjectName, Role, SortOrder, SqliteRepo, TeamId, TeamName,
+    TemplateName, TextFormat, UserId, Username, ViewName,
 };
+use serde::Deserialize;
+use std::{net::SocketAddr, str::FromStr, time::Duration};
 use tokio::signal;
 use tracing::info;
-#[debug_handler]
-async fn add_log(store: axum::extract::State<AppContext>, Json(log): Json) -> StatusCode {
-    store.add_log(log);
-    StatusCode::CREATED
-}
+
+/// A log is either written as `text` or filled from a `template` with the
+/// values of its `fields`, by the user of the `x-user-id` header
 #[derive(Deserialize, Debug)]
-struct Pagination {
-    page: usize,
-    size: usize,
+struct NewLog {
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    template: Option<String>,
+    #[serde(default)]
+    fields: LogFields,
 }
-impl From<Pagination> for Page {
-    fn from(Pagination { page, size }: Pagination) -> Self {
-        Self::new(page, size)
+async fn add_log<S: AppService>(
+    app: State<AppContext<S>>,
+    Path(project): Path<String>,
+    Acting(author): Acting,
+    Json(log): Json<NewLog>,
+) -> Response {
+    let project = match app.project_id(author, &project).await {
+        Ok(id) => id,
+        Err(e) => return error_response(e),
+    };
+    info!("add {log:?}");
+    let NewLog {
+        text,
+        template,
+        fields,
+    } = log;
+    let text = match (text, template) {
+        (Some(text), None) => text,
+        (None, Some(template)) => {
+            let template = match TemplateName::from_str(&template) {
+                Ok(name) => app.service.template(name, author, Some(project)).await,
+                Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+            };
+            let template = match template {
+                Ok(template) => template,
+                Err(e) => return error_response(e),
+            };
+            let missing = template.missing(&fields);
+            if !missing.is_empty() {
+                let message = format!("missing fields: {}", missing.join(", "));
+                return (StatusCode::BAD_REQUEST, message).into_response();
+            }
+            template.prefill(&fields)
+        }
+        (_, _) => {
+            let message = "either text or template is required";
+            return (StatusCode::BAD_REQUEST, message).into_response();
+        }
+    };
+    match app.service.add_log(author, project, text, fields).await {
+        Ok(log) => (StatusCode::CREATED, Json(log)).into_response(),
+        Err(e) => error_response(e),
     }
 }
-impl Default for Pagination {
-    fn default() -> Self {
-        Self { page: 1, size: 10 }
-    }
+/// `?page=&size=`, or `?after=&size=` and `?before=&size=` to page from the
+/// `next` and `prev` cursors of a previous page. All optional.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct Pagination {
+    page: Option<usize>,
+    size: Option<usize>,
+    after: Option<Cursor>,
+    before: Option<Cursor>,
 }
 /// Should fetch project id from (projcetname, userid)
 /// then fetch log
@@ -45,56 +100,817 @@ impl Default for Pagination {
 /// - api/project/<id>/blockers
 /// - api/project/<id>/blockers
 /// - api/project/<id>/update -> returns last change time and version
+///
 /// the headers contain the user id (jwt?)
-#[debug_handler]
-async fn project_logs(
-    app: axum::extract::State<AppContext>,
-    axum::extract::Path(project): axum::extract::Path<String>,
-    pagination: Option<Query<Pagination>>,
-) -> (StatusCode, Json<Paged<String>>) {
-    let Query(page) = pagination.unwrap_or_default();
-    (StatusCode::OK, app.fetch_log(project, &page.into()).into())
+///
+/// Responds with an HTML page (log texts rendered from markdown) when the
+/// client accepts `text/html`, with JSON otherwise
+///
+/// Logs can be filtered on their fields with `field` parameters such as
+/// `?field=env=prod&field=severity>=2`, on their author with `by=` (a user
+/// id or name, `me` for the user of the `x-user-id` header) and on their
+/// creation day with `since=` and `until=`. `sort=desc` lists the newest
+/// first.
+///
+/// Only the users who can read the project, given by the `x-user-id`
+/// header, get its logs: others get a 403.
+async fn project_logs<S: AppService>(
+    app: State<AppContext<S>>,
+    Path(project): Path<String>,
+    Acting(reader): Acting,
+    headers: HeaderMap,
+    Query(pagination): Query<Pagination>,
+    Query(params): Query<Vec<(String, String)>>,
+) -> Response {
+    let page = app.page(pagination);
+    let project_id = match app.project_id(reader, &project).await {
+        Ok(id) => id,
+        Err(e) => return error_response(e),
+    };
+    info!("get {project_id:?}");
+    let mut query = LogQuery::project(project_id).page(page);
+    for (key, value) in params {
+        query = match key.as_str() {
+            "field" => match FieldFilter::from_str(&value) {
+                Ok(filter) => query.field(filter),
+                Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+            },
+            "by" => match app.user_id(&value, &headers).await {
+                Ok(author) => query.by(author),
+                Err(response) => return response,
+            },
+            "since" => match Date::from_str(&value) {
+                Ok(date) => query.since(date),
+                Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+            },
+            "until" => match Date::from_str(&value) {
+                Ok(date) => query.until(date),
+                Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+            },
+            "sort" => match SortOrder::from_str(&value) {
+                Ok(sort) => query.sort(sort),
+                Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+            },
+            _ => query,
+        };
+    }
+    match app.service.project_logs(reader, query).await {
+        Ok(logs) if accepts_html(&headers) => Html(logs_page(&project, logs)).into_response(),
+        Ok(logs) => (StatusCode::OK, Json(logs)).into_response(),
+        Err(e) => error_response(e),
+    }
 }
 
-#[derive(Clone)]
-struct AppContext {
-    service: Arc<Mutex<LogService<SqliteRepo>>>,
+/// Custom fields declared by the project, for its readers
+async fn project_fields<S: AppService>(
+    app: State<AppContext<S>>,
+    Path(project): Path<String>,
+    Acting(reader): Acting,
+) -> Response {
+    let project_id = match app.project_id(reader, &project).await {
+        Ok(id) => id,
+        Err(e) => return error_response(e),
+    };
+    match app.service.fields(reader, project_id).await {
+        Ok(fields) => (StatusCode::OK, Json(fields)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Owner and members of the project with their roles, for its readers
+async fn project_members<S: AppService>(
+    app: State<AppContext<S>>,
+    Path(project): Path<String>,
+    Acting(reader): Acting,
+) -> Response {
+    let project_id = match app.project_id(reader, &project).await {
+        Ok(id) => id,
+        Err(e) => return error_response(e),
+    };
+    match app.service.members(reader, project_id).await {
+        Ok(members) => (StatusCode::OK, Json(members)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// The `user` (an id or a name) to add to the members of a project, with
+/// their `role`: `reader`, `writer` (the default) or `maintainer`
+#[derive(Deserialize, Debug)]
+struct NewMember {
+    user: String,
+    #[serde(default)]
+    role: Role,
+}
+/// Adds a member to the project maintained by the user of the `x-user-id`
+/// header
+async fn add_member<S: AppService>(
+    app: State<AppContext<S>>,
+    Path(project): Path<String>,
+    Acting(by): Acting,
+    headers: HeaderMap,
+    Json(NewMember { user, role }): Json<NewMember>,
+) -> Response {
+    let user = match app.user_id(&user, &headers).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let project_id = match app.project_id(by, &project).await {
+        Ok(id) => id,
+        Err(e) => return error_response(e),
+    };
+    match app.service.add_member(by, project_id, user, role).await {
+        Ok(member) => (StatusCode::CREATED, Json(member)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ChangeRole {
+    role: Role,
+}
+/// Changes the role of a member (an id or a name) of the project maintained
+/// by the user of the `x-user-id` header
+async fn change_role<S: AppService>(
+    app: State<AppContext<S>>,
+    Path((project, member)): Path<(String, String)>,
+    Acting(by): Acting,
+    headers: HeaderMap,
+    Json(ChangeRole { role }): Json<ChangeRole>,
+) -> Response {
+    let member = match app.user_id(&member, &headers).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let project_id = match app.project_id(by, &project).await {
+        Ok(id) => id,
+        Err(e) => return error_response(e),
+    };
+    match app.service.set_role(by, project_id, member, role).await {
+        Ok(member) => (StatusCode::OK, Json(member)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Removes a member (an id or a name, `me` to leave) from the project
+async fn remove_member<S: AppService>(
+    app: State<AppContext<S>>,
+    Path((project, member)): Path<(String, String)>,
+    Acting(by): Acting,
+    headers: HeaderMap,
+) -> Response {
+    let member = match app.user_id(&member, &headers).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let project_id = match app.project_id(by, &project).await {
+        Ok(id) => id,
+        Err(e) => return error_response(e),
+    };
+    match app.service.remove_member(by, project_id, member).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// The new `owner` (an id or a name) of a project
+#[derive(Deserialize, Debug)]
+struct Transfer {
+    owner: String,
+}
+/// Hands the project of the user of the `x-user-id` header over to another
+/// owner, the user staying a maintainer
+async fn transfer_project<S: AppService>(
+    app: State<AppContext<S>>,
+    Path(project): Path<String>,
+    Acting(by): Acting,
+    headers: HeaderMap,
+    Json(Transfer { owner }): Json<Transfer>,
+) -> Response {
+    let owner = match app.user_id(&owner, &headers).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let project_id = match app.project_id(by, &project).await {
+        Ok(id) => id,
+        Err(e) => return error_response(e),
+    };
+    match app.service.transfer_project(by, project_id, owner).await {
+        Ok(project) => (StatusCode::OK, Json(project)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Teams the user of the `x-user-id` header owns or belongs to
+async fn list_teams<S: AppService>(app: State<AppContext<S>>, Acting(user): Acting) -> Response {
+    match app.service.teams_of(user).await {
+        Ok(teams) => Json(teams).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Owner and members of the team with their roles, for its members
+async fn team_members<S: AppService>(
+    app: State<AppContext<S>>,
+    Path(team): Path<String>,
+    Acting(reader): Acting,
+) -> Response {
+    let team_id = match app.team_id(&team).await {
+        Ok(id) => id,
+        Err(e) => return error_response(e),
+    };
+    match app.service.team_members(reader, team_id).await {
+        Ok(members) => Json(members).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Adds a member to the team maintained by the user of the `x-user-id`
+/// header, with the role they get on its projects
+async fn add_team_member<S: AppService>(
+    app: State<AppContext<S>>,
+    Path(team): Path<String>,
+    Acting(by): Acting,
+    headers: HeaderMap,
+    Json(NewMember { user, role }): Json<NewMember>,
+) -> Response {
+    let user = match app.user_id(&user, &headers).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let team_id = match app.team_id(&team).await {
+        Ok(id) => id,
+        Err(e) => return error_response(e),
+    };
+    match app.service.add_team_member(by, team_id, user, role).await {
+        Ok(member) => (StatusCode::CREATED, Json(member)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Projects of the team, for its members
+async fn team_projects<S: AppService>(
+    app: State<AppContext<S>>,
+    Path(team): Path<String>,
+    Acting(reader): Acting,
+) -> Response {
+    let team_id = match app.team_id(&team).await {
+        Ok(id) => id,
+        Err(e) => return error_response(e),
+    };
+    match app.service.team_projects(reader, team_id).await {
+        Ok(projects) => Json(projects).into_response(),
+        Err(e) => error_response(e),
+    }
 }
-impl From<LogService<SqliteRepo>> for AppContext {
-    fn from(service: LogService<SqliteRepo>) -> Self {
-        Self {
-            service: Arc::new(Mutex::new(service)),
+
+/// `?q=` search in the query language of [`reverie::SearchQuery`], `since=`
+/// and `until=` days as YYYY-MM-DD, like the listings of a project
+#[derive(Deserialize, Debug)]
+struct SearchParams {
+    q: String,
+    #[serde(default)]
+    since: Option<String>,
+    #[serde(default)]
+    until: Option<String>,
+}
+/// Logs found by the search `q` in the projects of the user of the
+/// `x-user-id` header, best matches first, with snippets in which the
+/// matches are in `**bold**`. Pages are numbered. A query which can not be
+/// parsed is a bad request pointing at the faulty term.
+async fn search_logs<S: AppService>(
+    app: State<AppContext<S>>,
+    Acting(reader): Acting,
+    Query(pagination): Query<Pagination>,
+    Query(params): Query<SearchParams>,
+) -> Response {
+    let date = |date: Option<String>| date.map(|date| Date::from_str(&date)).transpose();
+    let (since, until) = match (date(params.since), date(params.until)) {
+        (Ok(since), Ok(until)) => (since, until),
+        (Err(e), _) | (_, Err(e)) => {
+            return (StatusCode::BAD_REQUEST, e.to_string()).into_response()
         }
+    };
+    let mut search = match LogSearch::parse(&params.q) {
+        Ok(search) => search.page(app.page(pagination)),
+        Err(e) => return error_response(e.into()),
+    };
+    if let Some(since) = since {
+        search = search.since(since);
+    }
+    if let Some(until) = until {
+        search = search.until(until);
+    }
+    match app.service.search_logs(reader, search).await {
+        Ok(hits) => Json(hits).into_response(),
+        Err(e) => error_response(e),
     }
 }
-impl AppContext {
-    fn add_log(&self, log: String, user: UserId, project: ProjectId) {
-        info!("add {log:?}");
-        self.service.lock().unwrap().add_log(user, project, log);
+
+/// `?limit=` number of related logs, 5 by default
+#[derive(Deserialize, Debug)]
+struct RelatedParams {
+    #[serde(default = "default_related")]
+    limit: usize,
+}
+fn default_related() -> usize {
+    5
+}
+/// Logs similar to the log in the projects the user of the `x-user-id`
+/// header can read, most similar first. Responds like `/search/logs` with
+/// a `score` from 0 to about 1 and the shared words in `**bold**`.
+async fn related_logs<S: AppService>(
+    app: State<AppContext<S>>,
+    Path(log): Path<EntryId>,
+    Acting(reader): Acting,
+    Query(RelatedParams { limit }): Query<RelatedParams>,
+) -> Response {
+    let limit = limit.min(app.max_page_size);
+    match app.service.related_logs(reader, log, limit).await {
+        Ok(related) => Json(related).into_response(),
+        Err(e) => error_response(e),
     }
-    fn fetch_log(&self, project: ProjectId, page: &Page) -> Paged<String> {
-        info!("get {project:?}");
-        self.service.lock().unwrap().get(project, page)
+}
+
+/// Views of the user of the `x-user-id` header, followed by the views
+/// shared with the projects they can read
+async fn list_views<S: AppService>(app: State<AppContext<S>>, Acting(user): Acting) -> Response {
+    match app.service.views(user).await {
+        Ok(views) => Json(views).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Hits of the view run by the user of the `x-user-id` header, as
+/// `/search/logs` responds. Pages are numbered.
+async fn run_view<S: AppService>(
+    app: State<AppContext<S>>,
+    Path(name): Path<String>,
+    Acting(user): Acting,
+    Query(pagination): Query<Pagination>,
+) -> Response {
+    let Ok(name) = ViewName::from_str(&name) else {
+        return error_response(LogServiceError::ViewNotFound);
+    };
+    let view = match app.service.view(name, user).await {
+        Ok(view) => view,
+        Err(e) => return error_response(e),
+    };
+    let search = match view.search() {
+        Ok(search) => search.page(app.page(pagination)),
+        Err(e) => return error_response(e.into()),
+    };
+    match app.service.search_logs(user, search).await {
+        Ok(hits) => Json(hits).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// A view is saved with its `query`, and shared with the members of
+/// `project` (an id or a name) if any
+#[derive(Deserialize, Debug)]
+struct SaveView {
+    query: String,
+    #[serde(default)]
+    project: Option<String>,
+}
+/// Saves the view of the user of the `x-user-id` header, replacing their
+/// view with the same name
+async fn save_view<S: AppService>(
+    app: State<AppContext<S>>,
+    Path(name): Path<String>,
+    Acting(author): Acting,
+    Json(SaveView { query, project }): Json<SaveView>,
+) -> Response {
+    let name = match ViewName::from_str(&name) {
+        Ok(name) => name,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    let project = match project {
+        Some(project) => match app.project_id(author, &project).await {
+            Ok(id) => Some(id),
+            Err(e) => return error_response(e),
+        },
+        None => None,
+    };
+    match app.service.save_view(author, name, query, project).await {
+        Ok(view) => (StatusCode::OK, Json(view)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Every log of the project, sent as they are read from the database to the
+/// readers of the project.
+///
+/// Responds with one JSON log per line (`application/x-ndjson`), or with a
+/// single JSON array when the client accepts `application/json`
+async fn export_logs<S: AppService>(
+    app: State<AppContext<S>>,
+    Path(project): Path<String>,
+    Acting(reader): Acting,
+    headers: HeaderMap,
+) -> Response {
+    let project_id = match app.project_id(reader, &project).await {
+        Ok(id) => id,
+        Err(e) => return error_response(e),
+    };
+    let logs = match app.service.stream_logs(reader, project_id).await {
+        Ok(logs) => logs,
+        Err(e) => return error_response(e),
+    };
+    let logs = logs.map(|log| {
+        log.map_err(|e| std::io::Error::other(e.to_string()))
+            .and_then(|log| serde_json::to_vec(&log).map_err(std::io::Error::other))
+    });
+    if accepts(&headers, "application/json") {
+        let mut first = true;
+        let items = logs.map_ok(move |log| {
+            let mut chunk = if std::mem::take(&mut first) {
+                vec![]
+            } else {
+                b",".to_vec()
+            };
+            chunk.extend(log);
+            chunk
+        });
+        let body = stream::once(async { Ok(b"[".to_vec()) })
+            .chain(items)
+            .chain(stream::once(async { Ok(b"]".to_vec()) }));
+        (
+            [(header::CONTENT_TYPE, "application/json")],
+            Body::from_stream(body),
+        )
+            .into_response()
+    } else {
+        let lines = logs.map_ok(|mut log| {
+            log.push(b'\n');
+            log
+        });
+        (
+            [(header::CONTENT_TYPE, "application/x-ndjson")],
+            Body::from_stream(lines),
+        )
+            .into_response()
+    }
+}
+
+/// Responds with the status matching the kind of failure: the client's
+/// mistake, a missing entry, or a storage failure worth retrying or not
+fn error_response(error: LogServiceError) -> Response {
+    use LogServiceError::*;
+    let status = match &error {
+        ProjectNotFound | UserNotFound | LogNotFound | TemplateNotFound | ViewNotFound
+        | MemberNotFound | TeamNotFound | NotFound(_) => StatusCode::NOT_FOUND,
+        ProjectExists | UserExists | TemplateExists | TeamExists | Conflict(_) => {
+            StatusCode::CONFLICT
+        }
+        LogNotPinned
+        | TooManyPinnedLogs(_)
+        | InvalidField(_)
+        | InvalidUsername(_)
+        | InvalidProjectName(_)
+        | InvalidSearch(_)
+        | InvalidRole(_) => StatusCode::BAD_REQUEST,
+        MissingReference(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        NoReadAccess(..) | NoWriteAccess(..) | NoTeamAccess(..) => StatusCode::FORBIDDEN,
+        Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+        Corrupt(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, error.to_string()).into_response()
+}
+
+fn accepts_html(headers: &HeaderMap) -> bool {
+    accepts(headers, "text/html")
+}
+
+fn accepts(headers: &HeaderMap, media_type: &str) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|media| media.split(';').next().unwrap_or_default().trim() == media_type)
+}
+
+/// Pins the log for the user of the `x-user-id` header
+async fn pin_log<S: AppService>(
+    app: State<AppContext<S>>,
+    Path((project, log)): Path<(String, EntryId)>,
+    Acting(by): Acting,
+) -> Response {
+    let project = match app.project_id(by, &project).await {
+        Ok(id) => id,
+        Err(e) => return error_response(e),
+    };
+    match app.service.pin(by, project, log).await {
+        Ok(log) => (StatusCode::OK, Json(log)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+/// Unpins the log for the user of the `x-user-id` header
+async fn unpin_log<S: AppService>(
+    app: State<AppContext<S>>,
+    Path((project, log)): Path<(String, EntryId)>,
+    Acting(by): Acting,
+) -> Response {
+    let project = match app.project_id(by, &project).await {
+        Ok(id) => id,
+        Err(e) => return error_response(e),
+    };
+    match app.service.unpin(by, project, log).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+fn log_article(log: Log, pinned: bool) -> String {
+    let fields = log
+        .fields()
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "<dt>{}</dt><dd>{}</dd>",
+                ammonia::clean_text(name),
+                ammonia::clean_text(value)
+            )
+        })
+        .join("");
+    let fields = if fields.is_empty() {
+        fields
+    } else {
+        format!("<dl>{fields}</dl>\n")
+    };
+    let log = log.rendered(TextFormat::Html);
+    format!(
+        "<article id=\"{}\"{}><header>Log #{} by user {}</header>\n{fields}{}</article>",
+        log.log().id(),
+        if pinned { " class=\"pinned\"" } else { "" },
+        log.log().id(),
+        log.log().author(),
+        log.text()
+    )
+}
+
+fn logs_page(project: &str, logs: ProjectLogs) -> String {
+    let project = ammonia::clean_text(project);
+    let pinned = logs
+        .pinned
+        .into_iter()
+        .map(|log| log_article(log, true))
+        .join("\n");
+    let pinned = if pinned.is_empty() {
+        pinned
+    } else {
+        format!("<section class=\"pinned\"><h2>Pinned</h2>\n{pinned}\n</section>")
+    };
+    let (page_size, total) = (logs.logs.page_size, logs.logs.total);
+    let links = [
+        logs.logs
+            .prev
+            .map(|prev| format!("<a href=\"?before={prev}&size={page_size}\">Previous</a>")),
+        logs.logs
+            .next
+            .map(|next| format!("<a href=\"?after={next}&size={page_size}\">Next</a>")),
+    ];
+    let nav = format!(
+        "<nav>{total} logs {}</nav>",
+        links.iter().flatten().join(" ")
+    );
+    let entries = logs
+        .logs
+        .data
+        .into_iter()
+        .map(|log| log_article(log, false))
+        .join("\n");
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{project} - logs</title></head>\n<body><h1>{project}</h1>\n{pinned}\n{nav}\n{entries}\n</body></html>"
+    )
+}
+
+/// Optional `?actor=`, `action=` and `target=` filters of the audit trail
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct AuditParams {
+    actor: Option<String>,
+    action: Option<String>,
+    target: Option<String>,
+}
+/// Changes recorded in the audit trail, newest first, for the admins of the
+/// configuration only. Pages are numbered.
+async fn audit_log<S: AppService>(
+    app: State<AppContext<S>>,
+    Acting(reader): Acting,
+    headers: HeaderMap,
+    Query(pagination): Query<Pagination>,
+    Query(params): Query<AuditParams>,
+) -> Response {
+    match app.is_admin(reader).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::FORBIDDEN, "admins only").into_response(),
+        Err(e) => return error_response(e),
+    }
+    let mut query = AuditQuery::default().page(app.page(pagination));
+    if let Some(actor) = params.actor {
+        match app.user_id(&actor, &headers).await {
+            Ok(id) => query = query.actor(id),
+            Err(response) => return response,
+        }
+    }
+    if let Some(action) = params.action {
+        query = query.action(action);
+    }
+    if let Some(target) = params.target {
+        query = query.target(target);
+    }
+    match app.service.audit_log(query).await {
+        Ok(entries) => Json(entries).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Service the handlers are generic over, whatever the database behind it
+trait AppService: LocalLogStoreService + Clone + Send + Sync + 'static {}
+impl<S> AppService for S where S: LocalLogStoreService + Clone + Send + Sync + 'static {}
+
+#[derive(Clone)]
+struct AppContext<S> {
+    service: S,
+    page_size: usize,
+    max_page_size: usize,
+    /// users reading the audit trail
+    admins: Vec<Username>,
+}
+impl<S: AppService> AppContext<S> {
+    /// Fails on admins which are not valid user names, or reserved words
+    /// naming nobody
+    fn new(service: S, config: &Config) -> anyhow::Result<Self> {
+        let mut admins = vec![];
+        for admin in &config.admins {
+            let admin = Username::from_str(admin)?;
+            if admin.is_reserved() {
+                anyhow::bail!("admin {admin} is a reserved word, which names no user");
+            }
+            admins.push(admin);
+        }
+        Ok(Self {
+            service,
+            page_size: config.page_size,
+            max_page_size: config.max_page_size,
+            admins,
+        })
+    }
+    /// Whether the user is one of the configured admins
+    async fn is_admin(&self, user: UserId) -> Result<bool, LogServiceError> {
+        for admin in &self.admins {
+            let admin = self.service.get_user(admin.clone()).await?;
+            if admin.is_some_and(|admin| admin.id() == user) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+    /// The requested page, no larger than the configured maximum
+    fn page(&self, pagination: Pagination) -> Page {
+        let size = pagination.size.unwrap_or(self.page_size).max(1);
+        let page = match (pagination.after, pagination.before) {
+            (Some(after), _) => Page::after(after, size),
+            (_, Some(before)) => Page::before(before, size),
+            (None, None) => Page::new(pagination.page.unwrap_or(1).max(1), size),
+        };
+        page.limited(self.max_page_size)
+    }
+    /// Resolves a user id or name, `me` standing for the user of the
+    /// `x-user-id` header
+    async fn user_id(&self, user: &str, headers: &HeaderMap) -> Result<UserId, Response> {
+        let user = if user == "me" {
+            let Some(me) = headers.get("x-user-id").and_then(|h| h.to_str().ok()) else {
+                let message = "`me` stands for the user of the x-user-id header, which is missing";
+                return Err((StatusCode::UNAUTHORIZED, message).into_response());
+            };
+            me
+        } else {
+            user
+        };
+        if let Ok(id) = UserId::from_str(user) {
+            return Ok(id);
+        }
+        let name = Username::from_str(user)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()).into_response())?;
+        match self.service.get_user(name).await {
+            Ok(Some(user)) => Ok(user.id()),
+            Ok(None) => Err(error_response(LogServiceError::UserNotFound)),
+            Err(e) => Err(error_response(e)),
+        }
+    }
+    /// Resolves a path segment holding either a project id or the name of a
+    /// project the reader may read
+    async fn project_id(
+        &self,
+        reader: UserId,
+        project: &str,
+    ) -> Result<ProjectId, LogServiceError> {
+        if let Ok(id) = ProjectId::from_str(project) {
+            return Ok(id);
+        }
+        let name = ProjectName::from_str(project).map_err(|_| LogServiceError::ProjectNotFound)?;
+        self.service
+            .get_project(reader, name)
+            .await?
+            .map(|p| p.id())
+            .ok_or(LogServiceError::ProjectNotFound)
+    }
+    /// Resolves a path segment holding a team name
+    async fn team_id(&self, team: &str) -> Result<TeamId, LogServiceError> {
+        let name = TeamName::from_str(team).map_err(|_| LogServiceError::TeamNotFound)?;
+        self.service
+            .get_team(name)
+            .await?
+            .map(|t| t.id())
+            .ok_or(LogServiceError::TeamNotFound)
+    }
+}
+
+/// User of the `x-user-id` header acting on the request, answering 401 when
+/// the header is missing
+struct Acting(UserId);
+#[async_trait]
+impl<S: AppService> FromRequestParts<AppContext<S>> for Acting {
+    type Rejection = Response;
+    async fn from_request_parts(
+        parts: &mut Parts,
+        app: &AppContext<S>,
+    ) -> Result<Self, Self::Rejection> {
+        app.user_id("me", &parts.headers).await.map(Acting)
     }
 }
 
 #[tokio::main]
-async fn main() {
+async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt()
         .with_max_level(tracing::Level::TRACE)
         .init();
 
+    let mut config = Config::from_env();
+    config.naming.clone().install()?;
+    let database = match std::mem::replace(&mut config.database, Database::None) {
+        Database::None => Database::default(),
+        database => database,
+    };
+    match database {
+        Database::Sqlite(path) => {
+            let repo = SqliteRepo::new(&path.to_string_lossy()).await?;
+            serve(LogService::new(repo).with_origin(Origin::Http), &config).await
+        }
+        #[cfg(feature = "postgres")]
+        Database::Postgres(url) => {
+            let repo = PostgresRepo::new(&url).await?;
+            serve(LogService::new(repo).with_origin(Origin::Http), &config).await
+        }
+        #[cfg(feature = "journal")]
+        Database::Journal(path) => {
+            let repo = JournalRepo::open(&path)?;
+            serve(LogService::new(repo).with_origin(Origin::Http), &config).await
+        }
+        Database::Memory => {
+            let repo = InMemoryRepo::new();
+            serve(LogService::new(repo).with_origin(Origin::Http), &config).await
+        }
+        Database::None => unreachable!(),
+    }
+}
+
+async fn serve<S: AppService>(service: S, config: &Config) -> anyhow::Result<()> {
     let s0o_bind_ip: String = std::env::var("S0O_BIND_IP").unwrap_or("127.0.0.1".to_string());
     let s0o_bind_port: String = std::env::var("S0O_BIND_PORT").unwrap_or("3000".to_string());
 
     let app = Router::new()
-        .route("/project/:project/logs", get(project_logs))
-        .route("/project/:project/add/log", post(add_log));
+        .route("/project/:project/logs", get(project_logs::<S>))
+        .route("/project/:project/fields", get(project_fields::<S>))
+        .route(
+            "/project/:project/members",
+            get(project_members::<S>).post(add_member::<S>),
+        )
+        .route(
+            "/project/:project/members/:user",
+            put(change_role::<S>).delete(remove_member::<S>),
+        )
+        .route("/project/:project/transfer", post(transfer_project::<S>))
+        .route("/project/:project/export", get(export_logs::<S>))
+        .route("/project/:project/add/log", post(add_log::<S>))
+        .route("/project/:project/pin/:log", post(pin_log::<S>))
+        .route("/project/:project/unpin/:log", post(unpin_log::<S>))
+        .route("/teams", get(list_teams::<S>))
+        .route(
+            "/team/:team/members",
+            get(team_members::<S>).post(add_team_member::<S>),
+        )
+        .route("/team/:team/projects", get(team_projects::<S>))
+        .route("/search/logs", get(search_logs::<S>))
+        .route("/entry/:id/related", get(related_logs::<S>))
+        .route("/views", get(list_views::<S>))
+        .route("/views/:name", get(run_view::<S>).put(save_view::<S>))
+        .route("/admin/audit", get(audit_log::<S>));
 
-    let repo = SqliteRepo::new("/tmp/db.sqlite").await.unwrap();
-    let service = LogService::new(repo);
-    let store: AppContext = service.into();
-    let store_clone = store.clone();
+    let store = AppContext::new(service, config)?;
     {
         let addr: SocketAddr = format!("{}:{}", s0o_bind_ip, s0o_bind_port)
             .parse()
@@ -113,6 +929,7 @@ async fn main() {
         }
         info!("Server is stopping");
     }
+    Ok(())
 }
 
 // https://github.com/tokio-rs/axum/blob/main/examples/graceful-shutdown/src/main.rs
@@ -0,0 +1,247 @@
+use std::str::FromStr;
+
+use futures_util::TryStreamExt;
+use reverie::{
+    FieldKind, InMemoryRepo, LocalLogStoreService, LogFields, LogQuery, LogService,
+    LogServiceError, Page, ProjectName, ProjectSearch, Role, Username,
+};
+
+#[macro_use]
+mod common;
+
+use common::TempFile;
+
+fn no_read<T: std::fmt::Debug>(result: Result<T, LogServiceError>) {
+    match result {
+        Err(LogServiceError::NoReadAccess(user, project)) => {
+            assert_eq!(
+                (user.to_string(), project.to_string()),
+                ("bobby".into(), "infra".into())
+            )
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+}
+
+fn no_write<T: std::fmt::Debug>(result: Result<T, LogServiceError>) {
+    match result {
+        Err(LogServiceError::NoWriteAccess(user, project)) => {
+            assert_eq!(
+                (user.to_string(), project.to_string()),
+                ("bobby".into(), "infra".into())
+            )
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+}
+
+/// Only the owner and the members of a project read and write it, and
+/// writers do not add members
+async fn projects_are_kept_to_their_members<S: LocalLogStoreService>(service: S) {
+    let mut users = vec![];
+    for name in ["alice", "bobby", "carol"] {
+        let user = service.new_user(Username::from_str(name).unwrap()).await;
+        users.push(user.unwrap().id());
+    }
+    let (alice, bob, carol) = (users[0], users[1], users[2]);
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice)
+        .await
+        .unwrap()
+        .id();
+    let log = service
+        .add_log(alice, infra, "disk full".into(), LogFields::new())
+        .await
+        .unwrap()
+        .id();
+
+    no_write(
+        service
+            .add_log(bob, infra, "mine now".into(), LogFields::new())
+            .await,
+    );
+    no_write(service.pin(bob, infra, log).await);
+    no_write(service.unpin(bob, infra, log).await);
+    no_write(
+        service
+            .declare_field(bob, infra, "env".into(), FieldKind::String, false)
+            .await,
+    );
+    no_read(service.logs(bob, infra, Page::default()).await);
+    no_read(service.query_logs(bob, LogQuery::project(infra)).await);
+    no_read(service.project_logs(bob, LogQuery::project(infra)).await);
+    no_read(service.fields(bob, infra).await);
+    no_read(service.members(bob, infra).await);
+    no_read(service.stream_logs(bob, infra).await.map(|_| ()));
+    no_write(service.add_member(bob, infra, bob, Role::Writer).await);
+    assert!(matches!(
+        service.logs(bob, Default::default(), Page::default()).await,
+        Err(LogServiceError::ProjectNotFound)
+    ));
+    // the projects of others are neither found nor listed
+    let infra_name = ProjectName::from_str("infra").unwrap();
+    no_read(service.get_project(bob, infra_name.clone()).await);
+    let owned = service.projects_of(bob, alice, Page::default()).await;
+    assert_eq!(owned.unwrap().total, 0);
+    let alice_name = Username::from_str("alice").unwrap();
+    let owned = service.projects_of_named(bob, alice_name.clone(), Page::default());
+    assert!(owned.await.unwrap().data.is_empty());
+    let found = service
+        .search_projects(bob, ProjectSearch::new("infra"))
+        .await;
+    assert!(found.unwrap().is_empty());
+
+    service
+        .add_member(alice, infra, bob, Role::Writer)
+        .await
+        .unwrap();
+    assert!(matches!(
+        service.add_member(alice, infra, bob, Role::Writer).await,
+        Err(LogServiceError::Conflict(_))
+    ));
+    assert!(matches!(
+        service.add_member(alice, infra, alice, Role::Writer).await,
+        Err(LogServiceError::Conflict(_))
+    ));
+    service
+        .add_log(bob, infra, "cleaned up".into(), LogFields::new())
+        .await
+        .unwrap();
+    service.pin(bob, infra, log).await.unwrap();
+    let logs = service.stream_logs(bob, infra).await.unwrap();
+    let logs: Vec<_> = logs.try_collect().await.unwrap();
+    assert_eq!(logs.len(), 2);
+    let members = service.members(bob, infra).await.unwrap();
+    let names: Vec<_> = members.iter().map(|m| m.user.name().to_string()).collect();
+    assert_eq!(names, ["alice", "bobby"]);
+    no_write(service.add_member(bob, infra, carol, Role::Writer).await);
+
+    let found = service.get_project(bob, infra_name).await.unwrap();
+    assert_eq!(found.map(|p| p.id()), Some(infra));
+    let owned = service.projects_of_named(bob, alice_name, Page::default());
+    assert_eq!(owned.await.unwrap().total, 1);
+    let found = service
+        .search_projects(bob, ProjectSearch::new("infra"))
+        .await;
+    assert_eq!(found.unwrap().len(), 1);
+}
+
+on_sqlite_and_memory!(projects_are_kept_to_their_members);
+
+/// Each role allows what the previous ones do, maintainers manage the
+/// members, and only the owner hands the project over
+async fn roles_limit_what_members_do<S: LocalLogStoreService>(service: S) {
+    let mut users = vec![];
+    for name in ["alice", "bobby", "carol", "david"] {
+        let user = service.new_user(Username::from_str(name).unwrap()).await;
+        users.push(user.unwrap().id());
+    }
+    let (alice, bob, carol, dave) = (users[0], users[1], users[2], users[3]);
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice)
+        .await
+        .unwrap()
+        .id();
+    for (user, role) in [
+        (bob, Role::Reader),
+        (carol, Role::Writer),
+        (dave, Role::Maintainer),
+    ] {
+        service.add_member(alice, infra, user, role).await.unwrap();
+    }
+    let write = |user| service.add_log(user, infra, "note".into(), LogFields::new());
+
+    service.logs(bob, infra, Page::default()).await.unwrap();
+    no_write(write(bob).await);
+    write(carol).await.unwrap();
+    let field = |user| service.declare_field(user, infra, "env".into(), FieldKind::String, false);
+    assert!(matches!(
+        field(carol).await,
+        Err(LogServiceError::NoWriteAccess(..))
+    ));
+    field(dave).await.unwrap();
+    assert!(matches!(
+        service.add_member(carol, infra, bob, Role::Writer).await,
+        Err(LogServiceError::NoWriteAccess(..))
+    ));
+
+    let promoted = service.set_role(dave, infra, bob, Role::Writer).await;
+    assert_eq!(promoted.unwrap().role, Role::Writer);
+    write(bob).await.unwrap();
+    for (user, role) in [(bob, Role::Owner), (alice, Role::Reader)] {
+        assert!(matches!(
+            service.set_role(dave, infra, user, role).await,
+            Err(LogServiceError::InvalidRole(_))
+        ));
+    }
+    assert!(matches!(
+        service.add_member(alice, infra, bob, Role::Owner).await,
+        Err(LogServiceError::InvalidRole(_))
+    ));
+    assert!(matches!(
+        service.transfer_project(dave, infra, dave).await,
+        Err(LogServiceError::NoWriteAccess(..))
+    ));
+
+    let project = service.transfer_project(alice, infra, carol).await.unwrap();
+    assert_eq!(project.owner(), carol);
+    let members = service.members(alice, infra).await.unwrap();
+    let roles: Vec<_> = members
+        .iter()
+        .map(|m| format!("{} {}", m.user.name(), m.role))
+        .collect();
+    assert_eq!(
+        roles,
+        [
+            "carol owner",
+            "bobby writer",
+            "david maintainer",
+            "alice maintainer"
+        ]
+    );
+    let owned = service
+        .projects_of(carol, carol, Page::default())
+        .await
+        .unwrap();
+    assert_eq!(owned.data[0].id(), infra);
+
+    service.remove_member(bob, infra, bob).await.unwrap();
+    no_read(service.logs(bob, infra, Page::default()).await);
+    assert!(matches!(
+        service.remove_member(carol, infra, bob).await,
+        Err(LogServiceError::MemberNotFound)
+    ));
+    assert!(matches!(
+        service.remove_member(alice, infra, carol).await,
+        Err(LogServiceError::InvalidRole(_))
+    ));
+}
+
+#[tokio::test]
+async fn sqlite_roles_limit_what_members_do() {
+    let db = TempFile::database();
+    let repo = db.sqlite().await;
+    roles_limit_what_members_do(LogService::new(repo.clone())).await;
+
+    // roles and owners follow the tables when they are rebuilt
+    repo.rebuild().await.unwrap();
+    let service = LogService::new(repo);
+    let carol = service
+        .get_user(Username::from_str("carol").unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    let infra = service
+        .get_project(carol.id(), ProjectName::from_str("infra").unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(infra.owner(), carol.id());
+    let members = service.members(carol.id(), infra.id()).await.unwrap();
+    assert_eq!(members.len(), 3);
+}
+
+#[tokio::test]
+async fn memory_roles_limit_what_members_do() {
+    roles_limit_what_members_do(LogService::new(InMemoryRepo::new())).await;
+}
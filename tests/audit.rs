@@ -0,0 +1,191 @@
+use std::str::FromStr;
+
+use reverie::{
+    AuditQuery, FieldKind, InMemoryRepo, LocalLogStoreService, LogFields, LogService, Origin, Page,
+    ProjectName, Role, Username,
+};
+
+mod common;
+
+use common::TempFile;
+
+/// Every change is recorded with who made it, from where, and the state of
+/// its target before and after, newest first
+async fn changes_are_audited<S: LocalLogStoreService>(service: S) {
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap()
+        .id();
+    let bob = service
+        .new_user(Username::from_str("bobby").unwrap())
+        .await
+        .unwrap()
+        .id();
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice)
+        .await
+        .unwrap()
+        .id();
+    service
+        .add_member(alice, infra, bob, Role::Reader)
+        .await
+        .unwrap();
+    service
+        .set_role(alice, infra, bob, Role::Writer)
+        .await
+        .unwrap();
+    let log = service
+        .add_log(bob, infra, "disk full\nagain".into(), LogFields::new())
+        .await
+        .unwrap();
+    service
+        .declare_field(alice, infra, "severity".into(), FieldKind::Number, false)
+        .await
+        .unwrap();
+    service
+        .declare_field(alice, infra, "severity".into(), FieldKind::String, true)
+        .await
+        .unwrap();
+    service.remove_member(alice, infra, bob).await.unwrap();
+    // failed changes are not recorded
+    assert!(service.remove_member(alice, infra, bob).await.is_err());
+
+    let all = service.audit_log(AuditQuery::default()).await.unwrap();
+    assert_eq!(all.total, 9);
+    let actions: Vec<_> = all.data.iter().map(|e| e.action.as_str()).collect();
+    assert_eq!(
+        actions,
+        [
+            "member_removed",
+            "field_declared",
+            "field_declared",
+            "log_added",
+            "member_role_changed",
+            "member_added",
+            "project_created",
+            "user_created",
+            "user_created"
+        ]
+    );
+    assert!(all.data.iter().all(|e| e.origin == Origin::Cli));
+
+    let removed = &all.data[0];
+    assert_eq!(removed.actor, alice);
+    assert_eq!(removed.target, infra.to_string());
+    assert_eq!(removed.before.as_deref(), Some("bobby writer"));
+    assert_eq!(removed.after, None);
+    let redeclared = &all.data[1];
+    assert_eq!(redeclared.before.as_deref(), Some("severity: number"));
+    assert_eq!(
+        redeclared.after.as_deref(),
+        Some("severity: string (required)")
+    );
+    let changed = &all.data[4];
+    assert_eq!(changed.before.as_deref(), Some("bobby reader"));
+    assert_eq!(changed.after.as_deref(), Some("bobby writer"));
+
+    // long texts are summarized
+    let added = service
+        .audit_log(AuditQuery::default().target(log.id().to_string()))
+        .await
+        .unwrap();
+    assert_eq!(added.total, 1);
+    assert_eq!(added.data[0].actor, bob);
+    assert_eq!(added.data[0].after.as_deref(), Some("disk full…"));
+
+    let by_bob = AuditQuery::default().actor(bob);
+    assert_eq!(service.audit_log(by_bob).await.unwrap().total, 2);
+    let roles = AuditQuery::default()
+        .actor(alice)
+        .action("field_declared")
+        .page(Page::new(2, 1));
+    let page = service.audit_log(roles).await.unwrap();
+    assert_eq!(page.total, 2);
+    assert_eq!(page.data.len(), 1);
+    assert_eq!(page.data[0].before, None);
+}
+
+#[tokio::test]
+async fn sqlite_changes_are_audited() {
+    let db = TempFile::database();
+    let repo = db.sqlite().await;
+    changes_are_audited(LogService::new(repo.clone()).with_origin(Origin::Cli)).await;
+
+    // the audit trail follows the tables when they are rebuilt
+    repo.rebuild().await.unwrap();
+    let service = LogService::new(repo);
+    let all = service.audit_log(AuditQuery::default()).await.unwrap();
+    assert_eq!(all.total, 9);
+    assert_eq!(all.data[0].action, "member_removed");
+    assert_eq!(all.data[0].origin, Origin::Cli);
+}
+
+#[tokio::test]
+async fn memory_changes_are_audited() {
+    let service = LogService::new(InMemoryRepo::new()).with_origin(Origin::Cli);
+    changes_are_audited(service).await;
+}
+
+/// Changes made in one transaction are audited with the origin of the
+/// service running it
+#[tokio::test]
+async fn atomic_changes_keep_their_origin() {
+    let service = LogService::new(InMemoryRepo::new()).with_origin(Origin::Http);
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap()
+        .id();
+    service
+        .new_project_with_log(ProjectName::from_str("infra").unwrap(), alice, "hi".into())
+        .await
+        .unwrap();
+    let all = service.audit_log(AuditQuery::default()).await.unwrap();
+    let actions: Vec<_> = all.data.iter().map(|e| e.action.as_str()).collect();
+    assert_eq!(actions, ["log_added", "project_created", "user_created"]);
+    assert!(all.data.iter().all(|e| e.origin == Origin::Http));
+}
+
+/// A change whose audit entry can not be written is not made
+#[tokio::test]
+async fn unaudited_changes_are_rolled_back() {
+    let db = TempFile::database();
+    let service = LogService::new(db.sqlite().await);
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap()
+        .id();
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice)
+        .await
+        .unwrap()
+        .id();
+
+    let pool = sqlx::SqlitePool::connect(db.as_str()).await.unwrap();
+    sqlx::query(
+        "CREATE TRIGGER refuse_audit BEFORE INSERT ON audit \
+         BEGIN SELECT RAISE(ABORT, 'audit refused'); END",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    assert!(service
+        .new_project(ProjectName::from_str("web").unwrap(), alice)
+        .await
+        .is_err());
+    assert!(service
+        .add_log(alice, infra, "disk full".into(), LogFields::new())
+        .await
+        .is_err());
+    let web = service
+        .get_project(alice, ProjectName::from_str("web").unwrap())
+        .await
+        .unwrap();
+    assert!(web.is_none());
+    let logs = service.logs(alice, infra, Page::default()).await.unwrap();
+    assert_eq!(logs.total, 0);
+    let all = service.audit_log(AuditQuery::default()).await.unwrap();
+    assert_eq!(all.total, 2);
+}
@@ -1,9 +1,339 @@
 use assert_cmd::prelude::*; // Add methods on commands
 use std::process::Command; // Run programs
 
+mod common;
+
+use common::TempFile;
+
 #[test]
 fn test() {
-    let mut cmd = Command::cargo_bin("main").expect("main binary not found for test");
-    cmd.arg("p1").arg("test");
-    cmd.assert().success();
+    let mut cmd = Command::cargo_bin("cli").expect("cli binary not found for test");
+    cmd.arg("--ephemeral").arg("new").arg("user").arg("alice");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    assert!(String::from_utf8(output).unwrap().contains("(alice)"));
+}
+
+#[test]
+fn failures_exit_with_their_kind() {
+    let mut cmd = Command::cargo_bin("cli").expect("cli binary not found for test");
+    cmd.arg("--ephemeral").arg("id").arg("user").arg("nobody");
+    let output = cmd.assert().code(66).get_output().stderr.clone();
+    assert!(String::from_utf8(output)
+        .unwrap()
+        .contains("User not found"));
+}
+
+#[test]
+fn empty_pages_are_refused() {
+    for args in [
+        &["audit", "--page", "0"][..],
+        &["audit", "--size", "0"],
+        &["list", "users", "--size", "0"],
+    ] {
+        let mut cmd = Command::cargo_bin("cli").expect("cli binary not found for test");
+        cmd.arg("--ephemeral").args(args);
+        let output = cmd.assert().code(2).get_output().stderr.clone();
+        assert!(!String::from_utf8(output).unwrap().contains("panicked"));
+    }
+}
+
+#[test]
+fn ids_are_printed_or_not_found() {
+    let db = TempFile::database();
+    let cli = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("cli").expect("cli binary not found for test");
+        cmd.env("REVERIE_DB", format!("sqlite://{}", db.as_str()))
+            .args(args);
+        cmd
+    };
+    let output = cli(&["new", "user", "alice"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let created = String::from_utf8(output).unwrap();
+    let output = cli(&["id", "user", "alice"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let id = String::from_utf8(output).unwrap();
+    assert!(!id.trim().is_empty() && created.contains(id.trim()));
+    cli(&["new", "project", "infra", "alice"])
+        .assert()
+        .success();
+    cli(&["id", "project", "infra", "alice"]).assert().success();
+    let output = cli(&["id", "project", "web", "alice"])
+        .assert()
+        .code(66)
+        .get_output()
+        .stderr
+        .clone();
+    assert!(String::from_utf8(output)
+        .unwrap()
+        .contains("Project not found"));
+}
+
+#[test]
+fn missing_projects_get_suggestions() {
+    let db = TempFile::database();
+    let cli = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("cli").expect("cli binary not found for test");
+        cmd.env("REVERIE_DB", format!("sqlite://{}", db.as_str()))
+            .args(args);
+        cmd
+    };
+    cli(&["new", "user", "alice"]).assert().success();
+    cli(&["new", "project", "infrastructure", "alice"])
+        .assert()
+        .success();
+    let output = cli(&["list", "logs", "infrastrucutre", "-u", "alice"])
+        .assert()
+        .code(66)
+        .get_output()
+        .stderr
+        .clone();
+    assert!(String::from_utf8(output)
+        .unwrap()
+        .contains("did you mean infrastructure?"));
+
+    // projects the reader may not read are not suggested
+    cli(&["new", "user", "bobby"]).assert().success();
+    let output = cli(&["list", "logs", "infrastrucutre", "-u", "bobby"])
+        .assert()
+        .code(66)
+        .get_output()
+        .stderr
+        .clone();
+    assert!(!String::from_utf8(output).unwrap().contains("did you mean"));
+}
+
+#[test]
+fn projects_are_only_read_by_their_members() {
+    let db = TempFile::database();
+    let cli = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("cli").expect("cli binary not found for test");
+        cmd.env("REVERIE_DB", format!("sqlite://{}", db.as_str()))
+            .args(args);
+        cmd
+    };
+    cli(&["new", "user", "alice"]).assert().success();
+    cli(&["new", "user", "bobby"]).assert().success();
+    cli(&["new", "project", "infra", "alice"])
+        .assert()
+        .success();
+    let output = cli(&["list", "logs", "infra", "-u", "bobby"])
+        .assert()
+        .code(77)
+        .get_output()
+        .stderr
+        .clone();
+    assert!(String::from_utf8(output)
+        .unwrap()
+        .contains("bobby has no read access on infra"));
+    cli(&["new", "member", "bobby", "-p", "infra", "-u", "bobby"])
+        .assert()
+        .code(77);
+    cli(&["new", "member", "bobby", "-p", "infra", "-u", "alice"])
+        .assert()
+        .success();
+    cli(&["list", "logs", "infra", "-u", "bobby"])
+        .assert()
+        .success();
+    let output = cli(&["list", "members", "infra", "-u", "bobby"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let members = String::from_utf8(output).unwrap();
+    assert!(members.contains("(alice) owner") && members.contains("(bobby) writer"));
+    cli(&["role", "bobby", "reader", "-p", "infra", "-u", "alice"])
+        .assert()
+        .success();
+    cli(&["new", "log", "-a", "bobby", "-p", "infra", "read only"])
+        .assert()
+        .code(77);
+    cli(&["role", "bobby", "owner", "-p", "infra", "-u", "alice"])
+        .assert()
+        .code(65);
+    cli(&["transfer", "infra", "bobby", "-u", "alice"])
+        .assert()
+        .success();
+    let output = cli(&["list", "members", "infra", "-u", "alice"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let members = String::from_utf8(output).unwrap();
+    assert!(members.contains("(bobby) owner") && members.contains("(alice) maintainer"));
+    cli(&["remove", "member", "alice", "-p", "infra", "-u", "alice"])
+        .assert()
+        .success();
+
+    // members of a team read its projects
+    cli(&["new", "team", "ops", "alice"]).assert().success();
+    cli(&["new", "project", "web", "alice", "--team", "ops"])
+        .assert()
+        .success();
+    cli(&["list", "team-projects", "ops", "-u", "bobby"])
+        .assert()
+        .code(77);
+    cli(&[
+        "new",
+        "team-member",
+        "bobby",
+        "-t",
+        "ops",
+        "-r",
+        "reader",
+        "-u",
+        "alice",
+    ])
+    .assert()
+    .success();
+    let output = cli(&["list", "team-projects", "ops", "-u", "bobby"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert!(String::from_utf8(output).unwrap().contains(" - web "));
+    cli(&["list", "logs", "web", "-u", "bobby"])
+        .assert()
+        .success();
+    cli(&["list", "team-members", "nope", "-u", "bobby"])
+        .assert()
+        .code(66);
+}
+
+#[test]
+fn searches_are_bounded_by_dates() {
+    let db = TempFile::database();
+    let cli = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("cli").expect("cli binary not found for test");
+        cmd.env("REVERIE_DB", format!("sqlite://{}", db.as_str()))
+            .args(args);
+        cmd
+    };
+    cli(&["new", "user", "alice"]).assert().success();
+    cli(&["new", "project", "infra", "alice"])
+        .assert()
+        .success();
+    cli(&["new", "log", "-a", "alice", "-p", "infra", "disk full"])
+        .assert()
+        .success();
+    let search = |dates: &[&str]| {
+        let args = [&["search", "logs", "-u", "alice"], dates, &["disk"]].concat();
+        let output = cli(&args).assert().success().get_output().stdout.clone();
+        String::from_utf8(output).unwrap()
+    };
+    assert!(search(&["--since", "2000-01-01"]).contains("(1 in total)"));
+    assert!(search(&["--until", "2000-01-01"]).contains("(0 in total)"));
+}
+
+#[test]
+fn changes_are_audited_as_coming_from_the_cli() {
+    let db = TempFile::database();
+    let cli = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("cli").expect("cli binary not found for test");
+        cmd.env("REVERIE_DB", format!("sqlite://{}", db.as_str()))
+            .args(args);
+        cmd
+    };
+    cli(&["new", "user", "alice"]).assert().success();
+    cli(&["new", "user", "bobby"]).assert().success();
+    cli(&["new", "project", "infra", "alice"])
+        .assert()
+        .success();
+    let output = cli(&["audit", "--actor", "alice"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("[cli]"));
+    assert!(output.contains("project_created"));
+    assert!(output.contains("-> infra"));
+    assert!(!output.contains("-> bobby"));
+    let output = cli(&["audit", "--action", "user_created", "--size", "1"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("-> bobby"));
+    assert!(!output.contains("-> alice"));
+    cli(&["audit", "--actor", "nobody"]).assert().code(66);
+}
+
+#[test]
+fn names_follow_the_configured_policy() {
+    let db = TempFile::database();
+    let cli = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("cli").expect("cli binary not found for test");
+        cmd.env("REVERIE_DB", format!("sqlite://{}", db.as_str()))
+            .env("REVERIE_USERNAME_LENGTH", "2-8")
+            .env("REVERIE_USERNAME_RESERVED", "me,root")
+            .env("REVERIE_PROJECT_NAME_CASE_SENSITIVE", "true")
+            .args(args);
+        cmd
+    };
+    cli(&["new", "user", "bo"]).assert().success();
+    let output = cli(&["new", "user", "root"])
+        .assert()
+        .code(65)
+        .get_output()
+        .stderr
+        .clone();
+    assert!(String::from_utf8(output).unwrap().contains("reserved"));
+    // arguments are parsed under the policy
+    cli(&["new", "user", "bartholomew"]).assert().failure();
+    cli(&["new", "project", "infra/web", "bo"])
+        .assert()
+        .failure();
+    cli(&["new", "project", "Web", "bo"]).assert().success();
+    cli(&["new", "project", "web", "bo"]).assert().success();
+    cli(&["new", "user", "BO"]).assert().code(73);
+}
+
+#[test]
+fn names_are_keyed_again_when_the_policy_changes() {
+    let db = TempFile::database();
+    let cli = |case_sensitive: &str, args: &[&str]| {
+        let mut cmd = Command::cargo_bin("cli").expect("cli binary not found for test");
+        cmd.env("REVERIE_DB", format!("sqlite://{}", db.as_str()))
+            .env("REVERIE_PROJECT_NAME_CASE_SENSITIVE", case_sensitive)
+            .args(args);
+        cmd
+    };
+    cli("true", &["new", "user", "alice"]).assert().success();
+    cli("true", &["new", "project", "Infra", "alice"])
+        .assert()
+        .success();
+    cli("true", &["list", "logs", "infra", "-u", "alice"])
+        .assert()
+        .code(66);
+    cli("false", &["list", "logs", "infra", "-u", "alice"])
+        .assert()
+        .success();
+
+    // names made the same by the new policy are reported
+    cli("true", &["new", "project", "infra", "alice"])
+        .assert()
+        .success();
+    let output = cli("false", &["list", "logs", "infra", "-u", "alice"])
+        .assert()
+        .failure()
+        .get_output()
+        .stderr
+        .clone();
+    assert!(String::from_utf8(output)
+        .unwrap()
+        .contains("projects Infra and infra have the same name"));
 }
@@ -0,0 +1,65 @@
+//! Helpers shared by the integration tests, each using only some of them
+#![allow(dead_code, unused_macros)]
+
+use std::path::{Path, PathBuf};
+
+use reverie::SqliteRepo;
+
+/// A file in the temporary directory, removed with its SQLite companions
+/// when dropped, even by a failing test
+pub struct TempFile {
+    path: PathBuf,
+    name: String,
+}
+
+impl TempFile {
+    pub fn new(extension: &str) -> Self {
+        let file = format!("reverie_test_{}.{extension}", uuid::Uuid::now_v7().simple());
+        let path = std::env::temp_dir().join(file);
+        let name = path.to_string_lossy().into_owned();
+        Self { path, name }
+    }
+    /// A SQLite database file
+    pub fn database() -> Self {
+        Self::new("sqlite")
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+    /// The SQLite repository stored in the file, migrated
+    pub async fn sqlite(&self) -> SqliteRepo {
+        SqliteRepo::new(&self.name).await.unwrap()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+        for suffix in ["-wal", "-shm", "-journal"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", self.name));
+        }
+    }
+}
+
+/// Runs the scenario, an async function taking a `LocalLogStoreService`,
+/// on a SQLite database and in memory, as the tests `<scenario>::sqlite`
+/// and `<scenario>::memory`
+macro_rules! on_sqlite_and_memory {
+    ($($scenario:ident),+ $(,)?) => {$(
+        mod $scenario {
+            #[tokio::test]
+            async fn sqlite() {
+                let db = $crate::common::TempFile::database();
+                super::$scenario(reverie::LogService::new(db.sqlite().await)).await;
+            }
+
+            #[tokio::test]
+            async fn memory() {
+                super::$scenario(reverie::LogService::new(reverie::InMemoryRepo::new())).await;
+            }
+        }
+    )+};
+}
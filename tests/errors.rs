@@ -0,0 +1,25 @@
+use std::str::FromStr;
+
+use reverie::{LocalLogStoreService, LogService, LogServiceError, Username};
+use sqlx::Connection;
+
+mod common;
+
+use common::TempFile;
+
+#[tokio::test]
+async fn storage_failures_are_not_reported_as_missing_entries() {
+    let db = TempFile::database();
+    let service = LogService::new(db.sqlite().await);
+    let alice = Username::from_str("alice").unwrap();
+    service.new_user(alice.clone()).await.unwrap();
+    assert!(service.get_user(alice.clone()).await.unwrap().is_some());
+
+    let mut conn = sqlx::SqliteConnection::connect(db.as_str()).await.unwrap();
+    sqlx::query("DROP TABLE author")
+        .execute(&mut conn)
+        .await
+        .unwrap();
+    let found = service.get_user(alice).await;
+    assert!(matches!(found, Err(LogServiceError::Unavailable(_))));
+}
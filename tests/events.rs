@@ -0,0 +1,138 @@
+use std::str::FromStr;
+
+use reverie::{
+    FieldFilter, FieldKind, LocalLogStoreService, LogFields, LogQuery, LogService, ProjectName,
+    Role, SqliteRepo, Username,
+};
+
+mod common;
+
+use common::TempFile;
+
+/// Texts of the logs of the project read by its member, with pinned logs
+/// marked by a `*`
+async fn snapshot(service: &LogService<SqliteRepo>) -> Vec<String> {
+    let bob = service
+        .get_user(Username::from_str("bobby").unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    let project = service
+        .get_project(bob.id(), ProjectName::from_str("infra").unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    let logs = service
+        .project_logs(bob.id(), LogQuery::project(project.id()))
+        .await
+        .unwrap();
+    let pinned = logs.pinned.iter().map(|log| format!("*{}", log.text()));
+    let logs = logs.logs.data.iter().map(|log| log.text().to_string());
+    pinned.chain(logs).collect()
+}
+
+async fn fill(service: &LogService<SqliteRepo>) {
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap();
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice.id())
+        .await
+        .unwrap();
+    let bob = service
+        .new_user(Username::from_str("bobby").unwrap())
+        .await
+        .unwrap();
+    service
+        .add_member(alice.id(), infra.id(), bob.id(), Role::Writer)
+        .await
+        .unwrap();
+    service
+        .declare_field(
+            alice.id(),
+            infra.id(),
+            "env".into(),
+            FieldKind::String,
+            false,
+        )
+        .await
+        .unwrap();
+    let first = service
+        .add_log(alice.id(), infra.id(), "first".into(), LogFields::new())
+        .await
+        .unwrap();
+    let fields = LogFields::from([("env".to_string(), "prod".to_string())]);
+    let second = service
+        .add_log(alice.id(), infra.id(), "second".into(), fields)
+        .await
+        .unwrap();
+    service
+        .pin(alice.id(), infra.id(), first.id())
+        .await
+        .unwrap();
+    service
+        .pin(bob.id(), infra.id(), second.id())
+        .await
+        .unwrap();
+    service
+        .unpin(alice.id(), infra.id(), first.id())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn tables_are_rebuilt_from_events() {
+    let db = TempFile::database();
+    let repo = db.sqlite().await;
+    let service = LogService::new(repo.clone());
+    fill(&service).await;
+    let before = snapshot(&service).await;
+    assert_eq!(before, ["*second", "first", "second"]);
+
+    let pool = sqlx::SqlitePool::connect(db.as_str()).await.unwrap();
+    sqlx::raw_sql("DELETE FROM pin; DELETE FROM log_field; DELETE FROM log;")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(snapshot(&service).await, Vec::<String>::new());
+
+    // each change is followed by its audit entry
+    assert_eq!(repo.rebuild().await.unwrap(), 20);
+    assert_eq!(snapshot(&service).await, before);
+    let alice = service
+        .get_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    let project = service
+        .get_project(alice.id(), ProjectName::from_str("infra").unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    let prod = service
+        .query_logs(
+            alice.id(),
+            LogQuery::project(project.id()).field(FieldFilter::from_str("env=prod").unwrap()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(prod.data.len(), 1);
+}
+
+#[tokio::test]
+async fn rows_written_before_events_survive_a_rebuild() {
+    let db = TempFile::database();
+    fill(&LogService::new(db.sqlite().await)).await;
+    let pool = sqlx::SqlitePool::connect(db.as_str()).await.unwrap();
+    sqlx::query("DELETE FROM event")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let repo = db.sqlite().await;
+    let service = LogService::new(repo.clone());
+    let before = snapshot(&service).await;
+    repo.rebuild().await.unwrap();
+    assert_eq!(snapshot(&service).await, before);
+}
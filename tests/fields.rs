@@ -0,0 +1,193 @@
+use std::str::FromStr;
+
+use reverie::{
+    FieldFilter, FieldKind, LocalLogStoreService, LogFields, LogQuery, LogServiceError, ProjectId,
+    ProjectName, UserId, Username,
+};
+
+#[macro_use]
+mod common;
+
+fn fields(values: &[(&str, &str)]) -> LogFields {
+    values
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Name of the invalid field and why it is invalid
+fn invalid<T: std::fmt::Debug>(result: Result<T, LogServiceError>) -> (String, String) {
+    match result {
+        Err(LogServiceError::InvalidField(invalid)) => (invalid.0, invalid.1),
+        other => panic!("unexpected {other:?}"),
+    }
+}
+
+/// Texts of the logs of the project matching the filters, sorted
+async fn filtered<S: LocalLogStoreService>(
+    service: &S,
+    reader: UserId,
+    project: ProjectId,
+    filters: &[&str],
+) -> Result<Vec<String>, LogServiceError> {
+    let filters = filters.iter().map(|f| FieldFilter::from_str(f).unwrap());
+    let query = LogQuery::project(project).fields(filters);
+    let logs = service.query_logs(reader, query).await?;
+    let mut texts: Vec<String> = logs.data.iter().map(|log| log.text().to_string()).collect();
+    texts.sort();
+    Ok(texts)
+}
+
+/// Alice, Bob and the project "ops" of Alice declaring a field of each kind
+async fn ops<S: LocalLogStoreService>(service: &S) -> (UserId, UserId, ProjectId) {
+    let mut users = vec![];
+    for name in ["alice", "bobby"] {
+        let user = service.new_user(Username::from_str(name).unwrap()).await;
+        users.push(user.unwrap().id());
+    }
+    let (alice, bob) = (users[0], users[1]);
+    let ops = service
+        .new_project(ProjectName::from_str("ops").unwrap(), alice)
+        .await
+        .unwrap()
+        .id();
+    for (name, kind, required) in [
+        ("severity", "number", true),
+        ("env", "enum:prod,staging", false),
+        ("due", "date", false),
+        ("owner", "user", false),
+    ] {
+        let kind = FieldKind::from_str(kind).unwrap();
+        service
+            .declare_field(alice, ops, name.into(), kind, required)
+            .await
+            .unwrap();
+    }
+    (alice, bob, ops)
+}
+
+/// Logs are only added with values of the kinds the project declares, and
+/// with its required fields
+async fn fields_are_validated<S: LocalLogStoreService>(service: S) {
+    let (alice, _, ops) = ops(&service).await;
+    let add =
+        |values: &[(&str, &str)]| service.add_log(alice, ops, "disk full".into(), fields(values));
+
+    let (field, reason) = invalid(add(&[("severity", "high")]).await);
+    assert_eq!(
+        (field.as_str(), reason.as_str()),
+        ("severity", "high is not a number")
+    );
+    let (field, reason) = invalid(add(&[("severity", "2"), ("env", "dev")]).await);
+    assert_eq!(
+        (field.as_str(), reason.as_str()),
+        ("env", "expected one of prod, staging")
+    );
+    for date in ["2023-02-29", "2024-13-01", "24-01-01", "tomorrow"] {
+        let (field, _) = invalid(add(&[("severity", "2"), ("due", date)]).await);
+        assert_eq!(field, "due");
+    }
+    let (field, reason) = invalid(add(&[("env", "prod")]).await);
+    assert_eq!((field.as_str(), reason.as_str()), ("severity", "required"));
+    let (field, reason) = invalid(add(&[("severity", "2"), ("team", "web")]).await);
+    assert_eq!(
+        (field.as_str(), reason.as_str()),
+        ("team", "not declared by the project")
+    );
+    let (field, reason) = invalid(add(&[("severity", "2"), ("owner", "nobody")]).await);
+    assert_eq!(
+        (field.as_str(), reason.as_str()),
+        ("owner", "unknown user nobody")
+    );
+    let kind = FieldKind::String;
+    let (field, _) = invalid(
+        service
+            .declare_field(alice, ops, "due date".into(), kind, false)
+            .await,
+    );
+    assert_eq!(field, "due date");
+
+    add(&[("severity", "2.5"), ("env", "prod"), ("due", "2024-02-29")])
+        .await
+        .unwrap();
+    let logs = service
+        .query_logs(alice, LogQuery::project(ops))
+        .await
+        .unwrap();
+    assert_eq!(logs.data.len(), 1);
+}
+
+/// User fields are stored by id whether given by name or id, and logs are
+/// filtered on the declared fields only
+async fn logs_are_filtered_by_field<S: LocalLogStoreService>(service: S) {
+    let (alice, bob, ops) = ops(&service).await;
+    for (text, values) in [
+        (
+            "disk full",
+            [("severity", "1"), ("env", "staging"), ("owner", "bobby")],
+        ),
+        (
+            "server down",
+            [("severity", "10"), ("env", "prod"), ("owner", "alice")],
+        ),
+        (
+            "cert expired",
+            [
+                ("severity", "3"),
+                ("env", "prod"),
+                ("owner", &bob.to_string()),
+            ],
+        ),
+    ] {
+        service
+            .add_log(alice, ops, text.into(), fields(&values))
+            .await
+            .unwrap();
+    }
+    let owned = filtered(&service, alice, ops, &["owner=bobby"])
+        .await
+        .unwrap();
+    assert_eq!(owned, ["cert expired", "disk full"]);
+    assert_eq!(
+        filtered(&service, alice, ops, &[&format!("owner={bob}")])
+            .await
+            .unwrap(),
+        owned
+    );
+    let logs = service
+        .query_logs(alice, LogQuery::project(ops))
+        .await
+        .unwrap();
+    for log in logs.data.iter().filter(|log| log.text() != "server down") {
+        assert_eq!(log.fields().get("owner"), Some(&bob.to_string()));
+    }
+    let severe = filtered(&service, alice, ops, &["severity>=3"])
+        .await
+        .unwrap();
+    assert_eq!(severe, ["cert expired", "server down"]);
+    let prod = filtered(&service, alice, ops, &["env=prod", "severity<5"])
+        .await
+        .unwrap();
+    assert_eq!(prod, ["cert expired"]);
+    assert_eq!(
+        filtered(&service, alice, ops, &["env!=prod"])
+            .await
+            .unwrap(),
+        ["disk full"]
+    );
+
+    assert_eq!(
+        invalid(filtered(&service, alice, ops, &["severity>high"]).await).0,
+        "severity"
+    );
+    assert_eq!(
+        invalid(filtered(&service, alice, ops, &["owner=nobody"]).await).0,
+        "owner"
+    );
+    assert_eq!(
+        invalid(filtered(&service, alice, ops, &["team=web"]).await).0,
+        "team"
+    );
+}
+
+on_sqlite_and_memory!(fields_are_validated, logs_are_filtered_by_field);
@@ -0,0 +1,119 @@
+#![cfg(feature = "journal")]
+
+use std::{path::Path, str::FromStr};
+
+use reverie::{
+    AuditQuery, JournalRepo, LocalLogStoreService, LogFields, LogService, Page, ProjectName,
+    Username,
+};
+
+mod common;
+
+use common::TempFile;
+
+fn open(path: &Path) -> LogService<JournalRepo> {
+    LogService::new(JournalRepo::open(path).unwrap())
+}
+
+async fn fill(service: &LogService<JournalRepo>, logs: usize) {
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap();
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice.id())
+        .await
+        .unwrap();
+    for i in 0..logs {
+        service
+            .add_log(alice.id(), infra.id(), format!("log {i}"), LogFields::new())
+            .await
+            .unwrap();
+    }
+}
+
+async fn texts(service: &LogService<JournalRepo>) -> Vec<String> {
+    let alice = service
+        .get_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap();
+    let Some(alice) = alice else {
+        return vec![];
+    };
+    let Some(project) = service
+        .get_project(alice.id(), ProjectName::from_str("infra").unwrap())
+        .await
+        .unwrap()
+    else {
+        return vec![];
+    };
+    let logs = service
+        .logs(alice.id(), project.id(), Page::default())
+        .await
+        .unwrap();
+    logs.data.iter().map(|log| log.text().to_string()).collect()
+}
+
+#[tokio::test]
+async fn journal_is_replayed_when_reopened() {
+    let journal = TempFile::new("cbor");
+    let path = journal.path();
+    fill(&open(path), 2).await;
+
+    let service = open(path);
+    assert_eq!(texts(&service).await, ["log 0", "log 1"]);
+    let duplicate = service.new_user(Username::from_str("alice").unwrap()).await;
+    assert!(duplicate.is_err());
+}
+
+#[tokio::test]
+async fn truncated_record_is_dropped() {
+    let journal = TempFile::new("cbor");
+    let path = journal.path();
+    fill(&open(path), 2).await;
+    let length = std::fs::metadata(path).unwrap().len();
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_len(length - 3)
+        .unwrap();
+
+    // the torn record is the commit of the last log, dropped with its audit
+    // entry
+    let service = open(path);
+    assert_eq!(texts(&service).await, ["log 0"]);
+    let audited = service.audit_log(AuditQuery::default()).await.unwrap();
+    assert!(audited
+        .data
+        .iter()
+        .all(|entry| entry.after.as_deref() != Some("log 1")));
+    assert_eq!(audited.data[0].after.as_deref(), Some("log 0"));
+    let alice = service
+        .get_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    let project = service
+        .get_project(alice.id(), ProjectName::from_str("infra").unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    service
+        .add_log(alice.id(), project.id(), "log 2".into(), LogFields::new())
+        .await
+        .unwrap();
+    assert_eq!(texts(&open(path)).await, ["log 0", "log 2"]);
+}
+
+#[tokio::test]
+async fn corrupt_journal_is_refused() {
+    let journal = TempFile::new("cbor");
+    let path = journal.path();
+    fill(&open(path), 1).await;
+    let mut bytes = std::fs::read(path).unwrap();
+    bytes.splice(0..0, [0xff, 0xff]);
+    std::fs::write(path, bytes).unwrap();
+
+    assert!(JournalRepo::open(path).is_err());
+}
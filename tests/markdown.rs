@@ -0,0 +1,39 @@
+use reverie::{Log, TextFormat, UserId};
+
+fn render(text: &str, format: TextFormat) -> String {
+    Log::new(text.to_string(), UserId::default())
+        .rendered(format)
+        .text()
+}
+
+#[test]
+fn html_is_sanitized() {
+    let html = render(
+        "<script>alert(1)</script>\n\n[link](javascript:alert(1)) `code`",
+        TextFormat::Html,
+    );
+    assert!(!html.contains("<script"));
+    assert!(!html.contains("javascript:"));
+    assert!(html.contains("<code>code</code>"));
+}
+
+#[test]
+fn ansi_renders_lists_and_links() {
+    let ansi = render("- one\n- [two](https://example.org)", TextFormat::Ansi);
+    assert!(ansi.contains("• one"));
+    assert!(ansi.contains("<https://example.org>"));
+    assert_eq!(render("*raw*", TextFormat::Raw), "*raw*");
+}
+
+#[test]
+fn ansi_leaves_out_control_characters_and_html() {
+    let ansi = render(
+        "\x1b[2Jcleared\r <b>bold</b>\n\n<div>block</div>\n\n```\nrm \x1b[31m-rf\n```",
+        TextFormat::Ansi,
+    );
+    assert!(!ansi.contains("\x1b[2J") && !ansi.contains("\x1b[31m"));
+    assert!(!ansi.contains('\r'));
+    assert!(!ansi.contains("<b>") && !ansi.contains("<div>"));
+    assert!(ansi.contains("cleared bold"));
+    assert!(ansi.contains("rm [31m-rf"));
+}
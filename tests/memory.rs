@@ -0,0 +1,77 @@
+use std::str::FromStr;
+
+use reverie::{
+    FieldFilter, InMemoryRepo, LocalLogStoreService, LogFields, LogQuery, LogService,
+    LogServiceError, Page, ProjectName, Username,
+};
+
+fn service() -> LogService<InMemoryRepo> {
+    LogService::new(InMemoryRepo::new())
+}
+
+#[tokio::test]
+async fn names_are_unique() {
+    let service = service();
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap();
+    let again = service.new_user(Username::from_str("alice").unwrap()).await;
+    assert!(matches!(again, Err(LogServiceError::UserExists)));
+
+    let name = ProjectName::from_str("infra").unwrap();
+    service.new_project(name.clone(), alice.id()).await.unwrap();
+    let again = service.new_project(name, alice.id()).await;
+    assert!(matches!(again, Err(LogServiceError::ProjectExists)));
+}
+
+#[tokio::test]
+async fn logs_are_paged_in_insertion_order() {
+    let service = service();
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap();
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice.id())
+        .await
+        .unwrap();
+    for i in 0..5 {
+        let fields = LogFields::from([("step".to_string(), i.to_string())]);
+        service
+            .add_log(alice.id(), infra.id(), format!("log {i}"), fields)
+            .await
+            .unwrap();
+    }
+    let texts = |page: Page| service.logs(alice.id(), infra.id(), page);
+    let page = texts(Page::new(1, 2)).await.unwrap();
+    assert_eq!(page.data[0].text(), "log 0");
+    assert_eq!(page.data[1].text(), "log 1");
+    let page = texts(Page::new(3, 2)).await.unwrap();
+    assert_eq!(page.data.len(), 1);
+    assert_eq!(page.data[0].text(), "log 4");
+    assert!(texts(Page::new(4, 2)).await.unwrap().data.is_empty());
+
+    let filtered = service
+        .query_logs(
+            alice.id(),
+            LogQuery::project(infra.id()).field(FieldFilter::from_str("step>=3").unwrap()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(filtered.data.len(), 2);
+}
+
+#[tokio::test]
+async fn logs_require_an_existing_project() {
+    let service = service();
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap();
+    let unknown = reverie::ProjectId::default();
+    let log = service
+        .add_log(alice.id(), unknown, "lost".into(), LogFields::new())
+        .await;
+    assert!(log.is_err());
+}
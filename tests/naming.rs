@@ -0,0 +1,133 @@
+use std::str::FromStr;
+
+use reverie::{
+    InMemoryRepo, LocalLogStoreService, LogService, LogServiceError, NamePolicy, ProjectName,
+    Username,
+};
+
+mod common;
+
+use common::TempFile;
+
+#[test]
+fn policies_normalize_and_fold_names() {
+    let users = NamePolicy::users();
+    assert_eq!(users.check("alice"), Ok("alice".to_string()));
+    // a decomposed Å is stored composed
+    assert_eq!(users.check("A\u{30a}sa.b"), Ok("\u{c5}sa.b".to_string()));
+    assert_eq!(users.check("bob"), Err("too short"));
+    assert!(users.check("al/ce").is_err());
+    assert!(users.check("ali\u{7}ce").is_err());
+    assert!(users.check("ali ce").is_err());
+    assert_eq!(users.check("_alice"), Err("starts or ends with a symbol"));
+    assert_eq!(users.key("ALICE"), users.key("alice"));
+    // compatibility characters are the same name
+    assert_eq!(users.key("\u{ff41}lice"), users.key("alice"));
+    assert!(users.is_reserved("ME"));
+
+    let projects = NamePolicy {
+        unicode: false,
+        case_sensitive: true,
+        ..NamePolicy::projects()
+    }
+    .with_symbols("-/\n");
+    assert_eq!(projects.symbols, "-");
+    assert!(projects.check("web-site").is_ok());
+    assert!(projects.check("web site").is_err());
+    assert!(projects.check("caf\u{e9}").is_err());
+    assert_ne!(projects.key("Web"), projects.key("web"));
+
+    // reserved words are parsed wherever a name is expected
+    assert!(Username::from_str("me").is_ok());
+    assert!(ProjectName::from_str("infra/web").is_err());
+    assert!(ProjectName::from_str("..").is_err());
+}
+
+/// Names differing only by case or normalization are taken once, and find
+/// the same entry
+async fn names_are_unique_once_folded<S: LocalLogStoreService>(service: S) {
+    let alice = service
+        .new_user(Username::from_str("Alice").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(alice.name().to_string(), "Alice");
+    assert!(matches!(
+        service.new_user(Username::from_str("alice").unwrap()).await,
+        Err(LogServiceError::UserExists)
+    ));
+    assert!(matches!(
+        service.new_user(Username::from_str("me").unwrap()).await,
+        Err(LogServiceError::InvalidUsername(_))
+    ));
+    let found = service
+        .get_user(Username::from_str("ALICE").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(found.map(|u| u.id()), Some(alice.id()));
+
+    service
+        .new_project(ProjectName::from_str("Infra").unwrap(), alice.id())
+        .await
+        .unwrap();
+    assert!(matches!(
+        service
+            .new_project(ProjectName::from_str("infra").unwrap(), alice.id())
+            .await,
+        Err(LogServiceError::ProjectExists)
+    ));
+    let found = service
+        .get_project(alice.id(), ProjectName::from_str("INFRA").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(found.unwrap().name().to_string(), "Infra");
+}
+
+#[tokio::test]
+async fn sqlite_names_are_unique_once_folded() {
+    let db = TempFile::database();
+    let repo = db.sqlite().await;
+    names_are_unique_once_folded(LogService::new(repo.clone())).await;
+
+    // names written before they had keys get them when the database opens
+    let pool = sqlx::SqlitePool::connect(db.as_str()).await.unwrap();
+    sqlx::raw_sql("UPDATE author SET name_key = NULL; UPDATE project SET name_key = NULL;")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let service = LogService::new(db.sqlite().await);
+    let found = service
+        .get_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap();
+    let alice = found.unwrap();
+    assert_eq!(alice.name().to_string(), "Alice");
+    let found = service
+        .get_project(alice.id(), ProjectName::from_str("infra").unwrap())
+        .await
+        .unwrap();
+    assert!(found.is_some());
+
+    // reserved words name nobody, even users named before they were reserved
+    sqlx::query("INSERT INTO author (id,name,name_key) VALUES ($1,'me','me')")
+        .bind(uuid::Uuid::now_v7())
+        .execute(&pool)
+        .await
+        .unwrap();
+    let found = service
+        .get_user(Username::from_str("me").unwrap())
+        .await
+        .unwrap();
+    assert!(found.is_none());
+
+    // and the keys follow the tables when they are rebuilt
+    repo.rebuild().await.unwrap();
+    assert!(matches!(
+        service.new_user(Username::from_str("ALICE").unwrap()).await,
+        Err(LogServiceError::UserExists)
+    ));
+}
+
+#[tokio::test]
+async fn memory_names_are_unique_once_folded() {
+    names_are_unique_once_folded(LogService::new(InMemoryRepo::new())).await;
+}
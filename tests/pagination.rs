@@ -0,0 +1,117 @@
+use std::str::FromStr;
+
+use reverie::{LocalLogStoreService, LogFields, Page, ProjectName, Username};
+
+#[macro_use]
+mod common;
+
+/// Pages hold at most `size` entries, in creation order, with the total
+/// counted over every page
+async fn pages_are_counted_and_ordered<S: LocalLogStoreService>(service: S) {
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap();
+    let mut projects = vec![];
+    for n in 0..7 {
+        let name = ProjectName::from_str(&format!("project-{n}")).unwrap();
+        projects.push(service.new_project(name, alice.id()).await.unwrap());
+    }
+    let first = service
+        .projects_of(alice.id(), alice.id(), Page::new(1, 3))
+        .await
+        .unwrap();
+    assert_eq!(first.data.len(), 3);
+    assert_eq!((first.total, first.page_count), (7, 3));
+    assert!(first.has_next);
+    let last = service
+        .projects_of(alice.id(), alice.id(), Page::new(3, 3))
+        .await
+        .unwrap();
+    assert_eq!(last.data.len(), 1);
+    assert_eq!(last.data[0].id(), projects[6].id());
+    assert!(!last.has_next);
+
+    let infra = projects[0].id();
+    for n in 0..5 {
+        service
+            .add_log(alice.id(), infra, format!("log {n}"), LogFields::new())
+            .await
+            .unwrap();
+    }
+    for number in 1..=3 {
+        let page = service
+            .logs(alice.id(), infra, Page::new(number, 2))
+            .await
+            .unwrap();
+        let texts: Vec<_> = page.data.iter().map(|log| log.text().to_string()).collect();
+        let expected: Vec<_> = (2 * (number - 1)..(2 * number).min(5))
+            .map(|n| format!("log {n}"))
+            .collect();
+        assert_eq!(texts, expected);
+        assert_eq!(page.total, 5);
+        assert_eq!(page.has_next, number < 3);
+    }
+    let past = service
+        .logs(alice.id(), infra, Page::new(4, 2))
+        .await
+        .unwrap();
+    assert!(past.data.is_empty());
+    assert_eq!(past.total, 5);
+}
+
+/// Following the `next` cursors lists every log once, even with logs added
+/// meanwhile, and `prev` cursors lead back
+async fn cursors_follow_the_logs<S: LocalLogStoreService>(service: S) {
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap();
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice.id())
+        .await
+        .unwrap()
+        .id();
+    let add = |n: usize| service.add_log(alice.id(), infra, format!("log {n}"), LogFields::new());
+    for n in 0..5 {
+        add(n).await.unwrap();
+    }
+    let mut texts = vec![];
+    let mut page = Page::new(1, 2);
+    loop {
+        let logs = service.logs(alice.id(), infra, page).await.unwrap();
+        texts.extend(logs.data.iter().map(|log| log.text().to_string()));
+        if texts.len() == 2 {
+            add(5).await.unwrap();
+        }
+        match logs.next {
+            Some(next) => page = Page::after(next, 2),
+            None => break,
+        }
+    }
+    let expected: Vec<_> = (0..6).map(|n| format!("log {n}")).collect();
+    assert_eq!(texts, expected);
+
+    let last = service
+        .logs(alice.id(), infra, Page::new(3, 2))
+        .await
+        .unwrap();
+    assert!(last.next.is_none());
+    let before = service
+        .logs(alice.id(), infra, Page::before(last.prev.unwrap(), 2))
+        .await
+        .unwrap();
+    let texts: Vec<_> = before.data.iter().map(|log| log.text()).collect();
+    assert_eq!(texts, ["log 2", "log 3"]);
+    assert!(before.has_next);
+    let first = service
+        .logs(alice.id(), infra, Page::before(before.prev.unwrap(), 2))
+        .await
+        .unwrap();
+    assert_eq!(first.data[0].text(), "log 0");
+    assert!(first.prev.is_none());
+}
+
+on_sqlite_and_memory!(pages_are_counted_and_ordered);
+
+on_sqlite_and_memory!(cursors_follow_the_logs);
@@ -0,0 +1,117 @@
+use std::str::FromStr;
+
+use reverie::{
+    EntryId, InMemoryRepo, LocalLogStoreService, LogFields, LogQuery, LogService, LogServiceError,
+    ProjectId, ProjectName, UnitOfWork, UserId, Username, MAX_PINNED_LOGS,
+};
+
+#[macro_use]
+mod common;
+
+/// Alice, her project "infra" and its logs "log 0", "log 1"… oldest first
+async fn infra<S: LocalLogStoreService>(
+    service: &S,
+    logs: usize,
+) -> (UserId, ProjectId, Vec<EntryId>) {
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap()
+        .id();
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice)
+        .await
+        .unwrap()
+        .id();
+    let mut ids = vec![];
+    for i in 0..logs {
+        let log = service
+            .add_log(alice, infra, format!("log {i}"), LogFields::new())
+            .await
+            .unwrap();
+        ids.push(log.id());
+    }
+    (alice, infra, ids)
+}
+
+/// Texts of the pinned logs then of the timeline, as read by the user
+async fn shown<S: LocalLogStoreService>(
+    service: &S,
+    user: UserId,
+    project: ProjectId,
+) -> (Vec<String>, Vec<String>) {
+    let logs = service
+        .project_logs(user, LogQuery::project(project))
+        .await
+        .unwrap();
+    let texts = |logs: &[reverie::Log]| logs.iter().map(|log| log.text().to_string()).collect();
+    (texts(&logs.pinned), texts(&logs.logs.data))
+}
+
+/// Pinned logs come first, most recently pinned first, and leave the
+/// timeline when unpinned
+async fn pinned_logs_come_first<S: LocalLogStoreService>(service: S) {
+    let (alice, infra, logs) = infra(&service, 3).await;
+
+    service.pin(alice, infra, logs[0]).await.unwrap();
+    service.pin(alice, infra, logs[1]).await.unwrap();
+    service.pin(alice, infra, logs[0]).await.unwrap();
+    let (pinned, timeline) = shown(&service, alice, infra).await;
+    assert_eq!(pinned, ["log 1", "log 0"]);
+    assert_eq!(timeline.len(), 3);
+
+    service.unpin(alice, infra, logs[1]).await.unwrap();
+    assert_eq!(shown(&service, alice, infra).await.0, ["log 0"]);
+    assert!(matches!(
+        service.unpin(alice, infra, logs[1]).await,
+        Err(LogServiceError::LogNotPinned)
+    ));
+    assert!(matches!(
+        service.pin(alice, infra, EntryId::default()).await,
+        Err(LogServiceError::LogNotFound)
+    ));
+}
+
+/// A project has at most `MAX_PINNED_LOGS` pinned logs, pinning one of
+/// them again being fine
+async fn pinned_logs_are_capped<S: LocalLogStoreService>(service: S) {
+    let (alice, infra, logs) = infra(&service, MAX_PINNED_LOGS + 1).await;
+    for &log in &logs[..MAX_PINNED_LOGS] {
+        service.pin(alice, infra, log).await.unwrap();
+    }
+
+    assert!(matches!(
+        service.pin(alice, infra, logs[MAX_PINNED_LOGS]).await,
+        Err(LogServiceError::TooManyPinnedLogs(MAX_PINNED_LOGS))
+    ));
+    service.pin(alice, infra, logs[0]).await.unwrap();
+    assert_eq!(shown(&service, alice, infra).await.0.len(), MAX_PINNED_LOGS);
+
+    service.unpin(alice, infra, logs[0]).await.unwrap();
+    service
+        .pin(alice, infra, logs[MAX_PINNED_LOGS])
+        .await
+        .unwrap();
+}
+
+on_sqlite_and_memory!(pinned_logs_come_first, pinned_logs_are_capped);
+
+#[tokio::test]
+async fn concurrent_pins_stay_under_the_cap() {
+    let repo = InMemoryRepo::new();
+    let service = LogService::new(repo.clone());
+    let (alice, infra, logs) = infra(&service, MAX_PINNED_LOGS + 1).await;
+    for &log in &logs[..MAX_PINNED_LOGS - 1] {
+        service.pin(alice, infra, log).await.unwrap();
+    }
+
+    let (first, second) = (repo.begin().await.unwrap(), repo.begin().await.unwrap());
+    let last = MAX_PINNED_LOGS - 1;
+    for (tx, log) in [(&first, logs[last]), (&second, logs[last + 1])] {
+        let service = LogService::new(tx.clone());
+        service.pin(alice, infra, log).await.unwrap();
+    }
+    first.commit().await.unwrap();
+    assert!(second.commit().await.is_err());
+    assert_eq!(shown(&service, alice, infra).await.0.len(), MAX_PINNED_LOGS);
+}
@@ -0,0 +1,732 @@
+//! Runs against the server given by `REVERIE_TEST_POSTGRES`, e.g.
+//! `postgres://postgres@localhost/postgres`. Each test creates (then drops)
+//! its own database, migrated by the repository. Skipped when the variable is not set.
+#![cfg(feature = "postgres")]
+
+use std::str::FromStr;
+
+use futures_util::TryStreamExt;
+use reverie::{
+    AuditQuery, Date, FieldFilter, FieldKind, LocalLogStoreService, LogFields, LogQuery, LogSearch,
+    LogService, LogServiceError, Origin, Page, PostgresRepo, ProjectName, ProjectSearch, Role,
+    SortOrder, TeamName, TemplateName, Username, ViewName,
+};
+use sqlx::{Connection, Executor};
+
+struct TestDatabase {
+    admin_url: String,
+    name: String,
+    url: String,
+}
+
+impl TestDatabase {
+    async fn create() -> Option<Self> {
+        let admin_url = std::env::var("REVERIE_TEST_POSTGRES").ok()?;
+        let name = format!("reverie_test_{}", uuid::Uuid::now_v7().simple());
+        let mut admin = sqlx::PgConnection::connect(&admin_url).await.unwrap();
+        admin
+            .execute(format!("CREATE DATABASE {name}").as_str())
+            .await
+            .unwrap();
+        let (base, _) = admin_url.rsplit_once('/').unwrap();
+        let url = format!("{base}/{name}");
+        Some(Self {
+            admin_url,
+            name,
+            url,
+        })
+    }
+
+    async fn repo(&self) -> PostgresRepo {
+        PostgresRepo::new(&self.url).await.unwrap()
+    }
+
+    async fn service(&self) -> LogService<PostgresRepo> {
+        LogService::new(self.repo().await)
+    }
+
+    async fn drop(self) {
+        let mut admin = sqlx::PgConnection::connect(&self.admin_url).await.unwrap();
+        admin
+            .execute(format!("DROP DATABASE {} WITH (FORCE)", self.name).as_str())
+            .await
+            .unwrap();
+    }
+}
+
+fn fields(values: &[(&str, &str)]) -> LogFields {
+    values
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn users_projects_and_logs() {
+    let Some(database) = TestDatabase::create().await else {
+        return;
+    };
+    let service = database.service().await;
+
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap();
+    let found = service
+        .get_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.id(), alice.id());
+
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice.id())
+        .await
+        .unwrap();
+    let projects = service
+        .projects_of(alice.id(), alice.id(), Page::default())
+        .await
+        .unwrap();
+    assert_eq!(projects.data.len(), 1);
+    assert_eq!(projects.data[0].id(), infra.id());
+
+    let first = service
+        .add_log(alice.id(), infra.id(), "first".into(), LogFields::new())
+        .await
+        .unwrap();
+    service
+        .add_log(
+            alice.id(),
+            infra.id(),
+            "second".into(),
+            fields(&[("env", "prod")]),
+        )
+        .await
+        .unwrap();
+    service
+        .pin(alice.id(), infra.id(), first.id())
+        .await
+        .unwrap();
+
+    let logs = service
+        .project_logs(alice.id(), LogQuery::project(infra.id()))
+        .await
+        .unwrap();
+    assert_eq!(logs.logs.data.len(), 2);
+    assert_eq!(logs.pinned.len(), 1);
+    assert_eq!(logs.pinned[0].id(), first.id());
+
+    let filtered = service
+        .query_logs(
+            alice.id(),
+            LogQuery::project(infra.id()).field(FieldFilter::from_str("env=prod").unwrap()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(filtered.data.len(), 1);
+    assert_eq!(filtered.data[0].fields().get("env").unwrap(), "prod");
+
+    service
+        .unpin(alice.id(), infra.id(), first.id())
+        .await
+        .unwrap();
+    assert!(service
+        .unpin(alice.id(), infra.id(), first.id())
+        .await
+        .is_err());
+
+    database.drop().await;
+}
+
+#[tokio::test]
+async fn fields_and_templates() {
+    let Some(database) = TestDatabase::create().await else {
+        return;
+    };
+    let service = database.service().await;
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap();
+    let ops = service
+        .new_project(ProjectName::from_str("ops").unwrap(), alice.id())
+        .await
+        .unwrap();
+    service
+        .declare_field(
+            alice.id(),
+            ops.id(),
+            "severity".into(),
+            FieldKind::Number,
+            true,
+        )
+        .await
+        .unwrap();
+    service
+        .declare_field(alice.id(), ops.id(), "owner".into(), FieldKind::User, false)
+        .await
+        .unwrap();
+    assert_eq!(service.fields(alice.id(), ops.id()).await.unwrap().len(), 2);
+
+    let missing = service
+        .add_log(alice.id(), ops.id(), "no severity".into(), LogFields::new())
+        .await;
+    assert!(missing.is_err());
+    for severity in ["2", "10", "not a number"] {
+        let _ = service
+            .add_log(
+                alice.id(),
+                ops.id(),
+                format!("severity {severity}"),
+                fields(&[("severity", severity), ("owner", "alice")]),
+            )
+            .await;
+    }
+    let high = service
+        .query_logs(
+            alice.id(),
+            LogQuery::project(ops.id()).field(FieldFilter::from_str("severity>=3").unwrap()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(high.data.len(), 1);
+    assert_eq!(
+        high.data[0].fields().get("owner").unwrap(),
+        &alice.id().to_string()
+    );
+
+    let standup = TemplateName::from_str("standup").unwrap();
+    service
+        .new_template(
+            standup.clone(),
+            alice.id(),
+            Some(ops.id()),
+            "Today: {{today}}".into(),
+        )
+        .await
+        .unwrap();
+    let template = service
+        .template(standup, alice.id(), Some(ops.id()))
+        .await
+        .unwrap();
+    assert_eq!(template.placeholders(), vec!["today"]);
+
+    database.drop().await;
+}
+
+#[tokio::test]
+async fn tables_are_rebuilt_from_events() {
+    let Some(database) = TestDatabase::create().await else {
+        return;
+    };
+    let repo = database.repo().await;
+    let service = LogService::new(repo.clone());
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap();
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice.id())
+        .await
+        .unwrap();
+    let log = service
+        .add_log(
+            alice.id(),
+            infra.id(),
+            "kept".into(),
+            fields(&[("env", "prod")]),
+        )
+        .await
+        .unwrap();
+    service.pin(alice.id(), infra.id(), log.id()).await.unwrap();
+
+    let mut connection = sqlx::PgConnection::connect(&database.url).await.unwrap();
+    sqlx::raw_sql("DELETE FROM pin; DELETE FROM log_field; DELETE FROM log;")
+        .execute(&mut connection)
+        .await
+        .unwrap();
+    // each change is followed by its audit entry
+    assert_eq!(repo.rebuild().await.unwrap(), 8);
+
+    let logs = service
+        .project_logs(alice.id(), LogQuery::project(infra.id()))
+        .await
+        .unwrap();
+    assert_eq!(logs.pinned.len(), 1);
+    assert_eq!(logs.logs.data.len(), 1);
+    assert_eq!(logs.logs.data[0].fields().get("env").unwrap(), "prod");
+
+    database.drop().await;
+}
+
+#[tokio::test]
+async fn logs_are_streamed_in_batches() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let service = db.service().await;
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap();
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice.id())
+        .await
+        .unwrap();
+    service
+        .declare_field(alice.id(), infra.id(), "n".into(), FieldKind::Number, false)
+        .await
+        .unwrap();
+    for n in 0..300 {
+        let n = n.to_string();
+        service
+            .add_log(alice.id(), infra.id(), n.clone(), fields(&[("n", &n)]))
+            .await
+            .unwrap();
+    }
+    let logs: Vec<_> = service
+        .stream_logs(alice.id(), infra.id())
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(logs.len(), 300);
+    for (n, log) in logs.iter().enumerate() {
+        assert_eq!(log.text(), n.to_string());
+        assert_eq!(log.fields().get("n"), Some(&n.to_string()));
+    }
+    db.drop().await;
+}
+
+#[tokio::test]
+async fn pages_are_counted_in_order() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let service = db.service().await;
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap();
+    for n in 0..4 {
+        let name = ProjectName::from_str(&format!("project-{n}")).unwrap();
+        service.new_project(name, alice.id()).await.unwrap();
+    }
+    let projects = service
+        .projects_of(alice.id(), alice.id(), Page::new(2, 3))
+        .await
+        .unwrap();
+    assert_eq!(projects.data.len(), 1);
+    assert_eq!((projects.total, projects.page_count), (4, 2));
+    assert!(!projects.has_next);
+
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice.id())
+        .await
+        .unwrap();
+    for n in 0..5 {
+        let env = if n % 2 == 0 { "prod" } else { "dev" };
+        service
+            .add_log(
+                alice.id(),
+                infra.id(),
+                n.to_string(),
+                fields(&[("env", env)]),
+            )
+            .await
+            .unwrap();
+    }
+    let prod = service
+        .query_logs(
+            alice.id(),
+            LogQuery::project(infra.id())
+                .field(FieldFilter::from_str("env=prod").unwrap())
+                .page(Page::new(1, 2)),
+        )
+        .await
+        .unwrap();
+    let texts: Vec<_> = prod.data.iter().map(|log| log.text()).collect();
+    assert_eq!(texts, ["0", "2"]);
+    assert_eq!(prod.total, 3);
+    assert!(prod.has_next);
+    let rest = service
+        .query_logs(
+            alice.id(),
+            LogQuery::project(infra.id())
+                .field(FieldFilter::from_str("env=prod").unwrap())
+                .page(Page::after(prod.next.unwrap(), 2)),
+        )
+        .await
+        .unwrap();
+    let texts: Vec<_> = rest.data.iter().map(|log| log.text()).collect();
+    assert_eq!(texts, ["4"]);
+    assert!(!rest.has_next);
+    let newest = service
+        .query_logs(
+            alice.id(),
+            LogQuery::project(infra.id())
+                .by(alice.id())
+                .since(Date::from_str("2000-01-01").unwrap())
+                .sort(SortOrder::Desc)
+                .page(Page::new(1, 2)),
+        )
+        .await
+        .unwrap();
+    let texts: Vec<_> = newest.data.iter().map(|log| log.text()).collect();
+    assert_eq!(texts, ["4", "3"]);
+    assert_eq!(newest.total, 5);
+    db.drop().await;
+}
+
+#[tokio::test]
+async fn failed_work_is_rolled_back() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let service = db.service().await;
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap()
+        .id();
+    let infra = ProjectName::from_str("infra").unwrap();
+    let failed: Result<(), _> = service
+        .atomically(|tx| {
+            let infra = infra.clone();
+            async move {
+                let project = tx.new_project(infra.clone(), alice).await?;
+                tx.add_log(alice, project.id(), "kickoff".into(), fields(&[]))
+                    .await?;
+                assert!(tx.get_project(alice, infra).await?.is_some());
+                Err(LogServiceError::LogNotFound)
+            }
+        })
+        .await;
+    assert!(matches!(failed, Err(LogServiceError::LogNotFound)));
+    assert!(service
+        .get_project(alice, infra.clone())
+        .await
+        .unwrap()
+        .is_none());
+
+    let (project, log) = service
+        .new_project_with_log(infra.clone(), alice, "kickoff".into())
+        .await
+        .unwrap();
+    let logs = service
+        .logs(alice, project.id(), Page::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        logs.data.iter().map(|l| l.id()).collect::<Vec<_>>(),
+        [log.id()]
+    );
+    db.drop().await;
+}
+
+#[tokio::test]
+async fn logs_are_searched_by_relevance() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let service = db.service().await;
+    let mut users = vec![];
+    for name in ["alice", "bobby"] {
+        let user = service.new_user(Username::from_str(name).unwrap()).await;
+        users.push(user.unwrap().id());
+    }
+    let (alice, bob) = (users[0], users[1]);
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice)
+        .await
+        .unwrap();
+    let secret = service
+        .new_project(ProjectName::from_str("secret").unwrap(), bob)
+        .await
+        .unwrap();
+    for text in ["Disks full on the database server", "server server outage"] {
+        service
+            .add_log(alice, infra.id(), text.into(), fields(&[]))
+            .await
+            .unwrap();
+    }
+    service
+        .add_log(bob, secret.id(), "disk full again".into(), fields(&[]))
+        .await
+        .unwrap();
+
+    let hits = service
+        .search_logs(alice, LogSearch::parse("disk full").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(hits.total, 1);
+    assert_eq!(hits.data[0].project, *infra.name());
+    assert!(hits.data[0].snippet.contains("**full**"));
+    let related = service
+        .related_logs(alice, hits.data[0].log.id(), 5)
+        .await
+        .unwrap();
+    let texts: Vec<_> = related.iter().map(|hit| hit.log.text()).collect();
+    assert_eq!(texts, ["server server outage"]);
+    let hits = service
+        .search_logs(alice, LogSearch::parse("server").unwrap())
+        .await
+        .unwrap();
+    let texts: Vec<_> = hits.data.iter().map(|hit| hit.log.text()).collect();
+    assert_eq!(
+        texts,
+        ["server server outage", "Disks full on the database server"]
+    );
+    let query = r#"server -outage author:me "database server""#;
+    let hits = service
+        .search_logs(alice, LogSearch::parse(query).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(hits.total, 1);
+    let hits = service
+        .search_logs(alice, LogSearch::parse("author:me -server").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(hits.total, 0);
+
+    let found = service
+        .search_projects(alice, ProjectSearch::new("infar").owner(alice))
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].activity.project.id(), infra.id());
+    let since = Date::from_str("2200-01-01").unwrap();
+    let found = service
+        .search_projects(alice, ProjectSearch::new("infra").active_since(since))
+        .await
+        .unwrap();
+    assert!(found.is_empty());
+    db.drop().await;
+}
+
+#[tokio::test]
+async fn views_are_saved_again_and_shared() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let service = db.service().await;
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap()
+        .id();
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice)
+        .await
+        .unwrap();
+    let name = ViewName::from_str("incidents").unwrap();
+    let first = service
+        .save_view(alice, name.clone(), "incident".into(), None)
+        .await
+        .unwrap();
+    let again = service
+        .save_view(
+            alice,
+            name.clone(),
+            "incident -down".into(),
+            Some(infra.id()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(first.id(), again.id());
+    let views = service.views(alice).await.unwrap();
+    assert_eq!(views.len(), 1);
+    assert_eq!(views[0].query(), "incident -down");
+    assert_eq!(views[0].project(), Some(infra.id()));
+    db.drop().await;
+}
+
+#[tokio::test]
+async fn members_read_and_write_projects() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let repo = db.repo().await;
+    let service = LogService::new(repo.clone());
+    let mut users = vec![];
+    for name in ["alice", "bobby"] {
+        let user = service.new_user(Username::from_str(name).unwrap()).await;
+        users.push(user.unwrap().id());
+    }
+    let (alice, bob) = (users[0], users[1]);
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice)
+        .await
+        .unwrap()
+        .id();
+    let denied = service.logs(bob, infra, Page::default()).await;
+    assert!(matches!(denied, Err(LogServiceError::NoReadAccess(..))));
+
+    service
+        .add_member(alice, infra, bob, Role::Writer)
+        .await
+        .unwrap();
+    let again = service.add_member(alice, infra, bob, Role::Writer).await;
+    assert!(matches!(again, Err(LogServiceError::Conflict(_))));
+    service
+        .add_log(bob, infra, "disk full".into(), LogFields::new())
+        .await
+        .unwrap();
+    let hits = service
+        .search_logs(alice, LogSearch::parse("author:bobby").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(hits.total, 1);
+
+    service
+        .set_role(alice, infra, bob, Role::Reader)
+        .await
+        .unwrap();
+    let denied = service
+        .add_log(bob, infra, "read only".into(), LogFields::new())
+        .await;
+    assert!(matches!(denied, Err(LogServiceError::NoWriteAccess(..))));
+    let project = service.transfer_project(alice, infra, bob).await.unwrap();
+    assert_eq!(project.owner(), bob);
+
+    repo.rebuild().await.unwrap();
+    let members = service.members(bob, infra).await.unwrap();
+    let roles: Vec<_> = members.iter().map(|m| (m.user.id(), m.role)).collect();
+    assert_eq!(roles, [(bob, Role::Owner), (alice, Role::Maintainer)]);
+    db.drop().await;
+}
+
+#[tokio::test]
+async fn teams_share_their_projects() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let repo = db.repo().await;
+    let service = LogService::new(repo.clone());
+    let mut users = vec![];
+    for name in ["alice", "bobby"] {
+        let user = service.new_user(Username::from_str(name).unwrap()).await;
+        users.push(user.unwrap().id());
+    }
+    let (alice, bob) = (users[0], users[1]);
+    let ops = service
+        .new_team(TeamName::from_str("ops").unwrap(), alice)
+        .await
+        .unwrap()
+        .id();
+    let infra = service
+        .new_team_project(ProjectName::from_str("infra").unwrap(), alice, ops)
+        .await
+        .unwrap()
+        .id();
+    let denied = service.logs(bob, infra, Page::default()).await;
+    assert!(matches!(denied, Err(LogServiceError::NoReadAccess(..))));
+
+    service
+        .add_team_member(alice, ops, bob, Role::Writer)
+        .await
+        .unwrap();
+    service
+        .add_log(bob, infra, "disk full".into(), LogFields::new())
+        .await
+        .unwrap();
+    let hits = service
+        .search_logs(bob, LogSearch::parse("disk").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(hits.total, 1);
+
+    repo.rebuild().await.unwrap();
+    let projects = service.team_projects(bob, ops).await.unwrap();
+    assert_eq!(projects.len(), 1);
+    assert_eq!(projects[0].team(), Some(ops));
+    let teams = service.teams_of(bob).await.unwrap();
+    assert_eq!(teams[0].name().to_string(), "ops");
+    db.drop().await;
+}
+
+#[tokio::test]
+async fn changes_are_audited() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let repo = db.repo().await;
+    let service = LogService::new(repo.clone()).with_origin(Origin::Http);
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap()
+        .id();
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice)
+        .await
+        .unwrap()
+        .id();
+    let log = service
+        .add_log(alice, infra, "disk full".into(), LogFields::new())
+        .await
+        .unwrap();
+    service.pin(alice, infra, log.id()).await.unwrap();
+
+    repo.rebuild().await.unwrap();
+    let all = service.audit_log(AuditQuery::default()).await.unwrap();
+    assert_eq!(all.total, 4);
+    let actions: Vec<_> = all.data.iter().map(|e| e.action.as_str()).collect();
+    assert_eq!(
+        actions,
+        ["log_pinned", "log_added", "project_created", "user_created"]
+    );
+    assert!(all.data.iter().all(|e| e.origin == Origin::Http));
+    let query = AuditQuery::default()
+        .actor(alice)
+        .target(log.id().to_string());
+    let log_changes = service.audit_log(query).await.unwrap();
+    assert_eq!(log_changes.total, 2);
+    assert_eq!(log_changes.data[1].after.as_deref(), Some("disk full"));
+    db.drop().await;
+}
+
+#[tokio::test]
+async fn names_are_unique_once_folded() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let repo = db.repo().await;
+    let service = LogService::new(repo.clone());
+    let alice = service
+        .new_user(Username::from_str("Alice").unwrap())
+        .await
+        .unwrap();
+    let duplicate = service.new_user(Username::from_str("ALICE").unwrap()).await;
+    assert!(matches!(duplicate, Err(LogServiceError::UserExists)));
+    service
+        .new_project(ProjectName::from_str("Infra").unwrap(), alice.id())
+        .await
+        .unwrap();
+
+    repo.rebuild().await.unwrap();
+    let found = service
+        .get_project(alice.id(), ProjectName::from_str("infra").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(found.unwrap().name().to_string(), "Infra");
+    let duplicate = service
+        .new_project(ProjectName::from_str("INFRA").unwrap(), alice.id())
+        .await;
+    assert!(matches!(duplicate, Err(LogServiceError::ProjectExists)));
+    db.drop().await;
+}
+
+#[tokio::test]
+async fn concurrent_connections_migrate_once() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let (first, second) = tokio::join!(PostgresRepo::new(&db.url), PostgresRepo::new(&db.url));
+    let (first, second) = (first.unwrap(), second.unwrap());
+    let status = first.schema_status().await.unwrap();
+    assert_eq!(status.current, Some(status.latest));
+    assert_eq!(second.migrate().await.unwrap(), 0);
+    db.drop().await;
+}
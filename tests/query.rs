@@ -0,0 +1,81 @@
+use std::str::FromStr;
+
+use reverie::{
+    Date, FieldFilter, FieldKind, LocalLogStoreService, LogFields, LogQuery, Page, ProjectName,
+    Role, SortOrder, Username,
+};
+
+#[macro_use]
+mod common;
+
+fn date(date: &str) -> Date {
+    Date::from_str(date).unwrap()
+}
+
+/// Criteria of a query add up, and descending pages follow their cursors
+async fn queries_combine_criteria<S: LocalLogStoreService>(service: S) {
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap()
+        .id();
+    let bob = service
+        .new_user(Username::from_str("bobby").unwrap())
+        .await
+        .unwrap()
+        .id();
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice)
+        .await
+        .unwrap()
+        .id();
+    service
+        .add_member(alice, infra, bob, Role::Writer)
+        .await
+        .unwrap();
+    service
+        .declare_field(alice, infra, "env".into(), FieldKind::String, false)
+        .await
+        .unwrap();
+    for n in 0..6 {
+        let author = if n % 2 == 0 { alice } else { bob };
+        let env = if n < 3 { "prod" } else { "test" };
+        let fields = LogFields::from([("env".to_string(), env.to_string())]);
+        service
+            .add_log(author, infra, format!("log {n}"), fields)
+            .await
+            .unwrap();
+    }
+    let texts = |query: LogQuery| async {
+        let logs = service.query_logs(bob, query).await.unwrap();
+        let texts: Vec<_> = logs.data.iter().map(|log| log.text().to_string()).collect();
+        (texts, logs)
+    };
+
+    let (by_alice, _) = texts(LogQuery::project(infra).by(alice)).await;
+    assert_eq!(by_alice, ["log 0", "log 2", "log 4"]);
+    let (prod_by_bob, page) = texts(
+        LogQuery::project(infra)
+            .by(bob)
+            .field(FieldFilter::from_str("env=prod").unwrap()),
+    )
+    .await;
+    assert_eq!(prod_by_bob, ["log 1"]);
+    assert_eq!(page.total, 1);
+
+    let (since, _) = texts(LogQuery::project(infra).since(date("2000-01-01"))).await;
+    assert_eq!(since.len(), 6);
+    let (until, _) = texts(LogQuery::project(infra).until(date("2000-01-01"))).await;
+    assert!(until.is_empty());
+
+    let newest = LogQuery::project(infra).sort(SortOrder::Desc);
+    let (first, page) = texts(newest.clone().page(Page::new(1, 4))).await;
+    assert_eq!(first, ["log 5", "log 4", "log 3", "log 2"]);
+    let (rest, page) = texts(newest.clone().page(Page::after(page.next.unwrap(), 4))).await;
+    assert_eq!(rest, ["log 1", "log 0"]);
+    assert!(!page.has_next);
+    let (back, _) = texts(newest.page(Page::before(page.prev.unwrap(), 4))).await;
+    assert_eq!(back, first);
+}
+
+on_sqlite_and_memory!(queries_combine_criteria);
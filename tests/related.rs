@@ -0,0 +1,65 @@
+use std::str::FromStr;
+
+use reverie::{LocalLogStoreService, LogFields, LogServiceError, ProjectName, Username};
+
+#[macro_use]
+mod common;
+
+/// Logs sharing rare words are related across the projects of the reader,
+/// the most similar first
+async fn related_logs_share_rare_words<S: LocalLogStoreService>(service: S) {
+    let mut users = vec![];
+    for name in ["alice", "bobby"] {
+        let user = service.new_user(Username::from_str(name).unwrap()).await;
+        users.push(user.unwrap().id());
+    }
+    let (alice, bob) = (users[0], users[1]);
+    let mut projects = vec![];
+    for (name, owner) in [("infra", alice), ("web", alice), ("secret", bob)] {
+        let name = ProjectName::from_str(name).unwrap();
+        projects.push(service.new_project(name, owner).await.unwrap().id());
+    }
+    let (infra, web, secret) = (projects[0], projects[1], projects[2]);
+    let mut ids = vec![];
+    for (author, project, text) in [
+        (
+            alice,
+            infra,
+            "The postgres replica lags behind after the vacuum",
+        ),
+        (alice, web, "Replica lag on postgres again, vacuum running"),
+        (alice, web, "The upload form is slow after the redesign"),
+        (alice, infra, "Postgres upgraded"),
+        (bob, secret, "postgres replica lag vacuum"),
+    ] {
+        let log = service
+            .add_log(author, project, text.into(), LogFields::new())
+            .await
+            .unwrap();
+        ids.push(log.id());
+    }
+
+    let related = service.related_logs(alice, ids[0], 5).await.unwrap();
+    let texts: Vec<_> = related.iter().map(|hit| hit.log.text()).collect();
+    assert_eq!(texts[0], "Replica lag on postgres again, vacuum running");
+    assert!(!texts.contains(&"The upload form is slow after the redesign"));
+    assert!(!texts.contains(&"postgres replica lag vacuum"));
+    assert!(!texts.contains(&"The postgres replica lags behind after the vacuum"));
+    assert_eq!(related[0].project.to_string(), "web");
+    assert!(related[0].snippet.contains("**vacuum**"));
+    assert!(related.windows(2).all(|w| w[0].score >= w[1].score));
+
+    assert_eq!(
+        service.related_logs(alice, ids[0], 1).await.unwrap().len(),
+        1
+    );
+    assert!(service
+        .related_logs(alice, ids[2], 5)
+        .await
+        .unwrap()
+        .is_empty());
+    let unreadable = service.related_logs(alice, ids[4], 5).await;
+    assert!(matches!(unreadable, Err(LogServiceError::LogNotFound)));
+}
+
+on_sqlite_and_memory!(related_logs_share_rare_words);
@@ -0,0 +1,63 @@
+use reverie::SqliteRepo;
+
+mod common;
+
+use common::TempFile;
+
+#[tokio::test]
+async fn fresh_database_is_migrated_on_connect() {
+    let db = TempFile::database();
+    let empty = SqliteRepo::open(db.as_str()).await.unwrap();
+    let status = empty.schema_status().await.unwrap();
+    assert_eq!(status.current, None);
+    assert!(!status.pending.is_empty());
+
+    let repo = db.sqlite().await;
+    let status = repo.schema_status().await.unwrap();
+    assert_eq!(status.current, Some(status.latest));
+    assert!(status.pending.is_empty());
+    assert_eq!(repo.migrate().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn newer_schema_is_refused() {
+    let db = TempFile::database();
+    let repo = db.sqlite().await;
+    let latest = repo.schema_status().await.unwrap().latest;
+    let pool = sqlx::SqlitePool::connect(db.as_str()).await.unwrap();
+    sqlx::query(
+        "INSERT INTO _sqlx_migrations (version,description,success,checksum,execution_time) VALUES (?,'from the future',1,x'00',0)",
+    )
+    .bind(latest + 1)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    assert!(SqliteRepo::new(db.as_str()).await.is_err());
+    assert!(repo.schema_status().await.is_err());
+}
+
+#[tokio::test]
+async fn changed_or_failed_migrations_are_refused() {
+    let db = TempFile::database();
+    db.sqlite().await;
+    let pool = sqlx::SqlitePool::connect(db.as_str()).await.unwrap();
+    let refusal = || async {
+        match SqliteRepo::new(db.as_str()).await {
+            Ok(_) => panic!("migrated"),
+            Err(error) => error.to_string(),
+        }
+    };
+
+    sqlx::query("UPDATE _sqlx_migrations SET checksum = x'00' WHERE version = (SELECT MIN(version) FROM _sqlx_migrations)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert!(refusal().await.contains("was changed after being applied"));
+
+    sqlx::query("UPDATE _sqlx_migrations SET success = 0 WHERE version = (SELECT MAX(version) FROM _sqlx_migrations)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert!(refusal().await.contains("failed halfway"));
+}
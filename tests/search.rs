@@ -0,0 +1,295 @@
+use std::str::FromStr;
+
+use reverie::{
+    Criterion, Date, FieldKind, InMemoryRepo, LocalLogStoreService, LogFields, LogSearch,
+    LogService, LogServiceError, ProjectName, ProjectSearch, Role, SearchQuery, TextFormat, UserId,
+    Username,
+};
+
+#[macro_use]
+mod common;
+
+use common::TempFile;
+
+fn search(query: &str) -> LogSearch {
+    LogSearch::parse(query).unwrap()
+}
+
+/// Texts of the logs found by the user, best first
+async fn found<S: LocalLogStoreService>(
+    service: &S,
+    user: UserId,
+    search: LogSearch,
+) -> Vec<String> {
+    let hits = service.search_logs(user, search).await.unwrap();
+    hits.data
+        .iter()
+        .map(|hit| hit.log.text().to_string())
+        .collect()
+}
+
+/// Searches find the logs holding every word, in the projects of the user
+/// only, best matches first
+async fn searches_rank_readable_logs<S: LocalLogStoreService>(service: S) {
+    let mut users = vec![];
+    for name in ["alice", "bobby"] {
+        let user = service.new_user(Username::from_str(name).unwrap()).await;
+        users.push(user.unwrap().id());
+    }
+    let (alice, bob) = (users[0], users[1]);
+    let logs = [
+        (alice, "infra", "Disk full on the database server"),
+        (
+            alice,
+            "infra",
+            "the server rebooted after a long maintenance window today",
+        ),
+        (alice, "infra", "server server outage"),
+        (alice, "web", "disk quota raised for uploads"),
+        (bob, "secret", "disk full again"),
+        (alice, "web", "**urgent** cleanup of the `backups`"),
+    ];
+    for (owner, project, text) in logs {
+        let name = ProjectName::from_str(project).unwrap();
+        let project = match service.get_project(owner, name.clone()).await.unwrap() {
+            Some(project) => project,
+            None => service.new_project(name, owner).await.unwrap(),
+        };
+        service
+            .add_log(owner, project.id(), text.into(), LogFields::new())
+            .await
+            .unwrap();
+    }
+
+    let disk = found(&service, alice, search("disk")).await;
+    assert_eq!(disk.len(), 2);
+    assert!(!disk.contains(&"disk full again".to_string()));
+    assert_eq!(
+        found(&service, bob, search("disk")).await,
+        ["disk full again"]
+    );
+
+    let hits = service
+        .search_logs(alice, search("disk-full"))
+        .await
+        .unwrap();
+    assert_eq!(hits.total, 1);
+    assert_eq!(hits.data[0].project.to_string(), "infra");
+    assert!(hits.data[0].snippet.contains("full**"));
+
+    // only the matches are bold, whatever the markdown of the text
+    let hits = service.search_logs(alice, search("backups")).await.unwrap();
+    assert!(hits.data[0].snippet.contains("**backups**"));
+    let snippet = hits.data[0].clone().rendered(TextFormat::Ansi).snippet();
+    assert!(snippet.contains("\x1b[1mbackups"));
+    assert!(!snippet.contains("\x1b[1murgent"));
+
+    let server = found(&service, alice, search("server")).await;
+    assert_eq!(server.len(), 3);
+    assert_eq!(server[0], "server server outage");
+
+    let old = search("disk").until(Date::from_str("2000-01-01").unwrap());
+    assert!(found(&service, alice, old).await.is_empty());
+    let recent = search("disk").since(Date::from_str("2000-01-01").unwrap());
+    assert_eq!(found(&service, alice, recent).await.len(), 2);
+    assert!(found(&service, alice, search("...")).await.is_empty());
+}
+
+#[tokio::test]
+async fn sqlite_searches_rank_readable_logs() {
+    let db = TempFile::database();
+    let repo = db.sqlite().await;
+    searches_rank_readable_logs(LogService::new(repo.clone())).await;
+
+    // the index follows the tables when they are rebuilt from the events
+    repo.rebuild().await.unwrap();
+    let service = LogService::new(repo);
+    let alice = service
+        .get_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    let disk = found(&service, alice.id(), search("disk")).await;
+    assert_eq!(disk.len(), 2);
+}
+
+#[tokio::test]
+async fn memory_searches_rank_readable_logs() {
+    searches_rank_readable_logs(LogService::new(InMemoryRepo::new())).await;
+}
+
+/// Terms of the query language narrow the search down, or exclude logs when
+/// negated
+async fn query_terms_narrow_searches<S: LocalLogStoreService>(service: S) {
+    let mut users = vec![];
+    for name in ["alice", "bobby"] {
+        let user = service.new_user(Username::from_str(name).unwrap()).await;
+        users.push(user.unwrap().id());
+    }
+    let (alice, bob) = (users[0], users[1]);
+    let mut projects = vec![];
+    for name in ["infra", "web"] {
+        let name = ProjectName::from_str(name).unwrap();
+        projects.push(service.new_project(name, alice).await.unwrap().id());
+    }
+    let (infra, web) = (projects[0], projects[1]);
+    service
+        .add_member(alice, infra, bob, Role::Writer)
+        .await
+        .unwrap();
+    service
+        .declare_field(alice, infra, "severity".into(), FieldKind::Number, false)
+        .await
+        .unwrap();
+    let logs = [
+        (alice, infra, "disk full on the backup server", "3"),
+        (bob, infra, "full disk cleaned up", "1"),
+        (alice, infra, "server rebooted", "2"),
+        (alice, web, "disk full in the upload cache", ""),
+    ];
+    for (author, project, text, severity) in logs {
+        let mut fields = LogFields::new();
+        if !severity.is_empty() {
+            fields.insert("severity".to_string(), severity.to_string());
+        }
+        service
+            .add_log(author, project, text.into(), fields)
+            .await
+            .unwrap();
+    }
+    let count = |query: &'static str| async {
+        let hits = service.search_logs(alice, search(query)).await.unwrap();
+        hits.total
+    };
+
+    assert_eq!(count("disk").await, 3);
+    assert_eq!(count(r#""disk full""#).await, 2);
+    assert_eq!(count("disk -backup").await, 2);
+    assert_eq!(count(r#"disk -"full in""#).await, 2);
+    assert_eq!(count("disk author:bobby").await, 1);
+    assert_eq!(count("disk -author:me").await, 1);
+    assert_eq!(count("disk project:web").await, 1);
+    assert_eq!(count("disk -project:web").await, 2);
+    assert_eq!(count("severity:>=2").await, 2);
+    assert_eq!(count("disk -severity:3").await, 2);
+    assert_eq!(count("author:alice project:infra").await, 2);
+    assert_eq!(count("server before:2000-01-01").await, 0);
+    assert_eq!(count("server after:2000-01-01").await, 2);
+
+    let unknown = service
+        .search_logs(alice, search("disk author:nobody"))
+        .await;
+    match unknown {
+        Err(LogServiceError::InvalidSearch(e)) => assert_eq!(e.column(), 6),
+        other => panic!("unexpected {other:?}"),
+    }
+}
+
+on_sqlite_and_memory!(query_terms_narrow_searches);
+
+#[test]
+fn queries_are_parsed_into_terms() {
+    let query = SearchQuery::from_str(r#"-disk author:"alice" "full  again" kind:>2"#).unwrap();
+    let terms = &query.terms;
+    assert_eq!(terms.len(), 4);
+    assert!(terms[0].negated);
+    assert!(matches!(&terms[0].criterion, Criterion::Word(word) if word == "disk"));
+    assert!(matches!(&terms[1].criterion, Criterion::Author(name) if name.to_string() == "alice"));
+    assert!(matches!(&terms[2].criterion, Criterion::Phrase(phrase) if phrase == "full  again"));
+    assert_eq!(terms[2].span, 21..34);
+    match &terms[3].criterion {
+        Criterion::Field(filter) => assert!(filter.numeric),
+        other => panic!("unexpected {other:?}"),
+    }
+    assert!(SearchQuery::from_str("").unwrap().terms.is_empty());
+}
+
+#[test]
+fn query_errors_point_at_the_term() {
+    let column = |query: &str| SearchQuery::from_str(query).unwrap_err().column();
+    assert_eq!(column(r#"disk "full"#), 6);
+    assert_eq!(column(r#"disk full"again""#), 10);
+    assert_eq!(column("disk - full"), 6);
+    assert_eq!(column("disk -after:2024-01-01"), 6);
+    assert_eq!(column("disk after:2024-13-01"), 12);
+    assert_eq!(column("disk author:"), 13);
+    assert_eq!(column("disk no.such:field"), 6);
+    assert_eq!(column("é author:x!"), 10);
+
+    let error = SearchQuery::from_str(r#"disk "full"#).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid search at column 6: unterminated quote\n  disk \"full\n       ^"
+    );
+}
+
+/// Names of the projects found by the reader, closest first
+async fn names<S: LocalLogStoreService>(
+    service: &S,
+    reader: UserId,
+    search: ProjectSearch,
+) -> Vec<String> {
+    let found = service.search_projects(reader, search).await.unwrap();
+    found
+        .iter()
+        .map(|m| m.activity.project.name().to_string())
+        .collect()
+}
+
+/// Project names are found by prefix, part or with typos among the projects
+/// of the reader, and filtered on their owner and last activity
+async fn project_names_are_matched<S: LocalLogStoreService>(service: S) {
+    let mut users = vec![];
+    for name in ["alice", "bobby"] {
+        let user = service.new_user(Username::from_str(name).unwrap()).await;
+        users.push(user.unwrap().id());
+    }
+    let (alice, bob) = (users[0], users[1]);
+    for (name, owner) in [
+        ("infrastructure", alice),
+        ("infra-web", bob),
+        ("web-infra", alice),
+        ("marketing", bob),
+    ] {
+        let name = ProjectName::from_str(name).unwrap();
+        let project = service.new_project(name, owner).await.unwrap();
+        if owner == bob {
+            service
+                .add_member(bob, project.id(), alice, Role::Reader)
+                .await
+                .unwrap();
+        }
+    }
+    // the reader only finds the projects they may read
+    let infra = names(&service, bob, ProjectSearch::new("infra")).await;
+    assert_eq!(infra, ["infra-web"]);
+
+    let infra = names(&service, alice, ProjectSearch::new("Infra")).await;
+    assert_eq!(infra[2], "web-infra");
+    assert_eq!(infra.len(), 3);
+    let typo = names(&service, alice, ProjectSearch::new("infar")).await;
+    assert!(typo.contains(&"infrastructure".to_string()));
+    assert!(typo.contains(&"infra-web".to_string()));
+    assert_eq!(
+        names(&service, alice, ProjectSearch::new("marketnig")).await,
+        ["marketing"]
+    );
+    assert!(names(&service, alice, ProjectSearch::new("sales"))
+        .await
+        .is_empty());
+
+    let of_bob = ProjectSearch::new("infra").owner(bob);
+    assert_eq!(names(&service, alice, of_bob).await, ["infra-web"]);
+    let active = ProjectSearch::new("infra").active_since(Date::from_str("2000-01-01").unwrap());
+    assert_eq!(names(&service, alice, active).await.len(), 3);
+    let future = ProjectSearch::new("infra").active_since(Date::from_str("2200-01-01").unwrap());
+    assert!(names(&service, alice, future).await.is_empty());
+    assert_eq!(
+        names(&service, alice, ProjectSearch::new("infra").limit(1))
+            .await
+            .len(),
+        1
+    );
+}
+
+on_sqlite_and_memory!(project_names_are_matched);
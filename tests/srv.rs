@@ -0,0 +1,126 @@
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    process::{Child, Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use assert_cmd::prelude::*;
+
+mod common;
+
+use common::TempFile;
+
+/// The HTTP server running on a free port of the database, killed when
+/// dropped
+struct Server {
+    process: Child,
+    port: u16,
+}
+
+impl Server {
+    fn start(db: &TempFile) -> Self {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let process = Command::cargo_bin("srv")
+            .expect("srv binary not found for test")
+            .env("REVERIE_DB", format!("sqlite://{}", db.as_str()))
+            .env("S0O_BIND_PORT", port.to_string())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let server = Self { process, port };
+        for _ in 0..100 {
+            if TcpStream::connect(("127.0.0.1", port)).is_ok() {
+                return server;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        panic!("the server did not start");
+    }
+
+    /// Status and body of the response to a GET request made by the user,
+    /// if any
+    fn get(&self, path: &str, user: Option<&str>) -> (u16, String) {
+        let mut stream = TcpStream::connect(("127.0.0.1", self.port)).unwrap();
+        let user = user.map(|user| format!("x-user-id: {user}\r\n"));
+        write!(
+            stream,
+            "GET {path} HTTP/1.1\r\nhost: localhost\r\n{}connection: close\r\n\r\n",
+            user.unwrap_or_default()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, body.to_string())
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+#[test]
+fn searches_take_dates_besides_cursors() {
+    let db = TempFile::database();
+    let cli = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("cli").expect("cli binary not found for test");
+        cmd.env("REVERIE_DB", format!("sqlite://{}", db.as_str()))
+            .args(args);
+        cmd
+    };
+    cli(&["new", "user", "alice"]).assert().success();
+    cli(&["new", "project", "infra", "alice"])
+        .assert()
+        .success();
+    cli(&["new", "log", "-a", "alice", "-p", "infra", "disk full"])
+        .assert()
+        .success();
+    let server = Server::start(&db);
+
+    let cursor = uuid::Uuid::now_v7().simple();
+    let (status, body) = server.get(
+        &format!("/search/logs?q=disk&since=2000-01-01&after={cursor}"),
+        Some("alice"),
+    );
+    assert_eq!(status, 200, "{body}");
+    assert!(body.contains("\"total\":1"));
+    let (status, body) = server.get(
+        &format!("/search/logs?q=disk&until=2000-01-01&after={cursor}"),
+        Some("alice"),
+    );
+    assert_eq!(status, 200, "{body}");
+    assert!(body.contains("\"total\":0"));
+    let (status, _) = server.get("/search/logs?q=disk&since=yesterday", Some("alice"));
+    assert_eq!(status, 400);
+}
+
+#[test]
+fn requests_are_made_by_the_user_of_the_header() {
+    let db = TempFile::database();
+    let mut cli = Command::cargo_bin("cli").expect("cli binary not found for test");
+    cli.env("REVERIE_DB", format!("sqlite://{}", db.as_str()))
+        .args(["new", "user", "alice"])
+        .assert()
+        .success();
+    let server = Server::start(&db);
+
+    for path in ["/teams", "/views", "/search/logs?q=disk"] {
+        let (status, _) = server.get(path, None);
+        assert_eq!(status, 401, "{path}");
+        let (status, body) = server.get(path, Some("alice"));
+        assert_eq!(status, 200, "{path}: {body}");
+    }
+    let (status, _) = server.get("/teams", Some("nobody"));
+    assert_eq!(status, 404);
+}
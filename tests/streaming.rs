@@ -0,0 +1,100 @@
+use std::str::FromStr;
+
+use futures_util::TryStreamExt;
+use reverie::{FieldKind, LocalLogStoreService, LogFields, ProjectName, Role, UserId, Username};
+
+#[macro_use]
+mod common;
+
+/// More logs than fetched in a single batch by the SQL adapters
+const LOGS: usize = 600;
+
+/// Adds `LOGS` logs to a new project, every other one with a field, and
+/// checks they are all streamed back in order with their fields
+async fn streams_every_log<S: LocalLogStoreService>(service: S) {
+    let alice = service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap();
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice.id())
+        .await
+        .unwrap();
+    service
+        .declare_field(alice.id(), infra.id(), "n".into(), FieldKind::Number, false)
+        .await
+        .unwrap();
+    for n in 0..LOGS {
+        let fields = if n % 2 == 0 {
+            LogFields::from([("n".to_string(), n.to_string())])
+        } else {
+            LogFields::new()
+        };
+        service
+            .add_log(alice.id(), infra.id(), format!("log {n}"), fields)
+            .await
+            .unwrap();
+    }
+
+    let logs = service.stream_logs(alice.id(), infra.id()).await.unwrap();
+    let logs: Vec<_> = logs.try_collect().await.unwrap();
+    assert_eq!(logs.len(), LOGS);
+    for (n, log) in logs.iter().enumerate() {
+        assert_eq!(log.text(), format!("log {n}"));
+        assert_eq!(
+            log.fields().get("n"),
+            (n % 2 == 0).then(|| n.to_string()).as_ref()
+        );
+    }
+
+    let projects: Vec<_> = service
+        .stream_projects_of(alice.id(), alice.id())
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(projects.len(), 1);
+    assert_eq!(projects[0].id(), infra.id());
+}
+
+/// Names of the projects of the owner streamed to the reader
+async fn streamed<S: LocalLogStoreService>(
+    service: &S,
+    reader: UserId,
+    owner: UserId,
+) -> Vec<String> {
+    let projects = service.stream_projects_of(reader, owner).await.unwrap();
+    let projects: Vec<_> = projects.try_collect().await.unwrap();
+    projects
+        .iter()
+        .map(|project| project.name().to_string())
+        .collect()
+}
+
+/// Only the projects the reader may read are streamed to them
+async fn streams_readable_projects<S: LocalLogStoreService>(service: S) {
+    let mut users = vec![];
+    for name in ["alice", "bobby"] {
+        let user = service.new_user(Username::from_str(name).unwrap()).await;
+        users.push(user.unwrap().id());
+    }
+    let (alice, bob) = (users[0], users[1]);
+    let mut projects = vec![];
+    for name in ["infra", "web"] {
+        let project = service
+            .new_project(ProjectName::from_str(name).unwrap(), alice)
+            .await;
+        projects.push(project.unwrap().id());
+    }
+
+    assert!(streamed(&service, bob, alice).await.is_empty());
+    service
+        .add_member(alice, projects[1], bob, Role::Reader)
+        .await
+        .unwrap();
+    assert_eq!(streamed(&service, bob, alice).await, ["web"]);
+    assert_eq!(streamed(&service, alice, alice).await, ["infra", "web"]);
+}
+
+on_sqlite_and_memory!(streams_every_log, streams_readable_projects);
@@ -0,0 +1,144 @@
+use std::str::FromStr;
+
+use reverie::{
+    InMemoryRepo, LocalLogStoreService, LogFields, LogSearch, LogService, LogServiceError, Page,
+    ProjectName, Role, TeamName, Username,
+};
+
+mod common;
+
+use common::TempFile;
+
+/// Members of a team get their role in the team on each of its projects,
+/// unless a project gives them another one
+async fn team_roles_are_inherited_by_projects<S: LocalLogStoreService>(service: S) {
+    let mut users = vec![];
+    for name in ["alice", "bobby", "carol", "david"] {
+        let user = service.new_user(Username::from_str(name).unwrap()).await;
+        users.push(user.unwrap().id());
+    }
+    let (alice, bob, carol, dave) = (users[0], users[1], users[2], users[3]);
+    let ops = service
+        .new_team(TeamName::from_str("ops").unwrap(), alice)
+        .await
+        .unwrap()
+        .id();
+    assert!(matches!(
+        service
+            .new_team(TeamName::from_str("ops").unwrap(), bob)
+            .await,
+        Err(LogServiceError::TeamExists)
+    ));
+    service
+        .add_team_member(alice, ops, bob, Role::Reader)
+        .await
+        .unwrap();
+    service
+        .add_team_member(alice, ops, carol, Role::Maintainer)
+        .await
+        .unwrap();
+    assert!(matches!(
+        service.add_team_member(bob, ops, dave, Role::Writer).await,
+        Err(LogServiceError::NoTeamAccess(..))
+    ));
+    assert!(matches!(
+        service.add_team_member(alice, ops, dave, Role::Owner).await,
+        Err(LogServiceError::InvalidRole(_))
+    ));
+
+    // maintainers of the team create its projects
+    let name = |name: &str| ProjectName::from_str(name).unwrap();
+    assert!(matches!(
+        service.new_team_project(name("infra"), bob, ops).await,
+        Err(LogServiceError::NoTeamAccess(..))
+    ));
+    let infra = service
+        .new_team_project(name("infra"), carol, ops)
+        .await
+        .unwrap();
+    assert_eq!(infra.team(), Some(ops));
+    let infra = infra.id();
+    service.new_project(name("personal"), alice).await.unwrap();
+
+    // bob reads the projects of the team, without writing them
+    service.logs(bob, infra, Page::default()).await.unwrap();
+    assert!(matches!(
+        service
+            .add_log(bob, infra, "disk full".into(), LogFields::new())
+            .await,
+        Err(LogServiceError::NoWriteAccess(..))
+    ));
+    // the team owner maintains them
+    service
+        .add_log(alice, infra, "disk full".into(), LogFields::new())
+        .await
+        .unwrap();
+    assert!(matches!(
+        service.transfer_project(alice, infra, alice).await,
+        Err(LogServiceError::NoWriteAccess(..))
+    ));
+    // a role on the project comes before the role in the team
+    service
+        .add_member(carol, infra, bob, Role::Writer)
+        .await
+        .unwrap();
+    service
+        .add_log(bob, infra, "cleaned up".into(), LogFields::new())
+        .await
+        .unwrap();
+    let search = LogSearch::parse("disk").unwrap();
+    let hits = service.search_logs(bob, search.clone()).await.unwrap();
+    assert_eq!(hits.total, 1);
+
+    // others do not see the team
+    assert!(matches!(
+        service.logs(dave, infra, Page::default()).await,
+        Err(LogServiceError::NoReadAccess(..))
+    ));
+    assert!(matches!(
+        service.team_projects(dave, ops).await,
+        Err(LogServiceError::NoTeamAccess(..))
+    ));
+    assert_eq!(service.search_logs(dave, search).await.unwrap().total, 0);
+    assert!(service.teams_of(dave).await.unwrap().is_empty());
+
+    let projects = service.team_projects(bob, ops).await.unwrap();
+    let names: Vec<_> = projects.iter().map(|p| p.name().to_string()).collect();
+    assert_eq!(names, ["infra"]);
+    let members = service.team_members(bob, ops).await.unwrap();
+    let roles: Vec<_> = members.iter().map(|m| m.to_string()).collect();
+    assert_eq!(roles.len(), 3);
+    assert!(roles[0].ends_with(" owner"));
+    assert_eq!(service.teams_of(carol).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn sqlite_team_roles_are_inherited_by_projects() {
+    let db = TempFile::database();
+    let repo = db.sqlite().await;
+    team_roles_are_inherited_by_projects(LogService::new(repo.clone())).await;
+
+    // teams follow the tables when they are rebuilt
+    repo.rebuild().await.unwrap();
+    let service = LogService::new(repo);
+    let bob = service
+        .get_user(Username::from_str("bobby").unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    let ops = service
+        .get_team(TeamName::from_str("ops").unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    let projects = service.team_projects(bob.id(), ops.id()).await.unwrap();
+    assert_eq!(projects.len(), 1);
+    assert_eq!(projects[0].team(), Some(ops.id()));
+    let members = service.team_members(bob.id(), ops.id()).await.unwrap();
+    assert_eq!(members.len(), 3);
+}
+
+#[tokio::test]
+async fn memory_team_roles_are_inherited_by_projects() {
+    team_roles_are_inherited_by_projects(LogService::new(InMemoryRepo::new())).await;
+}
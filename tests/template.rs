@@ -0,0 +1,35 @@
+use std::str::FromStr;
+
+use reverie::{LogFields, Template, TemplateName, UserId};
+
+fn standup() -> Template {
+    Template::new(
+        TemplateName::from_str("standup").unwrap(),
+        UserId::default(),
+        None,
+        "Yesterday: {{yesterday}}\nToday: {{ today }}\nBlockers: {{blockers}}\n".to_string(),
+    )
+}
+
+#[test]
+fn prefill_keeps_missing_placeholders() {
+    let template = standup();
+    let fields = LogFields::from([("today".to_string(), "deploy".to_string())]);
+    assert_eq!(template.missing(&fields), vec!["yesterday", "blockers"]);
+    assert_eq!(
+        template.prefill(&fields),
+        "Yesterday: {{yesterday}}\nToday: deploy\nBlockers: {{blockers}}\n"
+    );
+}
+
+#[test]
+fn extract_reads_back_edited_fields() {
+    let template = standup();
+    let fields = template
+        .extract("Yesterday: review\nToday: deploy\nBlockers: {{blockers}}\n\n")
+        .unwrap();
+    assert_eq!(fields.get("yesterday").unwrap(), "review");
+    assert_eq!(fields.get("today").unwrap(), "deploy");
+    assert!(!fields.contains_key("blockers"));
+    assert!(template.extract("Something else entirely").is_none());
+}
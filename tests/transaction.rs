@@ -0,0 +1,130 @@
+use std::str::FromStr;
+
+#[cfg(feature = "journal")]
+use reverie::JournalRepo;
+use reverie::{
+    InMemoryRepo, LocalLogStoreService, LogFields, LogService, LogServiceError, Page, ProjectName,
+    UnitOfWork, UserId, Username,
+};
+
+mod common;
+
+use common::TempFile;
+
+fn name(name: &str) -> ProjectName {
+    ProjectName::from_str(name).unwrap()
+}
+
+async fn alice<S: LocalLogStoreService>(service: &S) -> UserId {
+    service
+        .new_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap()
+        .id()
+}
+
+/// Logs of the project as read by alice, or `None` if it does not exist
+async fn logs<S: LocalLogStoreService>(service: &S, project: &str) -> Option<Vec<String>> {
+    let alice = Username::from_str("alice").unwrap();
+    let alice = service.get_user(alice).await.unwrap()?.id();
+    let project = service.get_project(alice, name(project)).await.unwrap()?;
+    let logs = service
+        .logs(alice, project.id(), Page::default())
+        .await
+        .unwrap();
+    Some(logs.data.iter().map(|log| log.text().to_string()).collect())
+}
+
+#[tokio::test]
+async fn sqlite_commits_or_rolls_back_everything() {
+    let db = TempFile::database();
+    let service = LogService::new(db.sqlite().await);
+    let alice = alice(&service).await;
+
+    service
+        .new_project_with_log(name("infra"), alice, "kickoff".into())
+        .await
+        .unwrap();
+    assert_eq!(logs(&service, "infra").await, Some(vec!["kickoff".into()]));
+
+    let failed: Result<(), _> = service
+        .atomically(|tx| async move {
+            let project = tx.new_project(name("web"), alice).await?;
+            tx.add_log(alice, project.id(), "kickoff".into(), LogFields::new())
+                .await?;
+            assert_eq!(logs(&tx, "web").await, Some(vec!["kickoff".into()]));
+            Err(LogServiceError::LogNotFound)
+        })
+        .await;
+    assert!(matches!(failed, Err(LogServiceError::LogNotFound)));
+    assert_eq!(logs(&service, "web").await, None);
+}
+
+#[tokio::test]
+async fn memory_commits_or_rolls_back_everything() {
+    let service = LogService::new(InMemoryRepo::new());
+    let alice = alice(&service).await;
+
+    service
+        .new_project_with_log(name("infra"), alice, "kickoff".into())
+        .await
+        .unwrap();
+    assert_eq!(logs(&service, "infra").await, Some(vec!["kickoff".into()]));
+
+    let failed: Result<(), _> = service
+        .atomically(|tx| async move {
+            tx.new_project(name("web"), alice).await?;
+            assert!(logs(&tx, "web").await.is_some());
+            Err(LogServiceError::LogNotFound)
+        })
+        .await;
+    assert!(matches!(failed, Err(LogServiceError::LogNotFound)));
+    assert_eq!(logs(&service, "web").await, None);
+}
+
+#[tokio::test]
+async fn changes_are_hidden_until_committed() {
+    let repo = InMemoryRepo::new();
+    let service = LogService::new(repo.clone());
+    let alice = alice(&service).await;
+
+    let first = repo.begin().await.unwrap();
+    let second = repo.begin().await.unwrap();
+    LogService::new(first.clone())
+        .new_project(name("infra"), alice)
+        .await
+        .unwrap();
+    LogService::new(second.clone())
+        .new_project(name("infra"), alice)
+        .await
+        .unwrap();
+    assert_eq!(logs(&service, "infra").await, None);
+
+    first.commit().await.unwrap();
+    assert_eq!(logs(&service, "infra").await, Some(vec![]));
+    // checked again on commit: the name was taken in the meantime
+    assert!(second.commit().await.is_err());
+}
+
+#[cfg(feature = "journal")]
+#[tokio::test]
+async fn journal_persists_committed_transactions_only() {
+    let journal = TempFile::new("journal");
+    {
+        let service = LogService::new(JournalRepo::open(journal.path()).unwrap());
+        let alice = alice(&service).await;
+        service
+            .new_project_with_log(name("infra"), alice, "kickoff".into())
+            .await
+            .unwrap();
+        let _: Result<(), _> = service
+            .atomically(|tx| async move {
+                tx.new_project(name("web"), alice).await?;
+                Err(LogServiceError::LogNotFound)
+            })
+            .await;
+    }
+    let service = LogService::new(JournalRepo::open(journal.path()).unwrap());
+    assert_eq!(logs(&service, "infra").await, Some(vec!["kickoff".into()]));
+    assert_eq!(logs(&service, "web").await, None);
+}
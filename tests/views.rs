@@ -0,0 +1,136 @@
+use std::str::FromStr;
+
+use reverie::{
+    InMemoryRepo, LocalLogStoreService, LogFields, LogService, LogServiceError, ProjectName,
+    UserId, Username, ViewName,
+};
+
+mod common;
+
+use common::TempFile;
+
+fn name(name: &str) -> ViewName {
+    ViewName::from_str(name).unwrap()
+}
+
+/// Texts of the logs found by the view run by the user
+async fn run<S: LocalLogStoreService>(service: &S, view: &str, user: UserId) -> Vec<String> {
+    let view = service.view(name(view), user).await.unwrap();
+    let hits = service
+        .search_logs(user, view.search().unwrap())
+        .await
+        .unwrap();
+    hits.data
+        .iter()
+        .map(|hit| hit.log.text().to_string())
+        .collect()
+}
+
+/// Views are saved per user, replaced when saved again, and seen by the
+/// readers of the project they are shared with
+async fn views_are_saved_and_shared<S: LocalLogStoreService>(service: S) {
+    let mut users = vec![];
+    for name in ["alice", "bobby"] {
+        let user = service.new_user(Username::from_str(name).unwrap()).await;
+        users.push(user.unwrap().id());
+    }
+    let (alice, bob) = (users[0], users[1]);
+    let infra = service
+        .new_project(ProjectName::from_str("infra").unwrap(), alice)
+        .await
+        .unwrap()
+        .id();
+    let web = service
+        .new_project(ProjectName::from_str("web").unwrap(), bob)
+        .await
+        .unwrap()
+        .id();
+    for (author, project, text) in [
+        (alice, infra, "disk full on the backup server"),
+        (alice, infra, "incident: server down"),
+        (bob, web, "incident: certificate expired"),
+    ] {
+        service
+            .add_log(author, project, text.into(), LogFields::new())
+            .await
+            .unwrap();
+    }
+
+    service
+        .save_view(alice, name("incidents"), "incident".into(), None)
+        .await
+        .unwrap();
+    assert_eq!(
+        run(&service, "incidents", alice).await,
+        ["incident: server down"]
+    );
+    let saved = service
+        .save_view(alice, name("incidents"), "incident -down".into(), None)
+        .await
+        .unwrap();
+    assert!(run(&service, "incidents", alice).await.is_empty());
+    let views = service.views(alice).await.unwrap();
+    assert_eq!(views.len(), 1);
+    assert_eq!(views[0].id(), saved.id());
+    assert_eq!(views[0].query(), "incident -down");
+
+    // personal views are not seen by others, and runs search the projects
+    // of the user running them, `me` standing for them
+    let missing = service.view(name("incidents"), bob).await;
+    assert!(matches!(missing, Err(LogServiceError::ViewNotFound)));
+    service
+        .save_view(bob, name("mine"), "incident author:me".into(), Some(web))
+        .await
+        .unwrap();
+    assert_eq!(
+        run(&service, "mine", bob).await,
+        ["incident: certificate expired"]
+    );
+    let refused = service
+        .save_view(bob, name("infra"), "disk".into(), Some(infra))
+        .await;
+    assert!(matches!(refused, Err(LogServiceError::NoReadAccess(..))));
+    let invalid = service
+        .save_view(alice, name("broken"), "disk \"full".into(), None)
+        .await;
+    assert!(matches!(invalid, Err(LogServiceError::InvalidSearch(_))));
+
+    service
+        .save_view(alice, name("disks"), "disk".into(), Some(infra))
+        .await
+        .unwrap();
+    let names = |views: Vec<reverie::View>| {
+        views
+            .iter()
+            .map(|view| view.name().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        names(service.views(alice).await.unwrap()),
+        ["disks", "incidents"]
+    );
+    assert_eq!(names(service.views(bob).await.unwrap()), ["mine"]);
+}
+
+#[tokio::test]
+async fn sqlite_views_are_saved_and_shared() {
+    let db = TempFile::database();
+    let repo = db.sqlite().await;
+    views_are_saved_and_shared(LogService::new(repo.clone())).await;
+
+    repo.rebuild().await.unwrap();
+    let service = LogService::new(repo);
+    let alice = service
+        .get_user(Username::from_str("alice").unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    let views = service.views(alice.id()).await.unwrap();
+    assert_eq!(views.len(), 2);
+    assert_eq!(views[1].query(), "incident -down");
+}
+
+#[tokio::test]
+async fn memory_views_are_saved_and_shared() {
+    views_are_saved_and_shared(LogService::new(InMemoryRepo::new())).await;
+}